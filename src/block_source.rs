@@ -0,0 +1,8 @@
+use clap::ValueEnum;
+
+#[derive(Default, ValueEnum, Copy, Clone, Debug, PartialEq)]
+pub(crate) enum BlockSource {
+  #[default]
+  Rpc,
+  Files,
+}
@@ -47,6 +47,15 @@ impl Chain {
     }
   }
 
+  pub(crate) fn explorer(self) -> &'static str {
+    match self {
+      Self::Mainnet => "https://ordinals.com",
+      Self::Regtest => "http://localhost",
+      Self::Signet => "https://signet.ordinals.com",
+      Self::Testnet => "https://testnet.ordinals.com",
+    }
+  }
+
   pub(crate) fn genesis_block(self) -> Block {
     bitcoin::blockdata::constants::genesis_block(self.network())
   }
@@ -1,29 +1,37 @@
 use {
   self::{
+    block_files::BlockFiles,
     entry::{
       BlockHashValue, Entry, InscriptionEntry, InscriptionEntryValue, InscriptionIdValue,
-      OutPointValue, SatPointValue, SatRange,
+      OutPointValue, SatPointValue, SatRange, TransferEntry, TransferEntryValue,
     },
+    reorg::Reorg,
     updater::Updater,
   },
   super::*,
   crate::wallet::Wallet,
-  bitcoin::BlockHeader,
+  bitcoin::{hashes::sha256, BlockHeader},
   bitcoincore_rpc::{json::GetBlockHeaderResult, Auth, Client},
   chrono::SubsecRound,
   indicatif::{ProgressBar, ProgressStyle},
   log::log_enabled,
-  redb::{Database, ReadableTable, Table, TableDefinition, WriteStrategy, WriteTransaction},
-  std::collections::HashMap,
+  redb::{
+    Database, MultimapTable, MultimapTableDefinition, ReadableMultimapTable, ReadableTable, Table,
+    TableDefinition, WriteStrategy, WriteTransaction,
+  },
+  std::collections::{BTreeSet, HashMap},
   std::sync::atomic::{self, AtomicBool},
 };
 
-mod entry;
+mod block_files;
+pub(crate) mod entry;
 mod fetcher;
+mod reorg;
 mod rtx;
+mod text;
 mod updater;
 
-const SCHEMA_VERSION: u64 = 3;
+pub(crate) const SCHEMA_VERSION: u64 = 6;
 
 macro_rules! define_table {
   ($name:ident, $key:ty, $value:ty) => {
@@ -31,20 +39,38 @@ macro_rules! define_table {
   };
 }
 
+macro_rules! define_multimap_table {
+  ($name:ident, $key:ty, $value:ty) => {
+    const $name: MultimapTableDefinition<$key, $value> =
+      MultimapTableDefinition::new(stringify!($name));
+  };
+}
+
+define_multimap_table! { CONTENT_HASH_TO_INSCRIPTION_NUMBERS, &[u8; 32], u64 }
 define_table! { HEIGHT_TO_BLOCK_HASH, u64, &BlockHashValue }
+define_table! { HEIGHT_TO_UNDO_LOG, u64, &[u8] }
+define_table! { INSCRIPTION_ID_TO_BODY, &InscriptionIdValue, &[u8] }
+define_table! { INSCRIPTION_ID_TO_CONTENT_TYPE, &InscriptionIdValue, &[u8] }
 define_table! { INSCRIPTION_ID_TO_INSCRIPTION_ENTRY, &InscriptionIdValue, InscriptionEntryValue }
 define_table! { INSCRIPTION_ID_TO_SATPOINT, &InscriptionIdValue, &SatPointValue }
+define_multimap_table! { INSCRIPTION_ID_TO_TRANSFER_NUMBER, &InscriptionIdValue, u64 }
 define_table! { INSCRIPTION_NUMBER_TO_INSCRIPTION_ID, u64, &InscriptionIdValue }
+define_table! { INSCRIPTION_NUMBER_TO_TERM_COUNT, u64, u32 }
 define_table! { OUTPOINT_TO_SAT_RANGES, &OutPointValue, &[u8] }
+define_table! { OUTPOINT_TO_SCRIPT_PUBKEY, &OutPointValue, &[u8] }
 define_table! { OUTPOINT_TO_VALUE, &OutPointValue, u64}
 define_table! { SATPOINT_TO_INSCRIPTION_ID, &SatPointValue, &InscriptionIdValue }
 define_table! { SAT_TO_INSCRIPTION_ID, u64, &InscriptionIdValue }
 define_table! { SAT_TO_SATPOINT, u64, &SatPointValue }
+define_multimap_table! { SCRIPT_PUBKEY_TO_OUTPOINTS, &[u8], &OutPointValue }
 define_table! { STATISTIC_TO_COUNT, u64, u64 }
+define_multimap_table! { TERM_TO_INSCRIPTION_NUMBER_AND_FREQUENCY, &str, (u64, u32) }
+define_table! { TRANSFER_NUMBER_TO_TRANSFER_ENTRY, u64, &TransferEntryValue }
 define_table! { WRITE_TRANSACTION_STARTING_BLOCK_COUNT_TO_TIMESTAMP, u64, u128 }
 
 pub(crate) struct Index {
   auth: Auth,
+  block_files: Option<Arc<Mutex<BlockFiles>>>,
   client: Client,
   database: Database,
   path: PathBuf,
@@ -70,6 +96,9 @@ pub(crate) enum Statistic {
   LostSats = 2,
   OutputsTraversed = 3,
   SatRanges = 4,
+  TextInscriptions = 5,
+  TextTerms = 6,
+  Reorgs = 7,
 }
 
 impl Statistic {
@@ -95,6 +124,7 @@ pub(crate) struct Info {
   pub(crate) metadata_bytes: usize,
   pub(crate) outputs_traversed: u64,
   pub(crate) page_size: usize,
+  pub(crate) reorgs: u64,
   pub(crate) sat_ranges: u64,
   pub(crate) stored_bytes: usize,
   pub(crate) transactions: Vec<TransactionInfo>,
@@ -102,6 +132,19 @@ pub(crate) struct Info {
   pub(crate) utxos_indexed: usize,
 }
 
+#[derive(Debug, PartialEq)]
+pub(crate) struct TextMatch {
+  pub(crate) inscription_id: InscriptionId,
+  pub(crate) number: u64,
+  pub(crate) score: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct TextSearch {
+  pub(crate) matches: Vec<TextMatch>,
+  pub(crate) total: usize,
+}
+
 #[derive(Serialize)]
 pub(crate) struct TransactionInfo {
   pub(crate) starting_block_count: u64,
@@ -143,7 +186,7 @@ impl Index {
 
     let auth = Auth::CookieFile(cookie_file);
 
-    let client = Client::new(&rpc_url, auth.clone()).context("failed to connect to RPC URL")?;
+    let client = Client::new(&rpc_url, auth.clone()).context("failed to connect to RPC URL");
 
     let data_dir = options.data_dir()?;
 
@@ -202,19 +245,38 @@ impl Index {
           tx
         };
 
+        tx.open_multimap_table(CONTENT_HASH_TO_INSCRIPTION_NUMBERS)?;
         tx.open_table(HEIGHT_TO_BLOCK_HASH)?;
+        tx.open_table(HEIGHT_TO_UNDO_LOG)?;
         tx.open_table(INSCRIPTION_ID_TO_INSCRIPTION_ENTRY)?;
         tx.open_table(INSCRIPTION_ID_TO_SATPOINT)?;
+        tx.open_multimap_table(INSCRIPTION_ID_TO_TRANSFER_NUMBER)?;
         tx.open_table(INSCRIPTION_NUMBER_TO_INSCRIPTION_ID)?;
         tx.open_table(OUTPOINT_TO_VALUE)?;
         tx.open_table(SATPOINT_TO_INSCRIPTION_ID)?;
         tx.open_table(SAT_TO_INSCRIPTION_ID)?;
         tx.open_table(SAT_TO_SATPOINT)?;
+        tx.open_table(TRANSFER_NUMBER_TO_TRANSFER_ENTRY)?;
         tx.open_table(WRITE_TRANSACTION_STARTING_BLOCK_COUNT_TO_TIMESTAMP)?;
 
         tx.open_table(STATISTIC_TO_COUNT)?
           .insert(&Statistic::Schema.key(), &SCHEMA_VERSION)?;
 
+        if options.index_addresses {
+          tx.open_table(OUTPOINT_TO_SCRIPT_PUBKEY)?;
+          tx.open_multimap_table(SCRIPT_PUBKEY_TO_OUTPOINTS)?;
+        }
+
+        if options.index_content {
+          tx.open_table(INSCRIPTION_ID_TO_BODY)?;
+          tx.open_table(INSCRIPTION_ID_TO_CONTENT_TYPE)?;
+        }
+
+        if options.index_text {
+          tx.open_table(INSCRIPTION_NUMBER_TO_TERM_COUNT)?;
+          tx.open_multimap_table(TERM_TO_INSCRIPTION_NUMBER_AND_FREQUENCY)?;
+        }
+
         if options.index_sats {
           tx.open_table(OUTPOINT_TO_SAT_RANGES)?
             .insert(&OutPoint::null().store(), [].as_slice())?;
@@ -227,12 +289,40 @@ impl Index {
       Err(error) => return Err(error.into()),
     };
 
+    // An index read from block files can be updated, and one with content can
+    // serve inscriptions, while Bitcoin Core is down, in which case its cookie
+    // file is gone and every RPC call will fail.
+    let client = match client {
+      Ok(client) => client,
+      Err(err) if options.block_source == BlockSource::Files => {
+        log::warn!("{err:#}, reading blocks from block files");
+        Client::new(&rpc_url, Auth::None)?
+      }
+      Err(err) => match database.begin_read()?.open_table(INSCRIPTION_ID_TO_BODY) {
+        Ok(_) => {
+          log::warn!("{err:#}, only indexed content is available");
+          Client::new(&rpc_url, Auth::None)?
+        }
+        Err(redb::Error::TableDoesNotExist(_)) => return Err(err),
+        Err(table_err) => return Err(table_err.into()),
+      },
+    };
+
+    let block_files = match options.block_source {
+      BlockSource::Rpc => None,
+      BlockSource::Files => Some(Arc::new(Mutex::new(BlockFiles::new(
+        options.blocks_dir()?,
+        options.chain(),
+      )))),
+    };
+
     let genesis_block_coinbase_transaction =
       options.chain().genesis_block().coinbase().unwrap().clone();
 
     Ok(Self {
       genesis_block_coinbase_txid: genesis_block_coinbase_transaction.txid(),
       auth,
+      block_files,
       client,
       database,
       path,
@@ -302,6 +392,22 @@ impl Index {
       .collect()
   }
 
+  pub(crate) fn has_address_index(&self) -> Result<bool> {
+    match self.begin_read()?.0.open_table(OUTPOINT_TO_SCRIPT_PUBKEY) {
+      Ok(_) => Ok(true),
+      Err(redb::Error::TableDoesNotExist(_)) => Ok(false),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  pub(crate) fn has_content_index(&self) -> Result<bool> {
+    match self.begin_read()?.0.open_table(INSCRIPTION_ID_TO_BODY) {
+      Ok(_) => Ok(true),
+      Err(redb::Error::TableDoesNotExist(_)) => Ok(false),
+      Err(err) => Err(err.into()),
+    }
+  }
+
   pub(crate) fn has_sat_index(&self) -> Result<bool> {
     match self.begin_read()?.0.open_table(OUTPOINT_TO_SAT_RANGES) {
       Ok(_) => Ok(true),
@@ -310,6 +416,26 @@ impl Index {
     }
   }
 
+  pub(crate) fn has_text_index(&self) -> Result<bool> {
+    match self
+      .begin_read()?
+      .0
+      .open_table(INSCRIPTION_NUMBER_TO_TERM_COUNT)
+    {
+      Ok(_) => Ok(true),
+      Err(redb::Error::TableDoesNotExist(_)) => Ok(false),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  fn require_address_index(&self, feature: &str) -> Result {
+    if !self.has_address_index()? {
+      bail!("{feature} requires index created with `--index-addresses` flag")
+    }
+
+    Ok(())
+  }
+
   fn require_sat_index(&self, feature: &str) -> Result {
     if !self.has_sat_index()? {
       bail!("{feature} requires index created with `--index-sats` flag")
@@ -333,6 +459,10 @@ impl Index {
         .get(&Statistic::OutputsTraversed.key())?
         .map(|x| x.value())
         .unwrap_or(0);
+      let reorgs = statistic_to_count
+        .get(&Statistic::Reorgs.key())?
+        .map(|x| x.value())
+        .unwrap_or(0);
       Info {
         index_path: self.path.clone(),
         blocks_indexed: wtx
@@ -350,6 +480,7 @@ impl Index {
         sat_ranges,
         outputs_traversed,
         page_size: stats.page_size(),
+        reorgs,
         stored_bytes: stats.stored_bytes(),
         transactions: wtx
           .open_table(WRITE_TRANSACTION_STARTING_BLOCK_COUNT_TO_TIMESTAMP)?
@@ -370,7 +501,15 @@ impl Index {
   }
 
   pub(crate) fn update(&self) -> Result {
-    Updater::update(self)
+    loop {
+      match Updater::update(self) {
+        Ok(()) => return Ok(()),
+        Err(err) => match err.downcast_ref::<Reorg>() {
+          Some(reorg) => reorg.rollback(self)?,
+          None => return Err(err),
+        },
+      }
+    }
   }
 
   pub(crate) fn is_reorged(&self) -> bool {
@@ -424,6 +563,22 @@ impl Index {
     self.begin_read()?.block_count()
   }
 
+  pub(crate) fn block_hash(&self, height: Option<u64>) -> Result<Option<BlockHash>> {
+    let rtx = self.begin_read()?;
+
+    let Some(height) = height.or(rtx.height()?.map(|height| height.n())) else {
+      return Ok(None);
+    };
+
+    let blockhash = rtx
+      .0
+      .open_table(HEIGHT_TO_BLOCK_HASH)?
+      .get(&height)?
+      .map(|hash| Entry::load(*hash.value()));
+
+    Ok(blockhash)
+  }
+
   pub(crate) fn blocks(&self, take: usize) -> Result<Vec<(u64, BlockHash)>> {
     let mut blocks = Vec::new();
 
@@ -539,9 +694,9 @@ impl Index {
     &self,
     inscription_id: InscriptionId,
   ) -> Result<Option<Inscription>> {
-    if self
-      .database
-      .begin_read()?
+    let rtx = self.database.begin_read()?;
+
+    if rtx
       .open_table(INSCRIPTION_ID_TO_SATPOINT)?
       .get(&inscription_id.store())?
       .is_none()
@@ -549,6 +704,23 @@ impl Index {
       return Ok(None);
     }
 
+    match rtx.open_table(INSCRIPTION_ID_TO_BODY) {
+      Ok(inscription_id_to_body) => {
+        let content_type = rtx
+          .open_table(INSCRIPTION_ID_TO_CONTENT_TYPE)?
+          .get(&inscription_id.store())?
+          .map(|content_type| content_type.value().to_vec());
+
+        let body = inscription_id_to_body
+          .get(&inscription_id.store())?
+          .map(|body| body.value().to_vec());
+
+        return Ok(Some(Inscription::new(content_type, body)));
+      }
+      Err(redb::Error::TableDoesNotExist(_)) => {}
+      Err(err) => return Err(err.into()),
+    }
+
     Ok(
       self
         .get_transaction(inscription_id.txid)?
@@ -574,6 +746,52 @@ impl Index {
     )
   }
 
+  /// Unspent outputs locked to `script_pubkey` and their values, in outpoint
+  /// order.
+  pub(crate) fn get_outputs_by_script_pubkey(
+    &self,
+    script_pubkey: &Script,
+  ) -> Result<Vec<(OutPoint, u64)>> {
+    self.require_address_index("address lookup")?;
+
+    let rtx = self.database.begin_read()?;
+
+    let outpoint_to_value = rtx.open_table(OUTPOINT_TO_VALUE)?;
+
+    let outputs = rtx
+      .open_multimap_table(SCRIPT_PUBKEY_TO_OUTPOINTS)?
+      .get(script_pubkey.as_bytes())?
+      .map(|outpoint| {
+        let outpoint = OutPoint::load(*outpoint.value());
+        let value = outpoint_to_value
+          .get(&outpoint.store())?
+          .ok_or_else(|| anyhow!("output {outpoint} has no value in index"))?
+          .value();
+        Ok((outpoint, value))
+      })
+      .collect();
+
+    outputs
+  }
+
+  /// The script pubkey of unspent output `outpoint`, if the index tracks
+  /// addresses.
+  pub(crate) fn get_output_script_pubkey(&self, outpoint: OutPoint) -> Result<Option<Script>> {
+    match self
+      .database
+      .begin_read()?
+      .open_table(OUTPOINT_TO_SCRIPT_PUBKEY)
+    {
+      Ok(outpoint_to_script_pubkey) => Ok(
+        outpoint_to_script_pubkey
+          .get(&outpoint.store())?
+          .map(|script_pubkey| Script::from(script_pubkey.value().to_vec())),
+      ),
+      Err(redb::Error::TableDoesNotExist(_)) => Ok(None),
+      Err(err) => Err(err.into()),
+    }
+  }
+
   pub(crate) fn get_transaction(&self, txid: Txid) -> Result<Option<Transaction>> {
     if txid == self.genesis_block_coinbase_txid {
       Ok(Some(self.genesis_block_coinbase_transaction.clone()))
@@ -781,6 +999,41 @@ impl Index {
     Ok((inscriptions, prev, next))
   }
 
+  pub(crate) fn get_inscriptions_from_number(
+    &self,
+    from: u64,
+    n: usize,
+  ) -> Result<Vec<(u64, InscriptionId)>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(INSCRIPTION_NUMBER_TO_INSCRIPTION_ID)?
+        .range(from..)?
+        .take(n)
+        .map(|(number, id)| (number.value(), Entry::load(*id.value())))
+        .collect(),
+    )
+  }
+
+  pub(crate) fn get_inscriptions_to_number(
+    &self,
+    to: u64,
+    n: usize,
+  ) -> Result<Vec<(u64, InscriptionId)>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(INSCRIPTION_NUMBER_TO_INSCRIPTION_ID)?
+        .range(..=to)?
+        .rev()
+        .take(n)
+        .map(|(number, id)| (number.value(), Entry::load(*id.value())))
+        .collect(),
+    )
+  }
+
   pub(crate) fn get_feed_inscriptions(&self, n: usize) -> Result<Vec<(u64, InscriptionId)>> {
     Ok(
       self
@@ -809,6 +1062,177 @@ impl Index {
     )
   }
 
+  /// Inscriptions whose body has SHA-256 digest `content_hash`, in
+  /// inscription number order, so the first is the original.
+  pub(crate) fn get_inscriptions_by_content_hash(
+    &self,
+    content_hash: sha256::Hash,
+  ) -> Result<Vec<(u64, InscriptionId)>> {
+    let rtx = self.database.begin_read()?;
+
+    let inscription_number_to_inscription_id =
+      rtx.open_table(INSCRIPTION_NUMBER_TO_INSCRIPTION_ID)?;
+
+    let inscriptions = rtx
+      .open_multimap_table(CONTENT_HASH_TO_INSCRIPTION_NUMBERS)?
+      .get(&content_hash.into_inner())?
+      .map(|number| {
+        let number = number.value();
+        let inscription_id = inscription_number_to_inscription_id
+          .get(&number)?
+          .ok_or_else(|| anyhow!("inscription {number} has no inscription id"))?;
+        Ok((number, InscriptionId::load(*inscription_id.value())))
+      })
+      .collect();
+
+    inscriptions
+  }
+
+  /// Transfers of `inscription_id` after it was revealed, oldest first.
+  pub(crate) fn get_transfers(&self, inscription_id: InscriptionId) -> Result<Vec<TransferEntry>> {
+    let rtx = self.database.begin_read()?;
+
+    let transfer_number_to_transfer_entry = rtx.open_table(TRANSFER_NUMBER_TO_TRANSFER_ENTRY)?;
+
+    let transfers = rtx
+      .open_multimap_table(INSCRIPTION_ID_TO_TRANSFER_NUMBER)?
+      .get(&inscription_id.store())?
+      .map(|number| {
+        let number = number.value();
+        Ok(TransferEntry::load(
+          *transfer_number_to_transfer_entry
+            .get(&number)?
+            .ok_or_else(|| anyhow!("transfer {number} has no index entry"))?
+            .value(),
+        ))
+      })
+      .collect();
+
+    transfers
+  }
+
+  pub(crate) fn get_transfers_from_number(
+    &self,
+    from: u64,
+    n: usize,
+  ) -> Result<Vec<(u64, TransferEntry)>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(TRANSFER_NUMBER_TO_TRANSFER_ENTRY)?
+        .range(from..)?
+        .take(n)
+        .map(|(number, entry)| (number.value(), TransferEntry::load(*entry.value())))
+        .collect(),
+    )
+  }
+
+  pub(crate) fn get_transfers_to_number(
+    &self,
+    to: u64,
+    n: usize,
+  ) -> Result<Vec<(u64, TransferEntry)>> {
+    Ok(
+      self
+        .database
+        .begin_read()?
+        .open_table(TRANSFER_NUMBER_TO_TRANSFER_ENTRY)?
+        .range(..=to)?
+        .rev()
+        .take(n)
+        .map(|(number, entry)| (number.value(), TransferEntry::load(*entry.value())))
+        .collect(),
+    )
+  }
+
+  /// Text inscriptions containing any term of `query`, ranked by BM25 score
+  /// and then by inscription number, paginated `page_size` to a page.
+  pub(crate) fn search_text(
+    &self,
+    query: &str,
+    page: usize,
+    page_size: usize,
+  ) -> Result<TextSearch> {
+    if !self.has_text_index()? {
+      bail!("full-text search requires index created with `--index-text` flag");
+    }
+
+    let rtx = self.database.begin_read()?;
+
+    let statistic_to_count = rtx.open_table(STATISTIC_TO_COUNT)?;
+    let statistic = |statistic: Statistic| -> Result<u64> {
+      Ok(
+        statistic_to_count
+          .get(&statistic.key())?
+          .map(|count| count.value())
+          .unwrap_or(0),
+      )
+    };
+
+    let corpus = text::Corpus {
+      documents: statistic(Statistic::TextInscriptions)?,
+      terms: statistic(Statistic::TextTerms)?,
+    };
+
+    let term_to_inscription_number_and_frequency =
+      rtx.open_multimap_table(TERM_TO_INSCRIPTION_NUMBER_AND_FREQUENCY)?;
+    let inscription_number_to_term_count = rtx.open_table(INSCRIPTION_NUMBER_TO_TERM_COUNT)?;
+
+    let mut scores = HashMap::<u64, f64>::new();
+
+    for term in text::tokenize(query).collect::<BTreeSet<String>>() {
+      let postings = term_to_inscription_number_and_frequency
+        .get(term.as_str())?
+        .map(|posting| posting.value())
+        .collect::<Vec<(u64, u32)>>();
+
+      for (number, frequency) in &postings {
+        let length = inscription_number_to_term_count
+          .get(number)?
+          .map(|length| length.value())
+          .unwrap_or(0);
+
+        *scores.entry(*number).or_default() +=
+          corpus.score(*frequency, length, postings.len().try_into().unwrap());
+      }
+    }
+
+    let mut scores = scores.into_iter().collect::<Vec<(u64, f64)>>();
+
+    scores.sort_by(|(a_number, a_score), (b_number, b_score)| {
+      b_score
+        .total_cmp(a_score)
+        .then_with(|| a_number.cmp(b_number))
+    });
+
+    let inscription_number_to_inscription_id =
+      rtx.open_table(INSCRIPTION_NUMBER_TO_INSCRIPTION_ID)?;
+
+    let matches = scores
+      .iter()
+      .skip(page.saturating_mul(page_size))
+      .take(page_size)
+      .map(|(number, score)| {
+        Ok(TextMatch {
+          inscription_id: Entry::load(
+            *inscription_number_to_inscription_id
+              .get(number)?
+              .ok_or_else(|| anyhow!("inscription {number} has no inscription ID"))?
+              .value(),
+          ),
+          number: *number,
+          score: *score,
+        })
+      })
+      .collect::<Result<Vec<TextMatch>>>()?;
+
+    Ok(TextSearch {
+      matches,
+      total: scores.len(),
+    })
+  }
+
   #[cfg(test)]
   fn assert_inscription_location(
     &self,
@@ -999,10 +1423,189 @@ mod tests {
       vec![
         Context::builder().build(),
         Context::builder().arg("--index-sats").build(),
+        Context::builder().arg("--index-content").build(),
+        Context::builder().arg("--index-addresses").build(),
       ]
     }
   }
 
+  #[test]
+  fn index_addresses_tracks_unspent_outputs_by_script_pubkey() {
+    let context = Context::builder().arg("--index-addresses").build();
+
+    assert!(context.index.has_address_index().unwrap());
+
+    let spent = context.mine_blocks(1)[0].txdata[0].txid();
+
+    let txid = context.rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0)],
+      outputs: 2,
+      ..Default::default()
+    });
+
+    let coinbase = context.mine_blocks(1)[0].txdata[0].txid();
+
+    let mut expected = vec![
+      (OutPoint::new(coinbase, 0), 50 * COIN_VALUE),
+      (OutPoint::new(txid, 0), 25 * COIN_VALUE),
+      (OutPoint::new(txid, 1), 25 * COIN_VALUE),
+    ];
+    expected.sort_by_key(|(outpoint, _value)| outpoint.store());
+
+    assert_eq!(
+      context
+        .index
+        .get_outputs_by_script_pubkey(&Script::new())
+        .unwrap(),
+      expected
+    );
+
+    assert_eq!(
+      context
+        .index
+        .get_output_script_pubkey(OutPoint::new(txid, 0))
+        .unwrap(),
+      Some(Script::new())
+    );
+
+    assert_eq!(
+      context
+        .index
+        .get_output_script_pubkey(OutPoint::new(spent, 0))
+        .unwrap(),
+      None
+    );
+  }
+
+  #[test]
+  fn address_lookup_requires_address_index() {
+    let context = Context::builder().build();
+
+    assert!(!context.index.has_address_index().unwrap());
+
+    assert_eq!(
+      context
+        .index
+        .get_outputs_by_script_pubkey(&Script::new())
+        .unwrap_err()
+        .to_string(),
+      "address lookup requires index created with `--index-addresses` flag"
+    );
+  }
+
+  #[test]
+  fn index_content_stores_inscription_content() {
+    for (context, has_content_index) in [
+      (Context::builder().build(), false),
+      (Context::builder().arg("--index-content").build(), true),
+    ] {
+      assert_eq!(
+        context.index.has_content_index().unwrap(),
+        has_content_index
+      );
+
+      context.mine_blocks(1);
+
+      let txid = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(1, 0, 0)],
+        witness: inscription("text/plain;charset=utf-8", "hello").to_witness(),
+        ..Default::default()
+      });
+      let inscription_id = InscriptionId::from(txid);
+
+      context.mine_blocks(1);
+
+      let rtx = context.index.database.begin_read().unwrap();
+
+      match rtx.open_table(INSCRIPTION_ID_TO_BODY) {
+        Ok(table) => assert_eq!(
+          table.get(&inscription_id.store()).unwrap().unwrap().value(),
+          b"hello"
+        ),
+        Err(redb::Error::TableDoesNotExist(_)) => assert!(!has_content_index),
+        Err(err) => panic!("{err}"),
+      }
+
+      assert_eq!(
+        context
+          .index
+          .get_inscription_by_id(inscription_id)
+          .unwrap()
+          .unwrap(),
+        inscription("text/plain;charset=utf-8", "hello"),
+      );
+    }
+  }
+
+  #[test]
+  fn text_index_ranks_matching_inscriptions() {
+    let context = Context::builder().arg("--index-text").build();
+
+    assert!(context.index.has_text_index().unwrap());
+
+    context.mine_blocks(1);
+
+    let mut ids = Vec::new();
+
+    for (i, (content_type, body)) in [
+      ("text/plain;charset=utf-8", "Hello, world!"),
+      ("text/plain;charset=utf-8", "hello hello hello"),
+      ("text/plain;charset=utf-8", "goodbye world"),
+      ("image/png", "hello"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+      let txid = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(i + 1, 0, 0)],
+        witness: inscription(content_type, body).to_witness(),
+        ..Default::default()
+      });
+      ids.push(InscriptionId::from(txid));
+      context.mine_blocks(1);
+    }
+
+    assert_eq!(context.index.statistic(Statistic::TextInscriptions), 3);
+    assert_eq!(context.index.statistic(Statistic::TextTerms), 7);
+
+    let search = |query, page, page_size| {
+      let search = context.index.search_text(query, page, page_size).unwrap();
+      (
+        search
+          .matches
+          .iter()
+          .map(|text_match| text_match.inscription_id)
+          .collect::<Vec<InscriptionId>>(),
+        search.total,
+      )
+    };
+
+    assert_eq!(search("HELLO", 0, 10), (vec![ids[1], ids[0]], 2));
+    assert_eq!(search("world goodbye", 0, 10), (vec![ids[2], ids[0]], 2));
+    assert_eq!(search("world goodbye", 1, 1), (vec![ids[0]], 2));
+    assert_eq!(search("world goodbye", 2, 1), (Vec::new(), 2));
+    assert_eq!(search("png", 0, 10), (Vec::new(), 0));
+    assert_eq!(search("", 0, 10), (Vec::new(), 0));
+
+    let text_match = &context.index.search_text("goodbye", 0, 10).unwrap().matches[0];
+    assert_eq!(text_match.number, 2);
+    assert!(text_match.score > 0.0);
+  }
+
+  #[test]
+  fn search_text_requires_text_index() {
+    let context = Context::builder().build();
+    assert!(!context.index.has_text_index().unwrap());
+    assert_eq!(
+      context
+        .index
+        .search_text("foo", 0, 10)
+        .unwrap_err()
+        .to_string(),
+      "full-text search requires index created with `--index-text` flag"
+    );
+  }
+
   #[test]
   fn height_limit() {
     {
@@ -1624,6 +2227,321 @@ mod tests {
     }
   }
 
+  #[test]
+  fn inscriptions_are_indexed_by_content_hash() {
+    for context in Context::configurations() {
+      context.mine_blocks(3);
+
+      let mut inscription_ids = Vec::new();
+
+      for (i, body) in ["hello", "world", "hello"].iter().enumerate() {
+        let txid = context.rpc_server.broadcast_tx(TransactionTemplate {
+          inputs: &[(i + 1, 0, 0)],
+          witness: inscription("text/plain", body).to_witness(),
+          ..Default::default()
+        });
+        inscription_ids.push(InscriptionId::from(txid));
+        context.mine_blocks(1);
+      }
+
+      assert_eq!(
+        context
+          .index
+          .get_inscriptions_by_content_hash(sha256::Hash::hash(b"hello"))
+          .unwrap(),
+        [(0, inscription_ids[0]), (2, inscription_ids[2])]
+      );
+
+      assert_eq!(
+        context
+          .index
+          .get_inscriptions_by_content_hash(sha256::Hash::hash(b"world"))
+          .unwrap(),
+        [(1, inscription_ids[1])]
+      );
+
+      assert!(context
+        .index
+        .get_inscriptions_by_content_hash(sha256::Hash::hash(b"foo"))
+        .unwrap()
+        .is_empty());
+    }
+  }
+
+  #[test]
+  fn reorgs_are_rolled_back() {
+    for context in Context::configurations()
+      .into_iter()
+      .chain([Context::builder().arg("--index-text").build()])
+    {
+      context.mine_blocks(1);
+
+      let template = TransactionTemplate {
+        inputs: &[(1, 0, 0)],
+        witness: inscription("text/plain;charset=utf-8", "hello").to_witness(),
+        ..Default::default()
+      };
+
+      let reveal = context.rpc_server.broadcast_tx(template.clone());
+      let inscription_id = InscriptionId::from(reveal);
+      context.mine_blocks(1);
+
+      let transfer = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(2, 1, 0)],
+        ..Default::default()
+      });
+      context.mine_blocks(1);
+
+      assert_eq!(
+        context.index.get_transfers(inscription_id).unwrap().len(),
+        1
+      );
+
+      context.rpc_server.invalidate_tip();
+      context.rpc_server.invalidate_tip();
+      context.rpc_server.mine_blocks(1);
+
+      assert_eq!(context.rpc_server.broadcast_tx(template), reveal);
+      context.mine_blocks(2);
+
+      assert_eq!(context.index.statistic(Statistic::Reorgs), 1);
+      assert_eq!(context.index.block_count().unwrap(), 5);
+
+      let entry = context
+        .index
+        .get_inscription_entry(inscription_id)
+        .unwrap()
+        .unwrap();
+
+      assert_eq!(entry.height, 3);
+      assert_eq!(entry.number, 0);
+      assert_eq!(
+        entry.sat,
+        context
+          .index
+          .has_sat_index()
+          .unwrap()
+          .then_some(Sat(50 * COIN_VALUE))
+      );
+
+      assert_eq!(
+        context
+          .index
+          .get_inscription_satpoint_by_id(inscription_id)
+          .unwrap(),
+        Some(SatPoint {
+          outpoint: OutPoint {
+            txid: reveal,
+            vout: 0
+          },
+          offset: 0,
+        })
+      );
+
+      assert!(context
+        .index
+        .get_transfers(inscription_id)
+        .unwrap()
+        .is_empty());
+
+      assert_eq!(
+        context
+          .index
+          .get_inscriptions_by_content_hash(sha256::Hash::hash(b"hello"))
+          .unwrap(),
+        [(0, inscription_id)]
+      );
+
+      if context.index.has_sat_index().unwrap() {
+        assert_eq!(
+          context
+            .index
+            .list(OutPoint {
+              txid: transfer,
+              vout: 0
+            })
+            .unwrap(),
+          None
+        );
+      }
+
+      if context.index.has_address_index().unwrap() {
+        let outputs = context
+          .index
+          .get_outputs_by_script_pubkey(&Script::new())
+          .unwrap();
+
+        assert!(outputs
+          .iter()
+          .all(|(outpoint, _value)| outpoint.txid != transfer));
+        assert!(outputs
+          .iter()
+          .any(|(outpoint, _value)| outpoint.txid == reveal));
+      }
+
+      if context.index.has_text_index().unwrap() {
+        assert_eq!(context.index.search_text("hello", 0, 10).unwrap().total, 1);
+      }
+    }
+  }
+
+  #[test]
+  fn rollback_without_diverging_blocks_does_nothing() {
+    let context = Context::builder().build();
+
+    context.mine_blocks(2);
+
+    Reorg { height: 1 }.rollback(&context.index).unwrap();
+
+    assert_eq!(context.index.statistic(Statistic::Reorgs), 0);
+    assert_eq!(context.index.block_count().unwrap(), 3);
+  }
+
+  #[test]
+  fn reorgs_deeper_than_undo_logs_require_rebuilding_index() {
+    let context = Context::builder().build();
+
+    context.mine_blocks(10);
+
+    for _ in 0..=reorg::MAX_REORG_DEPTH {
+      context.rpc_server.invalidate_tip();
+    }
+
+    context.rpc_server.mine_blocks(reorg::MAX_REORG_DEPTH + 2);
+
+    assert_eq!(
+      context.index.update().unwrap_err().to_string(),
+      format!(
+        "reorg detected at or before 10 and deeper than the {} blocks that can be rolled back, please rebuild the index",
+        reorg::MAX_REORG_DEPTH
+      )
+    );
+
+    assert!(context.index.is_reorged());
+    assert_eq!(context.index.statistic(Statistic::Reorgs), 0);
+    assert_eq!(context.index.block_count().unwrap(), 11);
+  }
+
+  #[test]
+  fn transfers_are_recorded() {
+    for context in Context::configurations() {
+      context.mine_blocks(1);
+
+      let reveal = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(1, 0, 0)],
+        witness: inscription("text/plain", "hello").to_witness(),
+        ..Default::default()
+      });
+      let inscription_id = InscriptionId::from(reveal);
+
+      context.mine_blocks(1);
+
+      assert!(context
+        .index
+        .get_transfers(inscription_id)
+        .unwrap()
+        .is_empty());
+
+      let send = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(2, 1, 0)],
+        ..Default::default()
+      });
+
+      context.mine_blocks(1);
+
+      let spend = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(3, 1, 0)],
+        fee: 50 * COIN_VALUE,
+        ..Default::default()
+      });
+
+      let coinbase_tx = context.mine_blocks(1)[0].txdata[0].txid();
+
+      let transfers = context
+        .index
+        .get_transfers(inscription_id)
+        .unwrap()
+        .into_iter()
+        .map(|transfer| {
+          assert_eq!(transfer.inscription_id, inscription_id);
+          (
+            transfer.height,
+            transfer.txid,
+            transfer.old_satpoint,
+            transfer.new_satpoint,
+            transfer.lost,
+          )
+        })
+        .collect::<Vec<(u64, Txid, SatPoint, SatPoint, bool)>>();
+
+      assert_eq!(
+        transfers,
+        [
+          (
+            3,
+            send,
+            SatPoint {
+              outpoint: OutPoint {
+                txid: reveal,
+                vout: 0
+              },
+              offset: 0,
+            },
+            SatPoint {
+              outpoint: OutPoint {
+                txid: send,
+                vout: 0
+              },
+              offset: 0,
+            },
+            false,
+          ),
+          (
+            4,
+            spend,
+            SatPoint {
+              outpoint: OutPoint {
+                txid: send,
+                vout: 0
+              },
+              offset: 0,
+            },
+            SatPoint {
+              outpoint: OutPoint {
+                txid: coinbase_tx,
+                vout: 0
+              },
+              offset: 50 * COIN_VALUE,
+            },
+            true,
+          ),
+        ]
+      );
+
+      assert_eq!(
+        context
+          .index
+          .get_transfers_from_number(0, usize::MAX)
+          .unwrap()
+          .into_iter()
+          .map(|(number, transfer)| (number, transfer.txid))
+          .collect::<Vec<(u64, Txid)>>(),
+        [(0, send), (1, spend)]
+      );
+
+      assert_eq!(
+        context
+          .index
+          .get_transfers_to_number(u64::MAX, 1)
+          .unwrap()
+          .into_iter()
+          .map(|(number, transfer)| (number, transfer.txid))
+          .collect::<Vec<(u64, Txid)>>(),
+        [(1, spend)]
+      );
+    }
+  }
+
   #[test]
   fn inscription_can_be_fee_spent_in_first_transaction() {
     for context in Context::configurations() {
@@ -2172,6 +3090,95 @@ mod tests {
     }
   }
 
+  #[test]
+  fn get_inscriptions_from_number() {
+    let context = Context::builder().build();
+    context.mine_blocks(1);
+
+    let mut ids = Vec::new();
+
+    for i in 0..3 {
+      let txid = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(i + 1, 0, 0)],
+        witness: inscription("text/plain", "hello").to_witness(),
+        ..Default::default()
+      });
+      ids.push(InscriptionId::from(txid));
+      context.mine_blocks(1);
+    }
+
+    assert_eq!(
+      context.index.get_inscriptions_from_number(0, 2).unwrap(),
+      [(0, ids[0]), (1, ids[1])]
+    );
+
+    assert_eq!(
+      context.index.get_inscriptions_from_number(1, 100).unwrap(),
+      [(1, ids[1]), (2, ids[2])]
+    );
+
+    assert!(context
+      .index
+      .get_inscriptions_from_number(3, 100)
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn get_inscriptions_to_number() {
+    let context = Context::builder().build();
+    context.mine_blocks(1);
+
+    assert!(context
+      .index
+      .get_inscriptions_to_number(u64::MAX, 100)
+      .unwrap()
+      .is_empty());
+
+    let mut ids = Vec::new();
+
+    for i in 0..3 {
+      let txid = context.rpc_server.broadcast_tx(TransactionTemplate {
+        inputs: &[(i + 1, 0, 0)],
+        witness: inscription("text/plain", "hello").to_witness(),
+        ..Default::default()
+      });
+      ids.push(InscriptionId::from(txid));
+      context.mine_blocks(1);
+    }
+
+    assert_eq!(
+      context
+        .index
+        .get_inscriptions_to_number(u64::MAX, 2)
+        .unwrap(),
+      [(2, ids[2]), (1, ids[1])]
+    );
+
+    assert_eq!(
+      context.index.get_inscriptions_to_number(1, 100).unwrap(),
+      [(1, ids[1]), (0, ids[0])]
+    );
+  }
+
+  #[test]
+  fn block_hash() {
+    let context = Context::builder().build();
+    let blocks = context.mine_blocks(2);
+
+    assert_eq!(
+      context.index.block_hash(None).unwrap(),
+      Some(blocks[1].block_hash())
+    );
+
+    assert_eq!(
+      context.index.block_hash(Some(1)).unwrap(),
+      Some(blocks[0].block_hash())
+    );
+
+    assert_eq!(context.index.block_hash(Some(3)).unwrap(), None);
+  }
+
   #[test]
   fn unsynced_index_fails() {
     for context in Context::configurations() {
@@ -0,0 +1,661 @@
+use {
+  super::*,
+  bitcoin::util::uint::Uint256,
+  std::io::{Read, Seek, SeekFrom},
+};
+
+mod level_db;
+
+// Every block in a blk*.dat file is preceded by the network magic and the
+// size of the serialized block.
+const PREFIX_LEN: usize = 8;
+
+// Block validity levels and flags from Bitcoin Core's `BlockStatus`.
+const BLOCK_VALID_MASK: u64 = 7;
+const BLOCK_VALID_SCRIPTS: u64 = 5;
+const BLOCK_HAVE_DATA: u64 = 8;
+const BLOCK_HAVE_UNDO: u64 = 16;
+const BLOCK_FAILED_MASK: u64 = 32 | 64;
+
+#[derive(Copy, Clone)]
+struct Location {
+  file: u32,
+  offset: u64,
+}
+
+struct Entry {
+  header: BlockHeader,
+  height: u64,
+  location: Option<Location>,
+  sequence: u64,
+  status: u64,
+}
+
+impl Entry {
+  /// Parse a serialized `CDiskBlockIndex`.
+  fn load(mut value: &[u8], sequence: u64) -> Result<Self> {
+    let _version = varint(&mut value)?;
+    let height = varint(&mut value)?;
+    let status = varint(&mut value)?;
+    let _transactions = varint(&mut value)?;
+
+    let file = if status & (BLOCK_HAVE_DATA | BLOCK_HAVE_UNDO) != 0 {
+      Some(u32::try_from(varint(&mut value)?)?)
+    } else {
+      None
+    };
+
+    let offset = if status & BLOCK_HAVE_DATA != 0 {
+      Some(varint(&mut value)?)
+    } else {
+      None
+    };
+
+    if status & BLOCK_HAVE_UNDO != 0 {
+      varint(&mut value)?;
+    }
+
+    Ok(Self {
+      header: consensus::deserialize(value)?,
+      height,
+      location: file
+        .zip(offset)
+        .map(|(file, offset)| Location { file, offset }),
+      sequence,
+      status,
+    })
+  }
+}
+
+/// Blocks read from Bitcoin Core's blk*.dat files, without Bitcoin Core.
+///
+/// The chain followed is the one with the most work among the blocks Bitcoin
+/// Core's block index records as fully validated and not failed, so blocks are
+/// only indexed once Bitcoin Core has flushed its block index to disk.
+pub(crate) struct BlockFiles {
+  blocks: HashMap<BlockHash, Entry>,
+  chain: Vec<BlockHash>,
+  dir: PathBuf,
+  genesis: BlockHash,
+  index: level_db::LevelDb,
+  key: Option<[u8; 8]>,
+  magic: [u8; 4],
+}
+
+impl BlockFiles {
+  pub(crate) fn new(dir: PathBuf, chain: Chain) -> Self {
+    Self {
+      blocks: HashMap::new(),
+      chain: Vec::new(),
+      index: level_db::LevelDb::new(dir.join("index")),
+      dir,
+      genesis: chain.genesis_block().block_hash(),
+      key: None,
+      magic: chain.network().magic().to_le_bytes(),
+    }
+  }
+
+  /// Read block index entries written since the last scan, and update the
+  /// best chain.
+  pub(crate) fn scan(&mut self) -> Result {
+    let start = Instant::now();
+
+    self.key = self.load_key()?;
+
+    let blocks = &mut self.blocks;
+    let mut updated = 0;
+
+    self
+      .index
+      .read(|key, sequence, value| {
+        // Bitcoin Core never deletes block index entries.
+        let (Some(hash), Some(value)) = (key.strip_prefix(b"b"), value) else {
+          return Ok(());
+        };
+
+        let hash = BlockHash::from_slice(hash)?;
+
+        if blocks
+          .get(&hash)
+          .map_or(true, |entry| sequence > entry.sequence)
+        {
+          blocks.insert(hash, Entry::load(value, sequence)?);
+          updated += 1;
+        }
+
+        Ok(())
+      })
+      .with_context(|| {
+        format!(
+          "failed to read block index `{}`",
+          self.dir.join("index").display()
+        )
+      })?;
+
+    if updated > 0 {
+      self.update_chain();
+    }
+
+    log::info!(
+      "Read {updated} block index entries from `{}` in {}ms, best chain has {} blocks",
+      self.dir.display(),
+      start.elapsed().as_millis(),
+      self.chain.len(),
+    );
+
+    Ok(())
+  }
+
+  /// The number of blocks on the best chain.
+  pub(crate) fn block_count(&self) -> u64 {
+    self.chain.len().try_into().unwrap()
+  }
+
+  /// The hash of the block at `height` on the best chain.
+  pub(crate) fn block_hash(&self, height: u64) -> Option<BlockHash> {
+    self.chain.get(usize::try_from(height).ok()?).copied()
+  }
+
+  /// Read the block at `height` on the best chain, or just its header if
+  /// `full` is false. Returns `None` past the tip.
+  pub(crate) fn block(&self, height: u64, full: bool) -> Result<Option<Block>> {
+    let Some(hash) = self.block_hash(height) else {
+      return Ok(None);
+    };
+
+    let entry = &self.blocks[&hash];
+
+    if !full {
+      return Ok(Some(Block {
+        header: entry.header,
+        txdata: Vec::new(),
+      }));
+    }
+
+    let Some(location) = entry.location else {
+      bail!("block {hash} at height {height} is not in the block files, it may have been pruned");
+    };
+
+    let path = self.path(location.file);
+
+    let mut file = File::open(&path)
+      .with_context(|| format!("failed to open block file `{}`", path.display()))?;
+
+    let mut prefix = [0; PREFIX_LEN];
+
+    self.read(
+      &mut file,
+      location
+        .offset
+        .checked_sub(PREFIX_LEN as u64)
+        .ok_or_else(|| anyhow!("block {hash} has invalid offset {}", location.offset))?,
+      &mut prefix,
+    )?;
+
+    if prefix[..4] != self.magic {
+      bail!(
+        "block file `{}` has no block at offset {}",
+        path.display(),
+        location.offset
+      );
+    }
+
+    let mut buffer = vec![0; u32::from_le_bytes(prefix[4..].try_into().unwrap()).try_into()?];
+
+    self.read(&mut file, location.offset, &mut buffer)?;
+
+    let block: Block = consensus::deserialize(&buffer)?;
+
+    if block.block_hash() != hash {
+      bail!(
+        "block file `{}` contains block {} instead of {hash}",
+        path.display(),
+        block.block_hash()
+      );
+    }
+
+    Ok(Some(block))
+  }
+
+  fn update_chain(&mut self) {
+    let mut valid = self
+      .blocks
+      .iter()
+      .filter(|(hash, entry)| {
+        // Bitcoin Core doesn't connect the genesis block, so it is never
+        // marked as having valid scripts.
+        **hash == self.genesis
+          || (entry.status & BLOCK_VALID_MASK >= BLOCK_VALID_SCRIPTS
+            && entry.status & BLOCK_FAILED_MASK == 0)
+      })
+      .collect::<Vec<(&BlockHash, &Entry)>>();
+
+    valid.sort_by_key(|(_, entry)| entry.height);
+
+    let mut chainwork = HashMap::<BlockHash, Uint256>::new();
+    let mut tip = None;
+
+    for (hash, entry) in valid {
+      let work = if *hash == self.genesis {
+        entry.header.work()
+      } else if let Some(parent) = chainwork.get(&entry.header.prev_blockhash) {
+        *parent + entry.header.work()
+      } else {
+        continue;
+      };
+
+      chainwork.insert(*hash, work);
+
+      // Keep following the current chain when another has as much work.
+      if tip.map_or(true, |(_, most)| {
+        work > most || (work == most && self.chain.last() == Some(hash))
+      }) {
+        tip = Some((*hash, work));
+      }
+    }
+
+    let mut chain = Vec::new();
+
+    if let Some((mut hash, _)) = tip {
+      loop {
+        chain.push(hash);
+
+        if hash == self.genesis {
+          break;
+        }
+
+        hash = self.blocks[&hash].header.prev_blockhash;
+      }
+    }
+
+    chain.reverse();
+
+    self.chain = chain;
+  }
+
+  fn load_key(&self) -> Result<Option<[u8; 8]>> {
+    let path = self.dir.join("xor.dat");
+
+    let key = match fs::read(&path) {
+      Ok(key) => key,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(err) => return Err(err).with_context(|| format!("failed to read `{}`", path.display())),
+    };
+
+    let key: [u8; 8] = key.try_into().map_err(|_| {
+      anyhow!(
+        "block file obfuscation key `{}` is not 8 bytes",
+        path.display()
+      )
+    })?;
+
+    Ok(if key == [0; 8] { None } else { Some(key) })
+  }
+
+  fn path(&self, file: u32) -> PathBuf {
+    self.dir.join(format!("blk{file:05}.dat"))
+  }
+
+  fn read(&self, file: &mut File, offset: u64, buffer: &mut [u8]) -> Result {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buffer)?;
+
+    if let Some(key) = self.key {
+      let skip = usize::try_from(offset % 8).unwrap();
+      for (byte, key) in buffer.iter_mut().zip(key.iter().cycle().skip(skip)) {
+        *byte ^= key;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Read one of Bitcoin Core's base-128 `VARINT`s, which, unlike LevelDB's,
+/// are big-endian and have no redundant encodings.
+fn varint(bytes: &mut &[u8]) -> Result<u64> {
+  let mut n: u64 = 0;
+
+  loop {
+    let (&byte, rest) = bytes
+      .split_first()
+      .ok_or_else(|| anyhow!("unexpected end of block index entry"))?;
+
+    *bytes = rest;
+
+    n = n
+      .checked_mul(128)
+      .ok_or_else(|| anyhow!("block index varint is too large"))?
+      | u64::from(byte & 0x7f);
+
+    if byte & 0x80 == 0 {
+      return Ok(n);
+    }
+
+    n += 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, bitcoin::TxMerkleNode};
+
+  fn child(parent: &BlockHeader, nonce: u32) -> BlockHeader {
+    BlockHeader {
+      version: 1,
+      prev_blockhash: parent.block_hash(),
+      merkle_root: TxMerkleNode::all_zeros(),
+      time: parent.time + 1,
+      bits: parent.bits,
+      nonce,
+    }
+  }
+
+  fn chain(parent: &BlockHeader, n: usize, nonce: u32) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for _ in 0..n {
+      let header = child(
+        blocks.last().map(|block| &block.header).unwrap_or(parent),
+        nonce,
+      );
+
+      blocks.push(Block {
+        header,
+        txdata: Vec::new(),
+      });
+    }
+
+    blocks
+  }
+
+  const VALID: u64 = BLOCK_HAVE_DATA | BLOCK_VALID_SCRIPTS;
+
+  fn encode(mut n: u64) -> Vec<u8> {
+    let mut bytes = vec![u8::try_from(n & 0x7f).unwrap()];
+    while n > 0x7f {
+      n = (n >> 7) - 1;
+      bytes.push(u8::try_from(n & 0x7f).unwrap() | 0x80);
+    }
+    bytes.reverse();
+    bytes
+  }
+
+  /// Write `blocks`, the first at `height`, to block file `file`, and return
+  /// block index entries for them with `status`.
+  fn write(
+    dir: &Path,
+    file: u32,
+    height: u64,
+    blocks: &[Block],
+    status: u64,
+    key: Option<[u8; 8]>,
+  ) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut bytes = Vec::new();
+    let mut entries = Vec::new();
+
+    for (i, block) in blocks.iter().enumerate() {
+      let serialized = consensus::serialize(block);
+      bytes.extend(Network::Regtest.magic().to_le_bytes());
+      bytes.extend(u32::try_from(serialized.len()).unwrap().to_le_bytes());
+
+      let mut value = [
+        encode(250000),
+        encode(height + i as u64),
+        encode(status),
+        encode(1),
+      ]
+      .concat();
+
+      if status & BLOCK_HAVE_DATA != 0 {
+        value.extend(encode(file.into()));
+        value.extend(encode(bytes.len() as u64));
+      }
+
+      value.extend(consensus::serialize(&block.header));
+
+      entries.push(([&b"b"[..], &block.block_hash()[..]].concat(), value));
+
+      bytes.extend(serialized);
+    }
+
+    // preallocated space
+    bytes.extend([0; 64]);
+
+    if let Some(key) = key {
+      for (byte, key) in bytes.iter_mut().zip(key.iter().cycle()) {
+        *byte ^= key;
+      }
+    }
+
+    fs::write(dir.join(format!("blk{file:05}.dat")), bytes).unwrap();
+
+    entries
+  }
+
+  /// Append `entries` to the block index's log in a single write batch.
+  fn index(dir: &Path, entries: &[(Vec<u8>, Vec<u8>)]) {
+    let dir = dir.join("index");
+    let log = dir.join("000002.log");
+
+    if !dir.exists() {
+      fs::create_dir(&dir).unwrap();
+      fs::write(dir.join("CURRENT"), "MANIFEST-000001\n").unwrap();
+      fs::write(dir.join("MANIFEST-000001"), [0, 0, 0, 0, 2, 0, 1, 2, 2]).unwrap();
+      fs::write(&log, []).unwrap();
+    }
+
+    let mut bytes = fs::read(&log).unwrap();
+
+    // later batches need higher sequence numbers
+    let mut batch = (bytes.len() as u64 + 1).to_le_bytes().to_vec();
+    batch.extend(u32::try_from(entries.len()).unwrap().to_le_bytes());
+    for (key, value) in entries {
+      batch.push(1);
+      batch.push(key.len().try_into().unwrap());
+      batch.extend(key);
+      batch.push(value.len().try_into().unwrap());
+      batch.extend(value);
+    }
+
+    bytes.extend([0; 4]);
+    bytes.extend(u16::try_from(batch.len()).unwrap().to_le_bytes());
+    bytes.push(1);
+    bytes.extend(batch);
+
+    fs::write(log, bytes).unwrap();
+  }
+
+  fn genesis(dir: &Path, key: Option<[u8; 8]>) -> Block {
+    let genesis = Chain::Regtest.genesis_block();
+    index(
+      dir,
+      &write(
+        dir,
+        0,
+        0,
+        std::slice::from_ref(&genesis),
+        BLOCK_HAVE_DATA | 3,
+        key,
+      ),
+    );
+    genesis
+  }
+
+  #[test]
+  fn varints_are_read() {
+    for n in [0, 1, 127, 128, 255, 16511, 16512, u64::from(u32::MAX)] {
+      assert_eq!(varint(&mut encode(n).as_slice()).unwrap(), n);
+    }
+
+    assert_eq!(encode(128), [0x80, 0x00]);
+    assert!(varint(&mut [0x80].as_slice()).is_err());
+  }
+
+  #[test]
+  fn blocks_are_read_from_block_files() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path();
+
+    let genesis = genesis(dir, None);
+    let blocks = chain(&genesis.header, 10, 0);
+
+    index(dir, &write(dir, 1, 1, &blocks[..5], VALID, None));
+    index(dir, &write(dir, 2, 6, &blocks[5..], VALID, None));
+
+    let mut block_files = BlockFiles::new(dir.into(), Chain::Regtest);
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 11);
+    assert_eq!(block_files.block(0, true).unwrap().unwrap(), genesis);
+    assert_eq!(block_files.block(4, true).unwrap().unwrap(), blocks[3]);
+    assert_eq!(block_files.block(8, true).unwrap().unwrap(), blocks[7]);
+    assert_eq!(
+      block_files.block(4, false).unwrap().unwrap().header,
+      blocks[3].header
+    );
+    assert_eq!(
+      block_files.block(4, false).unwrap().unwrap().txdata,
+      Vec::new()
+    );
+  }
+
+  #[test]
+  fn blocks_past_tip_are_not_read() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path();
+
+    let genesis = genesis(dir, None);
+    index(
+      dir,
+      &write(dir, 1, 1, &chain(&genesis.header, 10, 0), VALID, None),
+    );
+
+    let mut block_files = BlockFiles::new(dir.into(), Chain::Regtest);
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 11);
+    assert!(block_files.block(10, true).unwrap().is_some());
+    assert!(block_files.block(11, true).unwrap().is_none());
+    assert_eq!(block_files.block_hash(11), None);
+  }
+
+  #[test]
+  fn chain_with_most_work_is_selected() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path();
+
+    let genesis = genesis(dir, None);
+    let short = chain(&genesis.header, 8, 0);
+    let long = chain(&genesis.header, 9, 1);
+
+    index(dir, &write(dir, 1, 1, &short, VALID, None));
+
+    let mut block_files = BlockFiles::new(dir.into(), Chain::Regtest);
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 9);
+    assert_eq!(block_files.block(1, true).unwrap().unwrap(), short[0]);
+
+    index(dir, &write(dir, 2, 1, &long, VALID, None));
+
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 10);
+    assert_eq!(block_files.block(1, true).unwrap().unwrap(), long[0]);
+  }
+
+  #[test]
+  fn blocks_not_validated_or_failed_are_not_followed() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path();
+
+    let genesis = genesis(dir, None);
+    let short = chain(&genesis.header, 8, 0);
+    let unvalidated = chain(&genesis.header, 9, 1);
+    let failed = chain(&genesis.header, 10, 2);
+
+    index(dir, &write(dir, 1, 1, &short, VALID, None));
+    index(
+      dir,
+      &write(dir, 2, 1, &unvalidated, BLOCK_HAVE_DATA | 3, None),
+    );
+    index(dir, &write(dir, 3, 1, &failed, VALID | 32, None));
+
+    let mut block_files = BlockFiles::new(dir.into(), Chain::Regtest);
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 9);
+    assert_eq!(block_files.block(1, true).unwrap().unwrap(), short[0]);
+
+    // Bitcoin Core validates the chain and records it in a later entry
+    index(dir, &write(dir, 2, 1, &unvalidated, VALID, None));
+
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 10);
+    assert_eq!(block_files.block(1, true).unwrap().unwrap(), unvalidated[0]);
+  }
+
+  #[test]
+  fn pruned_blocks_are_not_read() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path();
+
+    let genesis = genesis(dir, None);
+    let blocks = chain(&genesis.header, 2, 0);
+
+    index(dir, &write(dir, 1, 1, &blocks, BLOCK_VALID_SCRIPTS, None));
+
+    let mut block_files = BlockFiles::new(dir.into(), Chain::Regtest);
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 3);
+    assert_eq!(
+      block_files.block(1, false).unwrap().unwrap().header,
+      blocks[0].header
+    );
+    assert_eq!(
+      block_files.block(1, true).unwrap_err().to_string(),
+      format!(
+        "block {} at height 1 is not in the block files, it may have been pruned",
+        blocks[0].block_hash()
+      )
+    );
+  }
+
+  #[test]
+  fn missing_block_index_is_an_error() {
+    let tempdir = TempDir::new().unwrap();
+
+    let mut block_files = BlockFiles::new(tempdir.path().into(), Chain::Regtest);
+
+    assert_eq!(
+      block_files.scan().unwrap_err().to_string(),
+      format!(
+        "failed to read block index `{}`",
+        tempdir.path().join("index").display()
+      )
+    );
+  }
+
+  #[test]
+  fn obfuscated_block_files_are_read() {
+    let tempdir = TempDir::new().unwrap();
+    let dir = tempdir.path();
+
+    let key = [1, 2, 3, 4, 5, 6, 7, 8];
+    fs::write(dir.join("xor.dat"), key).unwrap();
+
+    let genesis = genesis(dir, Some(key));
+    let blocks = chain(&genesis.header, 10, 0);
+
+    index(dir, &write(dir, 1, 1, &blocks, VALID, Some(key)));
+
+    let mut block_files = BlockFiles::new(dir.into(), Chain::Regtest);
+    block_files.scan().unwrap();
+
+    assert_eq!(block_files.block_count(), 11);
+    assert_eq!(block_files.block(2, true).unwrap().unwrap(), blocks[1]);
+  }
+}
@@ -0,0 +1,617 @@
+use {super::*, std::borrow::Cow, std::collections::HashSet};
+
+// The manifest and write-ahead logs are split into blocks of this size, and
+// records that don't fit in a block are continued in the next one.
+const LOG_BLOCK_SIZE: usize = 32768;
+
+const LOG_HEADER_LEN: usize = 7;
+
+const FULL: u8 = 1;
+const FIRST: u8 = 2;
+const MIDDLE: u8 = 3;
+const LAST: u8 = 4;
+
+const FOOTER_LEN: usize = 48;
+
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+
+/// A read-only view of a LevelDB database that another process may be writing
+/// to, just enough to follow Bitcoin Core's block index. Checksums aren't
+/// verified.
+pub(crate) struct LevelDb {
+  dir: PathBuf,
+  logs: HashMap<u64, usize>,
+  tables: HashSet<u64>,
+}
+
+impl LevelDb {
+  pub(crate) fn new(dir: PathBuf) -> Self {
+    Self {
+      dir,
+      logs: HashMap::new(),
+      tables: HashSet::new(),
+    }
+  }
+
+  /// Call `f` with the key, sequence number and value, or `None` if deleted,
+  /// of every record written since the last call. A record can be passed more
+  /// than once, and the one with the highest sequence number is current.
+  pub(crate) fn read(&mut self, mut f: impl FnMut(&[u8], u64, Option<&[u8]>) -> Result) -> Result {
+    let current = self.dir.join("CURRENT");
+
+    let manifest = fs::read_to_string(&current)
+      .with_context(|| format!("failed to read `{}`", current.display()))?;
+
+    let manifest = self.dir.join(manifest.trim_end());
+
+    let (log_number, tables) = Self::manifest(
+      &fs::read(&manifest).with_context(|| format!("failed to read `{}`", manifest.display()))?,
+    )?;
+
+    self.tables.retain(|table| tables.contains(table));
+
+    for table in tables {
+      if self.tables.contains(&table) {
+        continue;
+      }
+
+      let mut path = self.dir.join(format!("{table:06}.ldb"));
+
+      if !path.exists() {
+        path.set_extension("sst");
+      }
+
+      Self::table(
+        &fs::read(&path).with_context(|| format!("failed to read `{}`", path.display()))?,
+        &mut f,
+      )
+      .with_context(|| format!("failed to read LevelDB table `{}`", path.display()))?;
+
+      self.tables.insert(table);
+    }
+
+    let mut logs = Vec::new();
+
+    for entry in fs::read_dir(&self.dir)? {
+      let path = entry?.path();
+
+      if path.extension() != Some("log".as_ref()) {
+        continue;
+      }
+
+      if let Some(number) = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.parse::<u64>().ok())
+      {
+        if number >= log_number {
+          logs.push((number, path));
+        }
+      }
+    }
+
+    logs.sort();
+
+    self.logs.retain(|number, _| *number >= log_number);
+
+    for (number, path) in logs {
+      let offset = self.logs.entry(number).or_default();
+
+      *offset = Self::log(
+        &fs::read(&path).with_context(|| format!("failed to read `{}`", path.display()))?,
+        *offset,
+        |batch| Self::batch(batch, &mut f),
+      )
+      .with_context(|| format!("failed to read LevelDB log `{}`", path.display()))?;
+    }
+
+    Ok(())
+  }
+
+  /// Returns the number of the oldest live log and the numbers of the live
+  /// tables recorded in a manifest.
+  fn manifest(manifest: &[u8]) -> Result<(u64, HashSet<u64>)> {
+    let mut log_number = 0;
+    let mut prev_log_number = 0;
+    let mut tables = HashSet::new();
+
+    Self::log(manifest, 0, |edit| {
+      let mut edit = Reader(edit);
+
+      while !edit.is_empty() {
+        match edit.varint()? {
+          1 => {
+            edit.slice()?;
+          }
+          2 => log_number = edit.varint()?,
+          3 | 4 => {
+            edit.varint()?;
+          }
+          5 => {
+            edit.varint()?;
+            edit.slice()?;
+          }
+          6 => {
+            edit.varint()?;
+            tables.remove(&edit.varint()?);
+          }
+          7 => {
+            edit.varint()?;
+            tables.insert(edit.varint()?);
+            edit.varint()?;
+            edit.slice()?;
+            edit.slice()?;
+          }
+          9 => prev_log_number = edit.varint()?,
+          tag => bail!("unknown LevelDB manifest tag {tag}"),
+        }
+      }
+
+      Ok(())
+    })?;
+
+    let log_number = if prev_log_number == 0 {
+      log_number
+    } else {
+      log_number.min(prev_log_number)
+    };
+
+    Ok((log_number, tables))
+  }
+
+  /// Call `f` with every record in a log from `offset` on, returning the
+  /// offset after the last complete record.
+  fn log(log: &[u8], mut offset: usize, mut f: impl FnMut(&[u8]) -> Result) -> Result<usize> {
+    let mut end = offset;
+    let mut record = Vec::new();
+
+    while offset < log.len() {
+      let remaining = LOG_BLOCK_SIZE - offset % LOG_BLOCK_SIZE;
+
+      if remaining < LOG_HEADER_LEN {
+        offset += remaining;
+        continue;
+      }
+
+      if offset + LOG_HEADER_LEN > log.len() {
+        break;
+      }
+
+      let len = usize::from(u16::from_le_bytes([log[offset + 4], log[offset + 5]]));
+      let kind = log[offset + 6];
+      let start = offset + LOG_HEADER_LEN;
+
+      // Logs are preallocated with zeros, and the last record may still be
+      // being written.
+      if kind == 0 || start + len > log.len() {
+        break;
+      }
+
+      let data = &log[start..start + len];
+
+      offset = start + len;
+
+      match kind {
+        FULL => {
+          f(data)?;
+          end = offset;
+        }
+        FIRST => {
+          record.clear();
+          record.extend_from_slice(data);
+        }
+        MIDDLE => record.extend_from_slice(data),
+        LAST => {
+          record.extend_from_slice(data);
+          f(&record)?;
+          end = offset;
+        }
+        kind => bail!("unknown LevelDB log record type {kind}"),
+      }
+    }
+
+    Ok(end)
+  }
+
+  fn batch(batch: &[u8], f: &mut impl FnMut(&[u8], u64, Option<&[u8]>) -> Result) -> Result {
+    let mut batch = Reader(batch);
+
+    let sequence = u64::from_le_bytes(batch.bytes(8)?.try_into().unwrap());
+    let count = u32::from_le_bytes(batch.bytes(4)?.try_into().unwrap());
+
+    for i in 0..count {
+      let kind = batch.bytes(1)?[0];
+      let key = batch.slice()?;
+
+      match kind {
+        0 => f(key, sequence + u64::from(i), None)?,
+        1 => f(key, sequence + u64::from(i), Some(batch.slice()?))?,
+        kind => bail!("unknown LevelDB record type {kind}"),
+      }
+    }
+
+    Ok(())
+  }
+
+  fn table(table: &[u8], f: &mut impl FnMut(&[u8], u64, Option<&[u8]>) -> Result) -> Result {
+    let footer = table
+      .len()
+      .checked_sub(FOOTER_LEN)
+      .map(|start| &table[start..])
+      .ok_or_else(|| anyhow!("table is too short"))?;
+
+    if u64::from_le_bytes(footer[FOOTER_LEN - 8..].try_into().unwrap()) != TABLE_MAGIC {
+      bail!("table has invalid magic");
+    }
+
+    let mut footer = Reader(footer);
+    footer.handle()?;
+    let index = footer.handle()?;
+
+    Self::entries(&Self::block(table, index)?, |_, handle| {
+      Self::entries(
+        &Self::block(table, Reader(handle).handle()?)?,
+        |key, value| {
+          let split = key
+            .len()
+            .checked_sub(8)
+            .ok_or_else(|| anyhow!("table key is too short"))?;
+
+          let trailer = u64::from_le_bytes(key[split..].try_into().unwrap());
+
+          match trailer & 0xff {
+            0 => f(&key[..split], trailer >> 8, None),
+            1 => f(&key[..split], trailer >> 8, Some(value)),
+            kind => bail!("unknown LevelDB record type {kind}"),
+          }
+        },
+      )
+    })
+  }
+
+  fn block(table: &[u8], (offset, size): (usize, usize)) -> Result<Cow<'_, [u8]>> {
+    let contents = table
+      .get(offset..offset + size + 1)
+      .ok_or_else(|| anyhow!("table block is out of bounds"))?;
+
+    match contents[size] {
+      0 => Ok(Cow::Borrowed(&contents[..size])),
+      1 => Ok(Cow::Owned(snappy(&contents[..size])?)),
+      kind => bail!("unknown LevelDB compression type {kind}"),
+    }
+  }
+
+  fn entries(block: &[u8], mut f: impl FnMut(&[u8], &[u8]) -> Result) -> Result {
+    let restarts = block
+      .len()
+      .checked_sub(4)
+      .map(|start| u32::from_le_bytes(block[start..].try_into().unwrap()))
+      .ok_or_else(|| anyhow!("table block is too short"))?;
+
+    let end = usize::try_from(restarts)
+      .ok()
+      .and_then(|restarts| block.len().checked_sub(4 + restarts * 4))
+      .ok_or_else(|| anyhow!("table block has too many restarts"))?;
+
+    let mut entries = Reader(&block[..end]);
+    let mut key = Vec::new();
+
+    while !entries.is_empty() {
+      let shared = entries.varint()?;
+      let unshared = entries.varint()?;
+      let len = entries.varint()?;
+
+      key.truncate(shared.try_into().unwrap());
+      key.extend_from_slice(entries.bytes(unshared.try_into().unwrap())?);
+
+      f(&key, entries.bytes(len.try_into().unwrap())?)?;
+    }
+
+    Ok(())
+  }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+  fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+    if n > self.0.len() {
+      bail!("unexpected end of LevelDB record");
+    }
+
+    let (bytes, rest) = self.0.split_at(n);
+    self.0 = rest;
+    Ok(bytes)
+  }
+
+  fn varint(&mut self) -> Result<u64> {
+    let mut n = 0;
+
+    for shift in (0..64).step_by(7) {
+      let byte = self.bytes(1)?[0];
+      n |= u64::from(byte & 0x7f) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(n);
+      }
+    }
+
+    bail!("LevelDB varint is too long")
+  }
+
+  fn slice(&mut self) -> Result<&'a [u8]> {
+    let len = self.varint()?;
+    self.bytes(len.try_into()?)
+  }
+
+  fn handle(&mut self) -> Result<(usize, usize)> {
+    Ok((self.varint()?.try_into()?, self.varint()?.try_into()?))
+  }
+}
+
+fn snappy(compressed: &[u8]) -> Result<Vec<u8>> {
+  let mut compressed = Reader(compressed);
+
+  let len = usize::try_from(compressed.varint()?)?;
+
+  let mut output = Vec::with_capacity(len);
+
+  while !compressed.is_empty() {
+    let tag = compressed.bytes(1)?[0];
+
+    let (len, offset) = match tag & 3 {
+      0 => {
+        let len = match usize::from(tag >> 2) {
+          len @ 0..=59 => len,
+          n => {
+            let mut len = [0; 8];
+            len[..n - 59].copy_from_slice(compressed.bytes(n - 59)?);
+            usize::try_from(u64::from_le_bytes(len))?
+          }
+        };
+
+        output.extend_from_slice(compressed.bytes(len + 1)?);
+        continue;
+      }
+      1 => (
+        4 + usize::from((tag >> 2) & 7),
+        usize::from(tag >> 5) << 8 | usize::from(compressed.bytes(1)?[0]),
+      ),
+      2 => (
+        usize::from(tag >> 2) + 1,
+        usize::from(u16::from_le_bytes(compressed.bytes(2)?.try_into().unwrap())),
+      ),
+      _ => (
+        usize::from(tag >> 2) + 1,
+        usize::try_from(u32::from_le_bytes(compressed.bytes(4)?.try_into().unwrap()))?,
+      ),
+    };
+
+    if offset == 0 || offset > output.len() {
+      bail!("snappy copy offset {offset} is out of bounds");
+    }
+
+    // copies can overlap their own output
+    for _ in 0..len {
+      output.push(output[output.len() - offset]);
+    }
+  }
+
+  if output.len() != len {
+    bail!(
+      "snappy block decompressed to {} bytes instead of {len}",
+      output.len()
+    );
+  }
+
+  Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn varint(mut n: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    while n >= 0x80 {
+      bytes.push(u8::try_from(n & 0x7f).unwrap() | 0x80);
+      n >>= 7;
+    }
+    bytes.push(n.try_into().unwrap());
+    bytes
+  }
+
+  fn records(
+    f: impl FnOnce(&mut dyn FnMut(&[u8], u64, Option<&[u8]>) -> Result) -> Result,
+  ) -> Vec<(Vec<u8>, u64, Option<Vec<u8>>)> {
+    let mut records = Vec::new();
+    f(&mut |key, sequence, value| {
+      records.push((key.to_vec(), sequence, value.map(|value| value.to_vec())));
+      Ok(())
+    })
+    .unwrap();
+    records
+  }
+
+  #[test]
+  fn snappy_copies_literals_and_overlapping_back_references() {
+    assert_eq!(
+      snappy(&[
+        10,
+        // literal "ab"
+        1 << 2,
+        b'a',
+        b'b',
+        // copy 6 bytes from offset 2
+        1 | (2 << 2),
+        2,
+        // literal "c"
+        0,
+        b'c',
+        // copy 1 byte from offset 1, with a two-byte offset
+        2,
+        1,
+        0,
+      ])
+      .unwrap(),
+      b"abababab"
+        .iter()
+        .chain(b"cc")
+        .copied()
+        .collect::<Vec<u8>>()
+    );
+
+    assert!(snappy(&[4, 1, 1]).is_err());
+    assert!(snappy(&[3, 0, b'a']).is_err());
+  }
+
+  #[test]
+  fn records_spanning_log_blocks_are_reassembled() {
+    let record = vec![7; LOG_BLOCK_SIZE];
+
+    let mut log = Vec::new();
+    for (kind, data) in [
+      (FIRST, &record[..LOG_BLOCK_SIZE - LOG_HEADER_LEN]),
+      (LAST, &record[LOG_BLOCK_SIZE - LOG_HEADER_LEN..]),
+      (FULL, &[1, 2, 3][..]),
+    ] {
+      log.extend([0; 4]);
+      log.extend(u16::try_from(data.len()).unwrap().to_le_bytes());
+      log.push(kind);
+      log.extend(data);
+    }
+
+    let end = log.len();
+
+    // a record still being written
+    log.extend([0, 0, 0, 0, 100, 0, FULL, 1]);
+
+    let mut records = Vec::new();
+
+    assert_eq!(
+      LevelDb::log(&log, 0, |record| {
+        records.push(record.to_vec());
+        Ok(())
+      })
+      .unwrap(),
+      end
+    );
+
+    assert_eq!(records, [record, vec![1, 2, 3]]);
+  }
+
+  #[test]
+  fn batches_assign_consecutive_sequence_numbers() {
+    let mut batch = Vec::new();
+    batch.extend(5u64.to_le_bytes());
+    batch.extend(2u32.to_le_bytes());
+    batch.extend([1, 1, b'a', 1, b'b']);
+    batch.extend([0, 1, b'c']);
+
+    assert_eq!(
+      records(|f| LevelDb::batch(&batch, &mut |key, sequence, value| f(key, sequence, value))),
+      [
+        (b"a".to_vec(), 5, Some(b"b".to_vec())),
+        (b"c".to_vec(), 6, None),
+      ]
+    );
+  }
+
+  #[test]
+  fn tables_are_read() {
+    fn block(entries: &[(&[u8], &[u8])], compress: bool) -> Vec<u8> {
+      let mut block = Vec::new();
+      let mut previous: &[u8] = &[];
+      for (key, value) in entries {
+        let shared = key.iter().zip(previous).take_while(|(a, b)| a == b).count();
+        block.extend(varint(shared as u64));
+        block.extend(varint((key.len() - shared) as u64));
+        block.extend(varint(value.len() as u64));
+        block.extend(&key[shared..]);
+        block.extend(*value);
+        previous = key;
+      }
+      block.extend(0u32.to_le_bytes());
+      block.extend(1u32.to_le_bytes());
+
+      if compress {
+        // a single literal
+        let mut compressed = varint(block.len() as u64);
+        compressed.push(60 << 2);
+        compressed.push((block.len() - 1).try_into().unwrap());
+        compressed.extend(block);
+        compressed.push(1);
+        compressed
+      } else {
+        block.push(0);
+        block
+      }
+    }
+
+    fn key(user: &[u8], sequence: u64, kind: u64) -> Vec<u8> {
+      let mut key = user.to_vec();
+      key.extend(((sequence << 8) | kind).to_le_bytes());
+      key
+    }
+
+    let mut table = Vec::new();
+
+    let data = block(&[(&key(b"aa", 3, 1), b"x"), (&key(b"ab", 4, 0), b"")], true);
+    table.extend(&data);
+
+    let mut handle = varint(0);
+    handle.extend(varint((data.len() - 1) as u64));
+
+    let index_offset = table.len();
+    let index = block(&[(&key(b"ab", 4, 0), &handle)], false);
+    table.extend(&index);
+
+    let mut footer = varint(0);
+    footer.extend(varint(0));
+    footer.extend(varint(index_offset as u64));
+    footer.extend(varint((index.len() - 1) as u64));
+    footer.resize(FOOTER_LEN - 8, 0);
+    footer.extend(TABLE_MAGIC.to_le_bytes());
+    table.extend(footer);
+
+    assert_eq!(
+      records(|f| LevelDb::table(&table, &mut |key, sequence, value| f(key, sequence, value))),
+      [
+        (b"aa".to_vec(), 3, Some(b"x".to_vec())),
+        (b"ab".to_vec(), 4, None),
+      ]
+    );
+
+    *table.last_mut().unwrap() = 0;
+
+    assert_eq!(
+      LevelDb::table(&table, &mut |_, _, _| Ok(()))
+        .unwrap_err()
+        .to_string(),
+      "table has invalid magic"
+    );
+  }
+
+  #[test]
+  fn manifest_tracks_live_tables_and_logs() {
+    let mut log = Vec::new();
+
+    for edit in [
+      [&[2][..], &varint(3), &[7, 0, 4, 100, 0, 0]].concat(),
+      [&[7, 1, 5, 100, 0, 0][..], &[6, 0, 4], &[2, 6]].concat(),
+    ] {
+      log.extend([0; 4]);
+      log.extend(u16::try_from(edit.len()).unwrap().to_le_bytes());
+      log.push(FULL);
+      log.extend(edit);
+    }
+
+    assert_eq!(
+      LevelDb::manifest(&log).unwrap(),
+      (6, [5].into_iter().collect())
+    );
+  }
+}
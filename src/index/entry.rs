@@ -85,6 +85,59 @@ impl Entry for InscriptionId {
   }
 }
 
+pub(crate) struct TransferEntry {
+  pub(crate) height: u64,
+  pub(crate) inscription_id: InscriptionId,
+  pub(crate) lost: bool,
+  pub(crate) new_satpoint: SatPoint,
+  pub(crate) old_satpoint: SatPoint,
+  pub(crate) timestamp: u32,
+  pub(crate) txid: Txid,
+}
+
+pub(super) type TransferEntryValue = [u8; 169];
+
+impl Entry for TransferEntry {
+  type Value = TransferEntryValue;
+
+  fn load(value: Self::Value) -> Self {
+    let (inscription_id, rest) = value.split_at(36);
+    let (height, rest) = rest.split_at(8);
+    let (timestamp, rest) = rest.split_at(4);
+    let (txid, rest) = rest.split_at(32);
+    let (old_satpoint, rest) = rest.split_at(44);
+    let (new_satpoint, lost) = rest.split_at(44);
+
+    Self {
+      height: u64::from_be_bytes(height.try_into().unwrap()),
+      inscription_id: InscriptionId::load(inscription_id.try_into().unwrap()),
+      lost: lost[0] != 0,
+      new_satpoint: SatPoint::load(new_satpoint.try_into().unwrap()),
+      old_satpoint: SatPoint::load(old_satpoint.try_into().unwrap()),
+      timestamp: u32::from_be_bytes(timestamp.try_into().unwrap()),
+      txid: Txid::from_inner(txid.try_into().unwrap()),
+    }
+  }
+
+  fn store(self) -> Self::Value {
+    let mut value = [0; 169];
+    let (inscription_id, rest) = value.split_at_mut(36);
+    let (height, rest) = rest.split_at_mut(8);
+    let (timestamp, rest) = rest.split_at_mut(4);
+    let (txid, rest) = rest.split_at_mut(32);
+    let (old_satpoint, rest) = rest.split_at_mut(44);
+    let (new_satpoint, lost) = rest.split_at_mut(44);
+    inscription_id.copy_from_slice(&self.inscription_id.store());
+    height.copy_from_slice(&self.height.to_be_bytes());
+    timestamp.copy_from_slice(&self.timestamp.to_be_bytes());
+    txid.copy_from_slice(self.txid.as_inner());
+    old_satpoint.copy_from_slice(&self.old_satpoint.store());
+    new_satpoint.copy_from_slice(&self.new_satpoint.store());
+    lost[0] = self.lost.into();
+    value
+  }
+}
+
 pub(super) type OutPointValue = [u8; 36];
 
 impl Entry for OutPoint {
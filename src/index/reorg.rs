@@ -0,0 +1,488 @@
+use {super::*, std::cell::RefCell};
+
+/// Undo logs are kept for blocks within this many blocks of the chain tip,
+/// so reorgs no deeper than this are rolled back instead of requiring the
+/// index to be rebuilt.
+pub(crate) const MAX_REORG_DEPTH: u64 = 6;
+
+const CREATED: u8 = 0;
+const REPLACED: u8 = 1;
+const INSERTED: u8 = 2;
+const REMOVED: u8 = 3;
+
+#[derive(Debug)]
+pub(crate) struct Reorg {
+  pub(crate) height: u64,
+}
+
+impl Display for Reorg {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "reorg detected at or before {}", self.height)
+  }
+}
+
+impl std::error::Error for Reorg {}
+
+impl Reorg {
+  /// Undo indexed blocks that are no longer in the best chain, read from the
+  /// block files when indexing them and from Bitcoin Core otherwise, so that
+  /// indexing can resume from the fork point.
+  pub(crate) fn rollback(&self, index: &Index) -> Result {
+    let wtx = index.begin_write()?;
+
+    let mut rolled_back = 0;
+
+    loop {
+      let mut height_to_block_hash = wtx.open_table(HEIGHT_TO_BLOCK_HASH)?;
+
+      let Some((height, hash)) = height_to_block_hash
+        .range(0..)?
+        .next_back()
+        .map(|(height, hash)| (height.value(), BlockHash::load(*hash.value())))
+      else {
+        break;
+      };
+
+      let best = match &index.block_files {
+        Some(block_files) => block_files.lock().unwrap().block_hash(height),
+        None => index.client.get_block_hash(height).into_option()?,
+      };
+
+      if best == Some(hash) {
+        break;
+      }
+
+      let Some(undo_log) = wtx
+        .open_table(HEIGHT_TO_UNDO_LOG)?
+        .remove(&height)?
+        .map(|undo_log| undo_log.value().to_vec())
+      else {
+        index.reorged.store(true, atomic::Ordering::Relaxed);
+        bail!(
+          "{self} and deeper than the {MAX_REORG_DEPTH} blocks that can be rolled back, please rebuild the index"
+        );
+      };
+
+      UndoLog::apply(&wtx, &undo_log)?;
+
+      height_to_block_hash.remove(&height)?;
+
+      rolled_back += 1;
+    }
+
+    // The reorg was detected in blocks that were never committed, so the
+    // indexed chain already agrees with the best chain and updating again
+    // will index the new blocks.
+    if rolled_back == 0 {
+      log::info!("{self}, no committed blocks to roll back");
+      return Ok(());
+    }
+
+    Index::increment_statistic(&wtx, Statistic::Reorgs, 1)?;
+
+    wtx.commit()?;
+
+    log::info!("{self}, rolled back {rolled_back} blocks");
+
+    Ok(())
+  }
+}
+
+macro_rules! undo_tables {
+  ($($table:ident),* $(,)?) => {
+    #[derive(Copy, Clone, Debug)]
+    #[repr(u8)]
+    pub(super) enum UndoTable {
+      $($table),*
+    }
+
+    impl UndoTable {
+      const ALL: &'static [Self] = &[$(Self::$table),*];
+    }
+  };
+}
+
+undo_tables! {
+  ContentHashToInscriptionNumbers,
+  InscriptionIdToBody,
+  InscriptionIdToContentType,
+  InscriptionIdToInscriptionEntry,
+  InscriptionIdToSatpoint,
+  InscriptionIdToTransferNumber,
+  InscriptionNumberToInscriptionId,
+  InscriptionNumberToTermCount,
+  OutpointToSatRanges,
+  OutpointToScriptPubkey,
+  OutpointToValue,
+  SatpointToInscriptionId,
+  SatToInscriptionId,
+  SatToSatpoint,
+  ScriptPubkeyToOutpoints,
+  StatisticToCount,
+  TermToInscriptionNumberAndFrequency,
+  TransferNumberToTransferEntry,
+}
+
+pub(super) trait UndoBytes {
+  fn undo_bytes(&self) -> Vec<u8>;
+}
+
+impl UndoBytes for u32 {
+  fn undo_bytes(&self) -> Vec<u8> {
+    self.to_le_bytes().to_vec()
+  }
+}
+
+impl UndoBytes for u64 {
+  fn undo_bytes(&self) -> Vec<u8> {
+    self.to_le_bytes().to_vec()
+  }
+}
+
+impl<const N: usize> UndoBytes for [u8; N] {
+  fn undo_bytes(&self) -> Vec<u8> {
+    self.to_vec()
+  }
+}
+
+impl UndoBytes for [u8] {
+  fn undo_bytes(&self) -> Vec<u8> {
+    self.to_vec()
+  }
+}
+
+impl UndoBytes for str {
+  fn undo_bytes(&self) -> Vec<u8> {
+    self.as_bytes().to_vec()
+  }
+}
+
+impl UndoBytes for (u64, u32) {
+  fn undo_bytes(&self) -> Vec<u8> {
+    [self.0.undo_bytes(), self.1.undo_bytes()].concat()
+  }
+}
+
+impl UndoBytes for InscriptionEntryValue {
+  fn undo_bytes(&self) -> Vec<u8> {
+    [
+      self.0.undo_bytes(),
+      self.1.undo_bytes(),
+      self.2.undo_bytes(),
+      self.3.undo_bytes(),
+      self.4.undo_bytes(),
+    ]
+    .concat()
+  }
+}
+
+/// Changes made to the index while indexing a block, recorded so that they
+/// can be undone if the block is reorged out.
+///
+/// Changes made to the sat range and output value caches are recorded as if
+/// they had been made to the tables those caches are flushed to, since undo
+/// logs are only applied to committed blocks, after the caches are flushed.
+pub(super) struct UndoLog(Option<RefCell<Vec<u8>>>);
+
+impl UndoLog {
+  pub(super) fn new(enabled: bool) -> Self {
+    Self(enabled.then(|| RefCell::new(Vec::new())))
+  }
+
+  /// `key` was inserted into `table`, where it did not exist before.
+  pub(super) fn created(&self, table: UndoTable, key: &(impl UndoBytes + ?Sized)) {
+    self.record(table, CREATED, key, None);
+  }
+
+  /// `key` was inserted into or removed from `table`, where it was previously
+  /// set to `value`, or did not exist if `value` is `None`.
+  pub(super) fn replaced(
+    &self,
+    table: UndoTable,
+    key: &(impl UndoBytes + ?Sized),
+    value: Option<&(impl UndoBytes + ?Sized)>,
+  ) {
+    match value {
+      Some(value) => self.record(table, REPLACED, key, Some(value.undo_bytes().as_slice())),
+      None => self.record(table, CREATED, key, None),
+    }
+  }
+
+  /// `value` was inserted under `key` into multimap `table`.
+  pub(super) fn inserted(
+    &self,
+    table: UndoTable,
+    key: &(impl UndoBytes + ?Sized),
+    value: &(impl UndoBytes + ?Sized),
+  ) {
+    self.record(table, INSERTED, key, Some(value.undo_bytes().as_slice()));
+  }
+
+  /// `value` was removed from under `key` in multimap `table`.
+  pub(super) fn removed(
+    &self,
+    table: UndoTable,
+    key: &(impl UndoBytes + ?Sized),
+    value: &(impl UndoBytes + ?Sized),
+  ) {
+    self.record(table, REMOVED, key, Some(value.undo_bytes().as_slice()));
+  }
+
+  fn record(
+    &self,
+    table: UndoTable,
+    kind: u8,
+    key: &(impl UndoBytes + ?Sized),
+    value: Option<&[u8]>,
+  ) {
+    let Some(log) = &self.0 else {
+      return;
+    };
+
+    let mut log = log.borrow_mut();
+
+    log.push(table as u8);
+    log.push(kind);
+
+    for bytes in std::iter::once(key.undo_bytes().as_slice()).chain(value) {
+      log.extend_from_slice(&u32::try_from(bytes.len()).unwrap().to_le_bytes());
+      log.extend_from_slice(bytes);
+    }
+  }
+
+  pub(super) fn into_bytes(self) -> Option<Vec<u8>> {
+    self.0.map(RefCell::into_inner)
+  }
+
+  fn apply(wtx: &WriteTransaction, mut log: &[u8]) -> Result {
+    fn take<'a>(log: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+      if log.len() < n {
+        bail!("truncated undo log");
+      }
+
+      let (bytes, rest) = log.split_at(n);
+      *log = rest;
+      Ok(bytes)
+    }
+
+    fn take_bytes<'a>(log: &mut &'a [u8]) -> Result<&'a [u8]> {
+      let len = u32::from_le_bytes(take(log, 4)?.try_into().unwrap());
+      take(log, len.try_into().unwrap())
+    }
+
+    let mut changes = Vec::new();
+
+    while !log.is_empty() {
+      let header = take(&mut log, 2)?;
+
+      let table = *UndoTable::ALL
+        .get(usize::from(header[0]))
+        .ok_or_else(|| anyhow!("invalid undo log table {}", header[0]))?;
+
+      let kind = header[1];
+
+      let key = take_bytes(&mut log)?;
+
+      let value = if kind == CREATED {
+        None
+      } else {
+        Some(take_bytes(&mut log)?)
+      };
+
+      changes.push(Change {
+        kind,
+        key,
+        table,
+        value,
+      });
+    }
+
+    for change in changes.into_iter().rev() {
+      change.undo(wtx)?;
+    }
+
+    Ok(())
+  }
+}
+
+struct Change<'a> {
+  kind: u8,
+  key: &'a [u8],
+  table: UndoTable,
+  value: Option<&'a [u8]>,
+}
+
+macro_rules! undo_table {
+  ($wtx:expr, $change:expr, $table:ident, $key:expr, $value:expr) => {{
+    let mut table = $wtx.open_table($table)?;
+    match $change.value {
+      Some(value) => {
+        table.insert($key($change.key)?, $value(value)?)?;
+      }
+      None => {
+        table.remove($key($change.key)?)?;
+      }
+    }
+  }};
+}
+
+macro_rules! undo_multimap_table {
+  ($wtx:expr, $change:expr, $table:ident, $key:expr, $value:expr) => {{
+    let mut table = $wtx.open_multimap_table($table)?;
+    let value = $change
+      .value
+      .ok_or_else(|| anyhow!("undo log multimap change without value"))?;
+    match $change.kind {
+      INSERTED => {
+        table.remove($key($change.key)?, $value(value)?)?;
+      }
+      REMOVED => {
+        table.insert($key($change.key)?, $value(value)?)?;
+      }
+      kind => bail!("invalid undo log multimap change {kind}"),
+    }
+  }};
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> Result<&[u8; N]> {
+  Ok(bytes.try_into()?)
+}
+
+fn bytes(bytes: &[u8]) -> Result<&[u8]> {
+  Ok(bytes)
+}
+
+fn inscription_entry(bytes: &[u8]) -> Result<InscriptionEntryValue> {
+  let bytes = array::<36>(bytes)?;
+
+  Ok((
+    u64_value(&bytes[0..8])?,
+    u64_value(&bytes[8..16])?,
+    u64_value(&bytes[16..24])?,
+    u64_value(&bytes[24..32])?,
+    u32_value(&bytes[32..36])?,
+  ))
+}
+
+fn number_and_frequency(bytes: &[u8]) -> Result<(u64, u32)> {
+  let bytes = array::<12>(bytes)?;
+
+  Ok((u64_value(&bytes[0..8])?, u32_value(&bytes[8..12])?))
+}
+
+fn u32_value(bytes: &[u8]) -> Result<u32> {
+  Ok(u32::from_le_bytes(*array(bytes)?))
+}
+
+fn u64_value(bytes: &[u8]) -> Result<u64> {
+  Ok(u64::from_le_bytes(*array(bytes)?))
+}
+
+impl Change<'_> {
+  fn undo(self, wtx: &WriteTransaction) -> Result {
+    match self.table {
+      UndoTable::ContentHashToInscriptionNumbers => undo_multimap_table!(
+        wtx,
+        self,
+        CONTENT_HASH_TO_INSCRIPTION_NUMBERS,
+        array::<32>,
+        u64_value
+      ),
+      UndoTable::InscriptionIdToBody => {
+        undo_table!(wtx, self, INSCRIPTION_ID_TO_BODY, array::<36>, bytes)
+      }
+      UndoTable::InscriptionIdToContentType => {
+        undo_table!(
+          wtx,
+          self,
+          INSCRIPTION_ID_TO_CONTENT_TYPE,
+          array::<36>,
+          bytes
+        )
+      }
+      UndoTable::InscriptionIdToInscriptionEntry => undo_table!(
+        wtx,
+        self,
+        INSCRIPTION_ID_TO_INSCRIPTION_ENTRY,
+        array::<36>,
+        inscription_entry
+      ),
+      UndoTable::InscriptionIdToSatpoint => {
+        undo_table!(
+          wtx,
+          self,
+          INSCRIPTION_ID_TO_SATPOINT,
+          array::<36>,
+          array::<44>
+        )
+      }
+      UndoTable::InscriptionIdToTransferNumber => undo_multimap_table!(
+        wtx,
+        self,
+        INSCRIPTION_ID_TO_TRANSFER_NUMBER,
+        array::<36>,
+        u64_value
+      ),
+      UndoTable::InscriptionNumberToInscriptionId => undo_table!(
+        wtx,
+        self,
+        INSCRIPTION_NUMBER_TO_INSCRIPTION_ID,
+        u64_value,
+        array::<36>
+      ),
+      UndoTable::InscriptionNumberToTermCount => {
+        undo_table!(
+          wtx,
+          self,
+          INSCRIPTION_NUMBER_TO_TERM_COUNT,
+          u64_value,
+          u32_value
+        )
+      }
+      UndoTable::OutpointToSatRanges => {
+        undo_table!(wtx, self, OUTPOINT_TO_SAT_RANGES, array::<36>, bytes)
+      }
+      UndoTable::OutpointToScriptPubkey => {
+        undo_table!(wtx, self, OUTPOINT_TO_SCRIPT_PUBKEY, array::<36>, bytes)
+      }
+      UndoTable::OutpointToValue => {
+        undo_table!(wtx, self, OUTPOINT_TO_VALUE, array::<36>, u64_value)
+      }
+      UndoTable::SatpointToInscriptionId => {
+        undo_table!(
+          wtx,
+          self,
+          SATPOINT_TO_INSCRIPTION_ID,
+          array::<44>,
+          array::<36>
+        )
+      }
+      UndoTable::SatToInscriptionId => {
+        undo_table!(wtx, self, SAT_TO_INSCRIPTION_ID, u64_value, array::<36>)
+      }
+      UndoTable::SatToSatpoint => undo_table!(wtx, self, SAT_TO_SATPOINT, u64_value, array::<44>),
+      UndoTable::ScriptPubkeyToOutpoints => {
+        undo_multimap_table!(wtx, self, SCRIPT_PUBKEY_TO_OUTPOINTS, bytes, array::<36>)
+      }
+      UndoTable::StatisticToCount => {
+        undo_table!(wtx, self, STATISTIC_TO_COUNT, u64_value, u64_value)
+      }
+      UndoTable::TermToInscriptionNumberAndFrequency => undo_multimap_table!(
+        wtx,
+        self,
+        TERM_TO_INSCRIPTION_NUMBER_AND_FREQUENCY,
+        std::str::from_utf8,
+        number_and_frequency
+      ),
+      UndoTable::TransferNumberToTransferEntry => undo_table!(
+        wtx,
+        self,
+        TRANSFER_NUMBER_TO_TRANSFER_ENTRY,
+        u64_value,
+        array::<169>
+      ),
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,139 @@
+use super::*;
+
+/// Terms longer than this, like base64 blobs, are not indexed.
+const MAX_TERM_LENGTH: usize = 64;
+
+/// BM25 term frequency saturation and document length normalization.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Splits text into lowercase runs of alphanumeric characters.
+pub(crate) fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+  text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|term| !term.is_empty() && term.chars().count() <= MAX_TERM_LENGTH)
+    .map(str::to_lowercase)
+}
+
+/// The frequency of each term in `text`, and the total number of terms.
+pub(crate) fn terms(text: &str) -> (BTreeMap<String, u32>, u32) {
+  let mut terms = BTreeMap::new();
+  let mut length = 0u32;
+
+  for term in tokenize(text) {
+    *terms.entry(term).or_default() += 1;
+    length = length.saturating_add(1);
+  }
+
+  (terms, length)
+}
+
+pub(crate) struct Corpus {
+  pub(crate) documents: u64,
+  pub(crate) terms: u64,
+}
+
+impl Corpus {
+  /// Okapi BM25 score contribution of a term occurring `frequency` times in a
+  /// document `length` terms long, and in `matches` documents overall.
+  pub(crate) fn score(&self, frequency: u32, length: u32, matches: u64) -> f64 {
+    let documents = self.documents as f64;
+    let matches = matches as f64;
+    let frequency = f64::from(frequency);
+
+    let average_length = if self.documents == 0 {
+      0.0
+    } else {
+      self.terms as f64 / documents
+    };
+
+    let normalization = if average_length == 0.0 {
+      1.0
+    } else {
+      1.0 - B + B * f64::from(length) / average_length
+    };
+
+    let idf = ((documents - matches + 0.5) / (matches + 0.5) + 1.0).ln();
+
+    idf * frequency * (K1 + 1.0) / (frequency + K1 * normalization)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tokenize_splits_on_non_alphanumerics() {
+    assert_eq!(
+      tokenize("Hello, world! ordinals.com 2023-02-01 Ünïcode").collect::<Vec<String>>(),
+      [
+        "hello",
+        "world",
+        "ordinals",
+        "com",
+        "2023",
+        "02",
+        "01",
+        "ünïcode"
+      ]
+    );
+  }
+
+  #[test]
+  fn tokenize_skips_long_terms() {
+    assert_eq!(
+      tokenize(&format!("foo {} bar", "a".repeat(65))).collect::<Vec<String>>(),
+      ["foo", "bar"]
+    );
+    assert_eq!(tokenize(&"a".repeat(64)).count(), 1);
+  }
+
+  #[test]
+  fn terms_are_counted() {
+    let (terms, length) = terms("foo bar FOO");
+    assert_eq!(terms["foo"], 2);
+    assert_eq!(terms["bar"], 1);
+    assert_eq!(length, 3);
+  }
+
+  #[test]
+  fn rarer_terms_score_higher() {
+    let corpus = Corpus {
+      documents: 100,
+      terms: 1000,
+    };
+    assert!(corpus.score(1, 10, 1) > corpus.score(1, 10, 50));
+  }
+
+  #[test]
+  fn frequent_terms_score_higher_with_diminishing_returns() {
+    let corpus = Corpus {
+      documents: 100,
+      terms: 1000,
+    };
+    let one = corpus.score(1, 10, 10);
+    let two = corpus.score(2, 10, 10);
+    let three = corpus.score(3, 10, 10);
+    assert!(two > one);
+    assert!(three - two < two - one);
+  }
+
+  #[test]
+  fn shorter_documents_score_higher() {
+    let corpus = Corpus {
+      documents: 100,
+      terms: 1000,
+    };
+    assert!(corpus.score(1, 5, 10) > corpus.score(1, 50, 10));
+  }
+
+  #[test]
+  fn scores_are_positive() {
+    let corpus = Corpus {
+      documents: 1,
+      terms: 1,
+    };
+    assert!(corpus.score(1, 1, 1) > 0.0);
+  }
+}
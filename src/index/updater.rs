@@ -1,6 +1,10 @@
 use {
   self::inscription_updater::InscriptionUpdater,
-  super::{fetcher::Fetcher, *},
+  super::{
+    fetcher::Fetcher,
+    reorg::{UndoLog, UndoTable, MAX_REORG_DEPTH},
+    *,
+  },
   futures::future::try_join_all,
   std::sync::mpsc,
   tokio::sync::mpsc::{error::TryRecvError, Receiver, Sender},
@@ -29,14 +33,23 @@ impl From<Block> for BlockData {
   }
 }
 
+enum Source {
+  Files(Arc<Mutex<BlockFiles>>),
+  Rpc(Client),
+}
+
 pub(crate) struct Updater {
   range_cache: HashMap<OutPointValue, Vec<u8>>,
   height: u64,
+  index_addresses: bool,
+  index_content: bool,
   index_sats: bool,
+  index_text: bool,
   sat_ranges_since_flush: u64,
   outputs_cached: u64,
   outputs_inserted_since_flush: u64,
   outputs_traversed: u64,
+  undo_height: u64,
 }
 
 impl Updater {
@@ -64,11 +77,15 @@ impl Updater {
     let mut updater = Self {
       range_cache: HashMap::new(),
       height,
+      index_addresses: index.has_address_index()?,
+      index_content: index.has_content_index()?,
       index_sats: index.has_sat_index()?,
+      index_text: index.has_text_index()?,
       sat_ranges_since_flush: 0,
       outputs_cached: 0,
       outputs_inserted_since_flush: 0,
       outputs_traversed: 0,
+      undo_height: 0,
     };
 
     updater.update_index(index, wtx)
@@ -79,7 +96,16 @@ impl Updater {
     index: &'index Index,
     mut wtx: WriteTransaction<'index>,
   ) -> Result {
-    let starting_height = index.client.get_block_count()? + 1;
+    let starting_height = match &index.block_files {
+      Some(block_files) => {
+        let mut block_files = block_files.lock().unwrap();
+        block_files.scan()?;
+        block_files.block_count()
+      }
+      None => index.client.get_block_count()? + 1,
+    };
+
+    self.undo_height = starting_height.saturating_sub(MAX_REORG_DEPTH);
 
     let mut progress_bar = if cfg!(test)
       || log_enabled!(log::Level::Info)
@@ -96,7 +122,7 @@ impl Updater {
       Some(progress_bar)
     };
 
-    let rx = Self::fetch_blocks_from(index, self.height, self.index_sats)?;
+    let rx = Self::fetch_blocks_from(index, self.height, self.index_sats || self.index_addresses)?;
 
     let (mut outpoint_sender, mut value_receiver) = Self::spawn_fetcher(index)?;
 
@@ -109,7 +135,6 @@ impl Updater {
       };
 
       self.index_block(
-        index,
         &mut outpoint_sender,
         &mut value_receiver,
         &mut wtx,
@@ -178,14 +203,18 @@ impl Updater {
   fn fetch_blocks_from(
     index: &Index,
     mut height: u64,
-    index_sats: bool,
+    full_blocks: bool,
   ) -> Result<mpsc::Receiver<BlockData>> {
     let (tx, rx) = mpsc::sync_channel(32);
 
     let height_limit = index.height_limit;
 
-    let client =
-      Client::new(&index.rpc_url, index.auth.clone()).context("failed to connect to RPC URL")?;
+    let source = match &index.block_files {
+      Some(block_files) => Source::Files(block_files.clone()),
+      None => Source::Rpc(
+        Client::new(&index.rpc_url, index.auth.clone()).context("failed to connect to RPC URL")?,
+      ),
+    };
 
     let first_inscription_height = index.first_inscription_height;
 
@@ -196,7 +225,17 @@ impl Updater {
         }
       }
 
-      match Self::get_block_with_retries(&client, height, index_sats, first_inscription_height) {
+      let block = match &source {
+        // Input values can't be looked up by txid in the block files, so every
+        // block is read in full to keep the value of every unspent output in
+        // the index.
+        Source::Files(block_files) => block_files.lock().unwrap().block(height, true),
+        Source::Rpc(client) => {
+          Self::get_block_with_retries(client, height, full_blocks, first_inscription_height)
+        }
+      };
+
+      match block {
         Ok(Some(block)) => {
           if let Err(err) = tx.send(block.into()) {
             log::info!("Block receiver disconnected: {err}");
@@ -218,7 +257,7 @@ impl Updater {
   fn get_block_with_retries(
     client: &Client,
     height: u64,
-    index_sats: bool,
+    full_blocks: bool,
     first_inscription_height: u64,
   ) -> Result<Option<Block>> {
     let mut errors = 0;
@@ -229,7 +268,7 @@ impl Updater {
         .and_then(|option| {
           option
             .map(|hash| {
-              if index_sats || height >= first_inscription_height {
+              if full_blocks || height >= first_inscription_height {
                 Ok(client.get_block(&hash)?)
               } else {
                 Ok(Block {
@@ -262,7 +301,20 @@ impl Updater {
   }
 
   fn spawn_fetcher(index: &Index) -> Result<(Sender<OutPoint>, Receiver<u64>)> {
-    let fetcher = Fetcher::new(&index.rpc_url, index.auth.clone())?;
+    // Indexes read from block files hold the value of every output created
+    // since they were built from the first block, and only need Bitcoin Core
+    // for values missing from indexes built over RPC, so if it can't be
+    // reached the channels are left disconnected.
+    let fetcher = match Fetcher::new(&index.rpc_url, index.auth.clone()) {
+      Ok(fetcher) => fetcher,
+      Err(err) if index.block_files.is_some() => {
+        log::warn!("{err:#}, output values missing from the index can't be fetched");
+        let (outpoint_sender, _) = tokio::sync::mpsc::channel::<OutPoint>(1);
+        let (_, value_receiver) = tokio::sync::mpsc::channel::<u64>(1);
+        return Ok((outpoint_sender, value_receiver));
+      }
+      Err(err) => return Err(err),
+    };
 
     // Not sure if any block has more than 20k inputs, but none so far after first inscription block
     const CHANNEL_BUFFER_SIZE: usize = 20_000;
@@ -329,7 +381,6 @@ impl Updater {
 
   fn index_block(
     &mut self,
-    index: &Index,
     outpoint_sender: &mut Sender<OutPoint>,
     value_receiver: &mut Receiver<u64>,
     wtx: &mut WriteTransaction,
@@ -338,7 +389,7 @@ impl Updater {
   ) -> Result<()> {
     // If value_receiver still has values something went wrong with the last block
     // Could be an assert, shouldn't recover from this and commit the last block
-    let Err(TryRecvError::Empty) = value_receiver.try_recv() else {
+    let Err(TryRecvError::Empty | TryRecvError::Disconnected) = value_receiver.try_recv() else {
       return Err(anyhow!("Previous block did not consume all input values")); 
     };
 
@@ -372,6 +423,12 @@ impl Updater {
           if outpoint_to_value.get(&prev_output.store())?.is_some() {
             continue;
           }
+          if outpoint_sender.is_closed() {
+            bail!(
+              "value of output {prev_output} spent in block {} is not in the index and can't be fetched from Bitcoin Core",
+              self.height
+            );
+          }
           // We don't know the value of this tx input. Send this outpoint to background thread to be fetched
           outpoint_sender.blocking_send(prev_output)?;
         }
@@ -397,36 +454,79 @@ impl Updater {
       let prev_hash = height_to_block_hash.get(&prev_height)?.unwrap();
 
       if prev_hash.value() != block.header.prev_blockhash.as_ref() {
-        index.reorged.store(true, atomic::Ordering::Relaxed);
-        return Err(anyhow!("reorg detected at or before {prev_height}"));
+        return Err(
+          Reorg {
+            height: prev_height,
+          }
+          .into(),
+        );
       }
     }
 
+    let undo = UndoLog::new(self.height >= self.undo_height);
+
+    let mut content_hash_to_inscription_numbers =
+      wtx.open_multimap_table(CONTENT_HASH_TO_INSCRIPTION_NUMBERS)?;
     let mut inscription_id_to_inscription_entry =
       wtx.open_table(INSCRIPTION_ID_TO_INSCRIPTION_ENTRY)?;
     let mut inscription_id_to_satpoint = wtx.open_table(INSCRIPTION_ID_TO_SATPOINT)?;
+    let mut inscription_id_to_transfer_number =
+      wtx.open_multimap_table(INSCRIPTION_ID_TO_TRANSFER_NUMBER)?;
     let mut inscription_number_to_inscription_id =
       wtx.open_table(INSCRIPTION_NUMBER_TO_INSCRIPTION_ID)?;
     let mut sat_to_inscription_id = wtx.open_table(SAT_TO_INSCRIPTION_ID)?;
     let mut satpoint_to_inscription_id = wtx.open_table(SATPOINT_TO_INSCRIPTION_ID)?;
     let mut statistic_to_count = wtx.open_table(STATISTIC_TO_COUNT)?;
+    let mut transfer_number_to_transfer_entry =
+      wtx.open_table(TRANSFER_NUMBER_TO_TRANSFER_ENTRY)?;
 
-    let mut lost_sats = statistic_to_count
+    let previous_lost_sats = statistic_to_count
       .get(&Statistic::LostSats.key())?
-      .map(|lost_sats| lost_sats.value())
-      .unwrap_or(0);
+      .map(|lost_sats| lost_sats.value());
+
+    let mut lost_sats = previous_lost_sats.unwrap_or(0);
+
+    let mut inscription_id_to_body = if self.index_content {
+      Some(wtx.open_table(INSCRIPTION_ID_TO_BODY)?)
+    } else {
+      None
+    };
+    let mut inscription_id_to_content_type = if self.index_content {
+      Some(wtx.open_table(INSCRIPTION_ID_TO_CONTENT_TYPE)?)
+    } else {
+      None
+    };
+
+    let mut inscription_number_to_term_count = if self.index_text {
+      Some(wtx.open_table(INSCRIPTION_NUMBER_TO_TERM_COUNT)?)
+    } else {
+      None
+    };
+    let mut term_to_inscription_number_and_frequency = if self.index_text {
+      Some(wtx.open_multimap_table(TERM_TO_INSCRIPTION_NUMBER_AND_FREQUENCY)?)
+    } else {
+      None
+    };
 
     let mut inscription_updater = InscriptionUpdater::new(
+      &undo,
+      &mut content_hash_to_inscription_numbers,
       self.height,
+      inscription_id_to_body.as_mut(),
+      inscription_id_to_content_type.as_mut(),
       &mut inscription_id_to_satpoint,
+      &mut inscription_id_to_transfer_number,
       value_receiver,
       &mut inscription_id_to_inscription_entry,
       lost_sats,
       &mut inscription_number_to_inscription_id,
+      inscription_number_to_term_count.as_mut(),
       &mut outpoint_to_value,
       &mut sat_to_inscription_id,
       &mut satpoint_to_inscription_id,
+      term_to_inscription_number_and_frequency.as_mut(),
       block.header.time,
+      &mut transfer_number_to_transfer_entry,
       value_cache,
     )?;
 
@@ -463,6 +563,12 @@ impl Updater {
               .to_vec(),
           };
 
+          undo.replaced(
+            UndoTable::OutpointToSatRanges,
+            &key,
+            Some(sat_ranges.as_slice()),
+          );
+
           for chunk in sat_ranges.chunks_exact(11) {
             input_sat_ranges.push_back(SatRange::load(chunk.try_into().unwrap()));
           }
@@ -476,6 +582,7 @@ impl Updater {
           &mut sat_ranges_written,
           &mut outputs_in_block,
           &mut inscription_updater,
+          &undo,
         )?;
 
         coinbase_inputs.extend(input_sat_ranges);
@@ -490,17 +597,34 @@ impl Updater {
           &mut sat_ranges_written,
           &mut outputs_in_block,
           &mut inscription_updater,
+          &undo,
         )?;
       }
 
       if !coinbase_inputs.is_empty() {
-        let mut lost_sat_ranges = outpoint_to_sat_ranges
+        let previous_lost_sat_ranges = outpoint_to_sat_ranges
           .remove(&OutPoint::null().store())?
-          .map(|ranges| ranges.value().to_vec())
-          .unwrap_or_default();
+          .map(|ranges| ranges.value().to_vec());
+
+        undo.replaced(
+          UndoTable::OutpointToSatRanges,
+          &OutPoint::null().store(),
+          previous_lost_sat_ranges.as_deref(),
+        );
+
+        let mut lost_sat_ranges = previous_lost_sat_ranges.unwrap_or_default();
 
         for (start, end) in coinbase_inputs {
           if !Sat(start).is_common() {
+            undo.replaced(
+              UndoTable::SatToSatpoint,
+              &start,
+              sat_to_satpoint
+                .get(&start)?
+                .map(|satpoint| *satpoint.value())
+                .as_ref(),
+            );
+
             sat_to_satpoint.insert(
               &start,
               &SatPoint {
@@ -524,10 +648,72 @@ impl Updater {
       }
     }
 
+    undo.replaced(
+      UndoTable::StatisticToCount,
+      &Statistic::LostSats.key(),
+      previous_lost_sats.as_ref(),
+    );
+
     statistic_to_count.insert(&Statistic::LostSats.key(), &lost_sats)?;
 
+    if self.index_addresses {
+      let mut outpoint_to_script_pubkey = wtx.open_table(OUTPOINT_TO_SCRIPT_PUBKEY)?;
+      let mut script_pubkey_to_outpoints = wtx.open_multimap_table(SCRIPT_PUBKEY_TO_OUTPOINTS)?;
+
+      for (tx, txid) in &block.txdata {
+        Self::index_transaction_addresses(
+          tx,
+          *txid,
+          &mut outpoint_to_script_pubkey,
+          &mut script_pubkey_to_outpoints,
+          &undo,
+        )?;
+      }
+    }
+
+    if self.index_text {
+      for (statistic, n) in [
+        (
+          Statistic::TextInscriptions,
+          inscription_updater.text_inscriptions,
+        ),
+        (Statistic::TextTerms, inscription_updater.text_terms),
+      ] {
+        let count = statistic_to_count
+          .get(&statistic.key())?
+          .map(|count| count.value());
+
+        undo.replaced(
+          UndoTable::StatisticToCount,
+          &statistic.key(),
+          count.as_ref(),
+        );
+
+        statistic_to_count.insert(&statistic.key(), &(count.unwrap_or(0) + n))?;
+      }
+    }
+
     height_to_block_hash.insert(&self.height, &block.header.block_hash().store())?;
 
+    {
+      let mut height_to_undo_log = wtx.open_table(HEIGHT_TO_UNDO_LOG)?;
+
+      if let Some(undo_log) = undo.into_bytes() {
+        height_to_undo_log.insert(&self.height, undo_log.as_slice())?;
+      }
+
+      if let Some(stale) = self.height.checked_sub(MAX_REORG_DEPTH) {
+        let stale = height_to_undo_log
+          .range(0..=stale)?
+          .map(|(height, _undo_log)| height.value())
+          .collect::<Vec<u64>>();
+
+        for height in stale {
+          height_to_undo_log.remove(&height)?;
+        }
+      }
+    }
+
     self.height += 1;
     self.outputs_traversed += outputs_in_block;
 
@@ -539,6 +725,65 @@ impl Updater {
     Ok(())
   }
 
+  /// Transactions are indexed in block order, so outputs spent later in the
+  /// same block are added before they are removed.
+  fn index_transaction_addresses(
+    tx: &Transaction,
+    txid: Txid,
+    outpoint_to_script_pubkey: &mut Table<&OutPointValue, &[u8]>,
+    script_pubkey_to_outpoints: &mut MultimapTable<&[u8], &OutPointValue>,
+    undo: &UndoLog,
+  ) -> Result {
+    for input in &tx.input {
+      let outpoint = input.previous_output.store();
+
+      let Some(script_pubkey) = outpoint_to_script_pubkey
+        .remove(&outpoint)?
+        .map(|script_pubkey| script_pubkey.value().to_vec())
+      else {
+        continue;
+      };
+
+      undo.replaced(
+        UndoTable::OutpointToScriptPubkey,
+        &outpoint,
+        Some(script_pubkey.as_slice()),
+      );
+
+      if script_pubkey_to_outpoints.remove(script_pubkey.as_slice(), &outpoint)? {
+        undo.removed(
+          UndoTable::ScriptPubkeyToOutpoints,
+          script_pubkey.as_slice(),
+          &outpoint,
+        );
+      }
+    }
+
+    for (vout, output) in tx.output.iter().enumerate() {
+      if output.script_pubkey.is_provably_unspendable() {
+        continue;
+      }
+
+      let outpoint = OutPoint {
+        vout: vout.try_into().unwrap(),
+        txid,
+      }
+      .store();
+
+      outpoint_to_script_pubkey.insert(&outpoint, output.script_pubkey.as_bytes())?;
+      script_pubkey_to_outpoints.insert(output.script_pubkey.as_bytes(), &outpoint)?;
+
+      undo.created(UndoTable::OutpointToScriptPubkey, &outpoint);
+      undo.inserted(
+        UndoTable::ScriptPubkeyToOutpoints,
+        output.script_pubkey.as_bytes(),
+        &outpoint,
+      );
+    }
+
+    Ok(())
+  }
+
   fn index_transaction_sats(
     &mut self,
     tx: &Transaction,
@@ -548,6 +793,7 @@ impl Updater {
     sat_ranges_written: &mut u64,
     outputs_traversed: &mut u64,
     inscription_updater: &mut InscriptionUpdater,
+    undo: &UndoLog,
   ) -> Result {
     inscription_updater.index_transaction_inscriptions(tx, txid, Some(input_sat_ranges))?;
 
@@ -565,6 +811,15 @@ impl Updater {
           .ok_or_else(|| anyhow!("insufficient inputs for transaction outputs"))?;
 
         if !Sat(range.0).is_common() {
+          undo.replaced(
+            UndoTable::SatToSatpoint,
+            &range.0,
+            sat_to_satpoint
+              .get(&range.0)?
+              .map(|satpoint| *satpoint.value())
+              .as_ref(),
+          );
+
           sat_to_satpoint.insert(
             &range.0,
             &SatPoint {
@@ -595,6 +850,8 @@ impl Updater {
 
       *outputs_traversed += 1;
 
+      undo.created(UndoTable::OutpointToSatRanges, &outpoint.store());
+
       self.range_cache.insert(outpoint.store(), sats);
       self.outputs_inserted_since_flush += 1;
     }
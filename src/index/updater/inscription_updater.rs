@@ -7,39 +7,65 @@ pub(super) struct Flotsam {
 }
 
 enum Origin {
-  New(u64),
-  Old(SatPoint),
+  New {
+    content_hash: Option<sha256::Hash>,
+    fee: u64,
+    terms: Option<(BTreeMap<String, u32>, u32)>,
+  },
+  Old {
+    satpoint: SatPoint,
+    txid: Txid,
+  },
 }
 
 pub(super) struct InscriptionUpdater<'a, 'db, 'tx> {
+  undo: &'a UndoLog,
+  content_hash_to_numbers: &'a mut MultimapTable<'db, 'tx, &'static [u8; 32], u64>,
   flotsam: Vec<Flotsam>,
   height: u64,
+  id_to_body: Option<&'a mut Table<'db, 'tx, &'static InscriptionIdValue, &'static [u8]>>,
+  id_to_content_type: Option<&'a mut Table<'db, 'tx, &'static InscriptionIdValue, &'static [u8]>>,
   id_to_satpoint: &'a mut Table<'db, 'tx, &'static InscriptionIdValue, &'static SatPointValue>,
+  id_to_transfer_number: &'a mut MultimapTable<'db, 'tx, &'static InscriptionIdValue, u64>,
   value_receiver: &'a mut Receiver<u64>,
   id_to_entry: &'a mut Table<'db, 'tx, &'static InscriptionIdValue, InscriptionEntryValue>,
   lost_sats: u64,
   next_number: u64,
+  next_transfer_number: u64,
   number_to_id: &'a mut Table<'db, 'tx, u64, &'static InscriptionIdValue>,
+  number_to_term_count: Option<&'a mut Table<'db, 'tx, u64, u32>>,
   outpoint_to_value: &'a mut Table<'db, 'tx, &'static OutPointValue, u64>,
   reward: u64,
   sat_to_inscription_id: &'a mut Table<'db, 'tx, u64, &'static InscriptionIdValue>,
   satpoint_to_id: &'a mut Table<'db, 'tx, &'static SatPointValue, &'static InscriptionIdValue>,
+  term_to_number_and_frequency: Option<&'a mut MultimapTable<'db, 'tx, &'static str, (u64, u32)>>,
+  pub(super) text_inscriptions: u64,
+  pub(super) text_terms: u64,
   timestamp: u32,
+  transfer_number_to_entry: &'a mut Table<'db, 'tx, u64, &'static TransferEntryValue>,
   value_cache: &'a mut HashMap<OutPoint, u64>,
 }
 
 impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
   pub(super) fn new(
+    undo: &'a UndoLog,
+    content_hash_to_numbers: &'a mut MultimapTable<'db, 'tx, &'static [u8; 32], u64>,
     height: u64,
+    id_to_body: Option<&'a mut Table<'db, 'tx, &'static InscriptionIdValue, &'static [u8]>>,
+    id_to_content_type: Option<&'a mut Table<'db, 'tx, &'static InscriptionIdValue, &'static [u8]>>,
     id_to_satpoint: &'a mut Table<'db, 'tx, &'static InscriptionIdValue, &'static SatPointValue>,
+    id_to_transfer_number: &'a mut MultimapTable<'db, 'tx, &'static InscriptionIdValue, u64>,
     value_receiver: &'a mut Receiver<u64>,
     id_to_entry: &'a mut Table<'db, 'tx, &'static InscriptionIdValue, InscriptionEntryValue>,
     lost_sats: u64,
     number_to_id: &'a mut Table<'db, 'tx, u64, &'static InscriptionIdValue>,
+    number_to_term_count: Option<&'a mut Table<'db, 'tx, u64, u32>>,
     outpoint_to_value: &'a mut Table<'db, 'tx, &'static OutPointValue, u64>,
     sat_to_inscription_id: &'a mut Table<'db, 'tx, u64, &'static InscriptionIdValue>,
     satpoint_to_id: &'a mut Table<'db, 'tx, &'static SatPointValue, &'static InscriptionIdValue>,
+    term_to_number_and_frequency: Option<&'a mut MultimapTable<'db, 'tx, &'static str, (u64, u32)>>,
     timestamp: u32,
+    transfer_number_to_entry: &'a mut Table<'db, 'tx, u64, &'static TransferEntryValue>,
     value_cache: &'a mut HashMap<OutPoint, u64>,
   ) -> Result<Self> {
     let next_number = number_to_id
@@ -49,20 +75,38 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
       .next()
       .unwrap_or(0);
 
+    let next_transfer_number = transfer_number_to_entry
+      .iter()?
+      .rev()
+      .map(|(number, _entry)| number.value() + 1)
+      .next()
+      .unwrap_or(0);
+
     Ok(Self {
+      undo,
+      content_hash_to_numbers,
       flotsam: Vec::new(),
       height,
+      id_to_body,
+      id_to_content_type,
       id_to_satpoint,
+      id_to_transfer_number,
       value_receiver,
       id_to_entry,
       lost_sats,
       next_number,
+      next_transfer_number,
       number_to_id,
+      number_to_term_count,
       outpoint_to_value,
       reward: Height(height).subsidy(),
       sat_to_inscription_id,
       satpoint_to_id,
+      term_to_number_and_frequency,
+      text_inscriptions: 0,
+      text_terms: 0,
       timestamp,
+      transfer_number_to_entry,
       value_cache,
     })
   }
@@ -86,16 +130,29 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
           inscriptions.push(Flotsam {
             offset: input_value + old_satpoint.offset,
             inscription_id,
-            origin: Origin::Old(old_satpoint),
+            origin: Origin::Old {
+              satpoint: old_satpoint,
+              txid,
+            },
           });
         }
 
         input_value += if let Some(value) = self.value_cache.remove(&tx_in.previous_output) {
+          self.undo.replaced(
+            UndoTable::OutpointToValue,
+            &tx_in.previous_output.store(),
+            Some(&value),
+          );
           value
         } else if let Some(value) = self
           .outpoint_to_value
           .remove(&tx_in.previous_output.store())?
         {
+          self.undo.replaced(
+            UndoTable::OutpointToValue,
+            &tx_in.previous_output.store(),
+            Some(&value.value()),
+          );
           value.value()
         } else {
           self.value_receiver.blocking_recv().ok_or_else(|| {
@@ -108,14 +165,47 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
       }
     }
 
-    if inscriptions.iter().all(|flotsam| flotsam.offset != 0)
-      && Inscription::from_transaction(tx).is_some()
-    {
-      inscriptions.push(Flotsam {
-        inscription_id: txid.into(),
-        offset: 0,
-        origin: Origin::New(input_value - tx.output.iter().map(|txout| txout.value).sum::<u64>()),
-      });
+    if inscriptions.iter().all(|flotsam| flotsam.offset != 0) {
+      if let Some(inscription) = Inscription::from_transaction(tx) {
+        let inscription_id = InscriptionId::from(txid);
+
+        if let Some(id_to_body) = &mut self.id_to_body {
+          if let Some(body) = inscription.body() {
+            id_to_body.insert(&inscription_id.store(), body)?;
+            self
+              .undo
+              .created(UndoTable::InscriptionIdToBody, &inscription_id.store());
+          }
+        }
+
+        if let Some(id_to_content_type) = &mut self.id_to_content_type {
+          if let Some(content_type) = inscription.content_type_bytes() {
+            id_to_content_type.insert(&inscription_id.store(), content_type)?;
+            self.undo.created(
+              UndoTable::InscriptionIdToContentType,
+              &inscription_id.store(),
+            );
+          }
+        }
+
+        let terms = (self.term_to_number_and_frequency.is_some()
+          && inscription.media() == Media::Text)
+          .then(|| {
+            text::terms(&String::from_utf8_lossy(
+              inscription.body().unwrap_or_default(),
+            ))
+          });
+
+        inscriptions.push(Flotsam {
+          inscription_id,
+          offset: 0,
+          origin: Origin::New {
+            content_hash: inscription.content_hash(),
+            fee: input_value - tx.output.iter().map(|txout| txout.value).sum::<u64>(),
+            terms,
+          },
+        });
+      }
     };
 
     let is_coinbase = tx
@@ -157,13 +247,16 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
 
       output_value = end;
 
-      self.value_cache.insert(
-        OutPoint {
-          vout: vout.try_into().unwrap(),
-          txid,
-        },
-        tx_out.value,
-      );
+      let outpoint = OutPoint {
+        vout: vout.try_into().unwrap(),
+        txid,
+      };
+
+      self
+        .undo
+        .created(UndoTable::OutpointToValue, &outpoint.store());
+
+      self.value_cache.insert(outpoint, tx_out.value);
     }
 
     if is_coinbase {
@@ -195,14 +288,118 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
     let inscription_id = flotsam.inscription_id.store();
 
     match flotsam.origin {
-      Origin::Old(old_satpoint) => {
+      Origin::Old {
+        satpoint: old_satpoint,
+        txid,
+      } => {
         self.satpoint_to_id.remove(&old_satpoint.store())?;
+
+        self.undo.replaced(
+          UndoTable::SatpointToInscriptionId,
+          &old_satpoint.store(),
+          Some(&inscription_id),
+        );
+
+        self.undo.replaced(
+          UndoTable::InscriptionIdToSatpoint,
+          &inscription_id,
+          Some(&old_satpoint.store()),
+        );
+
+        self
+          .id_to_transfer_number
+          .insert(&inscription_id, &self.next_transfer_number)?;
+
+        self.undo.inserted(
+          UndoTable::InscriptionIdToTransferNumber,
+          &inscription_id,
+          &self.next_transfer_number,
+        );
+
+        self.transfer_number_to_entry.insert(
+          &self.next_transfer_number,
+          &TransferEntry {
+            height: self.height,
+            inscription_id: flotsam.inscription_id,
+            lost: new_satpoint.outpoint.txid != txid,
+            new_satpoint,
+            old_satpoint,
+            timestamp: self.timestamp,
+            txid,
+          }
+          .store(),
+        )?;
+
+        self.undo.created(
+          UndoTable::TransferNumberToTransferEntry,
+          &self.next_transfer_number,
+        );
+
+        self.next_transfer_number += 1;
       }
-      Origin::New(fee) => {
+      Origin::New {
+        content_hash,
+        fee,
+        terms,
+      } => {
         self
           .number_to_id
           .insert(&self.next_number, &inscription_id)?;
 
+        self.undo.created(
+          UndoTable::InscriptionNumberToInscriptionId,
+          &self.next_number,
+        );
+
+        self
+          .undo
+          .created(UndoTable::InscriptionIdToInscriptionEntry, &inscription_id);
+
+        self
+          .undo
+          .created(UndoTable::InscriptionIdToSatpoint, &inscription_id);
+
+        if let Some(content_hash) = content_hash {
+          self
+            .content_hash_to_numbers
+            .insert(&content_hash.into_inner(), &self.next_number)?;
+
+          self.undo.inserted(
+            UndoTable::ContentHashToInscriptionNumbers,
+            &content_hash.into_inner(),
+            &self.next_number,
+          );
+        }
+
+        if let (
+          Some((terms, length)),
+          Some(term_to_number_and_frequency),
+          Some(number_to_term_count),
+        ) = (
+          terms,
+          &mut self.term_to_number_and_frequency,
+          &mut self.number_to_term_count,
+        ) {
+          for (term, frequency) in &terms {
+            term_to_number_and_frequency.insert(term.as_str(), (self.next_number, *frequency))?;
+
+            self.undo.inserted(
+              UndoTable::TermToInscriptionNumberAndFrequency,
+              term.as_str(),
+              &(self.next_number, *frequency),
+            );
+          }
+
+          number_to_term_count.insert(&self.next_number, &length)?;
+
+          self
+            .undo
+            .created(UndoTable::InscriptionNumberToTermCount, &self.next_number);
+
+          self.text_inscriptions += 1;
+          self.text_terms += u64::from(length);
+        }
+
         let mut sat = None;
         if let Some(input_sat_ranges) = input_sat_ranges {
           let mut offset = 0;
@@ -210,6 +407,17 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
             let size = end - start;
             if offset + size > flotsam.offset {
               let n = start + flotsam.offset - offset;
+
+              self.undo.replaced(
+                UndoTable::SatToInscriptionId,
+                &n,
+                self
+                  .sat_to_inscription_id
+                  .get(&n)?
+                  .map(|inscription_id| *inscription_id.value())
+                  .as_ref(),
+              );
+
               self.sat_to_inscription_id.insert(&n, &inscription_id)?;
               sat = Some(Sat(n));
               break;
@@ -236,6 +444,16 @@ impl<'a, 'db, 'tx> InscriptionUpdater<'a, 'db, 'tx> {
 
     let new_satpoint = new_satpoint.store();
 
+    self.undo.replaced(
+      UndoTable::SatpointToInscriptionId,
+      &new_satpoint,
+      self
+        .satpoint_to_id
+        .get(&new_satpoint)?
+        .map(|inscription_id| *inscription_id.value())
+        .as_ref(),
+    );
+
     self.satpoint_to_id.insert(&new_satpoint, &inscription_id)?;
     self.id_to_satpoint.insert(&inscription_id, &new_satpoint)?;
 
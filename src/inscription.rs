@@ -5,6 +5,7 @@ use {
       opcodes,
       script::{self, Instruction, Instructions},
     },
+    hashes::sha256,
     util::taproot::TAPROOT_ANNEX_PREFIX,
     Script, Witness,
   },
@@ -23,7 +24,6 @@ pub(crate) struct Inscription {
 }
 
 impl Inscription {
-  #[cfg(test)]
   pub(crate) fn new(content_type: Option<Vec<u8>>, body: Option<Vec<u8>>) -> Self {
     Self { content_type, body }
   }
@@ -98,10 +98,20 @@ impl Inscription {
     self.body
   }
 
+  /// The SHA-256 digest of the body, under which the index groups
+  /// inscriptions with identical content.
+  pub(crate) fn content_hash(&self) -> Option<sha256::Hash> {
+    Some(sha256::Hash::hash(self.body()?))
+  }
+
   pub(crate) fn content_length(&self) -> Option<usize> {
     Some(self.body()?.len())
   }
 
+  pub(crate) fn content_type_bytes(&self) -> Option<&[u8]> {
+    Some(self.content_type.as_ref()?)
+  }
+
   pub(crate) fn content_type(&self) -> Option<&str> {
     str::from_utf8(self.content_type.as_ref()?).ok()
   }
@@ -740,4 +750,17 @@ mod tests {
       Err(InscriptionError::UnrecognizedEvenField),
     );
   }
+
+  #[test]
+  fn content_hash_is_sha256_of_body() {
+    assert_eq!(
+      inscription("text/plain", "foo")
+        .content_hash()
+        .unwrap()
+        .to_string(),
+      "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"
+    );
+
+    assert_eq!(Inscription::new(None, None).content_hash(), None);
+  }
 }
@@ -13,6 +13,7 @@
 use {
   self::{
     arguments::Arguments,
+    block_source::BlockSource,
     blocktime::Blocktime,
     config::Config,
     decimal::Decimal,
@@ -20,7 +21,10 @@ use {
     deserialize_from_str::DeserializeFromStr,
     epoch::Epoch,
     height::Height,
-    index::{Index, List},
+    index::{
+      entry::{InscriptionEntry, TransferEntry},
+      Index, List, SCHEMA_VERSION,
+    },
     inscription::Inscription,
     inscription_id::InscriptionId,
     media::Media,
@@ -95,6 +99,7 @@ macro_rules! tprintln {
 }
 
 mod arguments;
+mod block_source;
 mod blocktime;
 mod chain;
 mod config;
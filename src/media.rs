@@ -71,6 +71,13 @@ impl Media {
     ))
   }
 
+  pub(crate) fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    Self::TABLE
+      .iter()
+      .find(|(table_content_type, _, _)| *table_content_type == content_type)
+      .and_then(|(_, _, extensions)| extensions.first().copied())
+  }
+
   pub(crate) fn check_mp4_codec(path: &Path) -> Result<(), Error> {
     let f = File::open(path)?;
     let size = f.metadata()?.len();
@@ -93,6 +100,24 @@ impl Media {
   }
 }
 
+impl Display for Media {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{}",
+      match self {
+        Self::Audio => "audio",
+        Self::Iframe => "iframe",
+        Self::Image => "image",
+        Self::Pdf => "pdf",
+        Self::Text => "text",
+        Self::Unknown => "unknown",
+        Self::Video => "video",
+      }
+    )
+  }
+}
+
 impl FromStr for Media {
   type Err = Error;
 
@@ -132,6 +157,17 @@ mod tests {
     );
   }
 
+  #[test]
+  fn extension_for_content_type() {
+    assert_eq!(Media::extension_for_content_type("image/jpeg"), Some("jpg"));
+    assert_eq!(
+      Media::extension_for_content_type("text/plain;charset=utf-8"),
+      Some("txt")
+    );
+    assert_eq!(Media::extension_for_content_type("image/avif"), None);
+    assert_eq!(Media::extension_for_content_type("foo/bar"), None);
+  }
+
   #[test]
   fn h264_in_mp4_is_allowed() {
     assert!(Media::check_mp4_codec(Path::new("examples/h264.mp4")).is_ok(),);
@@ -9,6 +9,13 @@ use {super::*, bitcoincore_rpc::Auth};
 pub(crate) struct Options {
   #[clap(long, help = "Load Bitcoin Core data dir from <BITCOIN_DATA_DIR>.")]
   pub(crate) bitcoin_data_dir: Option<PathBuf>,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "rpc",
+    help = "Fetch blocks from <BLOCK_SOURCE>. `files` follows the best valid chain in Bitcoin Core's block index and reads blocks from its blk*.dat files, without needing Bitcoin Core to be running unless the index was built over RPC."
+  )]
+  pub(crate) block_source: BlockSource,
   #[clap(
     long = "chain",
     arg_enum,
@@ -33,8 +40,23 @@ pub(crate) struct Options {
   pub(crate) height_limit: Option<u64>,
   #[clap(long, help = "Use index at <INDEX>.")]
   pub(crate) index: Option<PathBuf>,
+  #[clap(
+    long,
+    help = "Track unspent outputs by script pubkey, so inscriptions and outputs can be looked up by address."
+  )]
+  pub(crate) index_addresses: bool,
+  #[clap(
+    long,
+    help = "Store inscription content types and bodies, so inscriptions can be read without Bitcoin Core."
+  )]
+  pub(crate) index_content: bool,
   #[clap(long, help = "Track location of all satoshis.")]
   pub(crate) index_sats: bool,
+  #[clap(
+    long,
+    help = "Build a full-text search index over text inscription bodies."
+  )]
+  pub(crate) index_text: bool,
   #[clap(long, short, help = "Use regtest. Equivalent to `--chain regtest`.")]
   pub(crate) regtest: bool,
   #[clap(long, help = "Connect to Bitcoin Core RPC at <RPC_URL>.")]
@@ -87,6 +109,14 @@ impl Options {
       return Ok(cookie_file.clone());
     }
 
+    Ok(self.bitcoin_data_dir()?.join(".cookie"))
+  }
+
+  pub(crate) fn blocks_dir(&self) -> Result<PathBuf> {
+    Ok(self.bitcoin_data_dir()?.join("blocks"))
+  }
+
+  fn bitcoin_data_dir(&self) -> Result<PathBuf> {
     let path = if let Some(bitcoin_data_dir) = &self.bitcoin_data_dir {
       bitcoin_data_dir.clone()
     } else if cfg!(target_os = "linux") {
@@ -99,9 +129,7 @@ impl Options {
         .join("Bitcoin")
     };
 
-    let path = self.chain().join_with_data_dir(&path);
-
-    Ok(path.join(".cookie"))
+    Ok(self.chain().join_with_data_dir(&path))
   }
 
   pub(crate) fn data_dir(&self) -> Result<PathBuf> {
@@ -331,6 +359,47 @@ mod tests {
     }));
   }
 
+  #[test]
+  fn blocks_dir_is_in_bitcoin_data_dir() {
+    let arguments =
+      Arguments::try_parse_from(["ord", "--bitcoin-data-dir=foo", "--chain=signet", "index"])
+        .unwrap();
+
+    let blocks_dir = arguments
+      .options
+      .blocks_dir()
+      .unwrap()
+      .display()
+      .to_string();
+
+    assert!(blocks_dir.ends_with(if cfg!(windows) {
+      r"foo\signet\blocks"
+    } else {
+      "foo/signet/blocks"
+    }));
+  }
+
+  #[test]
+  fn block_source_defaults_to_rpc() {
+    assert_eq!(
+      Arguments::try_parse_from(["ord", "index"])
+        .unwrap()
+        .options
+        .block_source,
+      BlockSource::Rpc
+    );
+
+    assert_eq!(
+      Arguments::try_parse_from(["ord", "--block-source=files", "index"])
+        .unwrap()
+        .options
+        .block_source,
+      BlockSource::Files
+    );
+
+    Arguments::try_parse_from(["ord", "--block-source=foo", "index"]).unwrap_err();
+  }
+
   #[test]
   fn mainnet_data_dir() {
     let data_dir = Arguments::try_parse_from(["ord", "index"])
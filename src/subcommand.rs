@@ -1,17 +1,16 @@
-use std::io::BufWriter;
-use sha3::{Sha3_256, Digest};
-use rustc_serialize::hex::ToHex;
-use indicatif::{ProgressBar, ProgressStyle};
-
 use super::*;
 
+pub mod address;
 pub mod epochs;
+pub mod export;
 pub mod find;
+pub mod find_content;
 mod index;
 pub mod info;
 pub mod list;
 pub mod parse;
 mod preview;
+pub mod search_text;
 mod server;
 pub mod subsidy;
 pub mod supply;
@@ -26,12 +25,16 @@ fn print_json(output: impl Serialize) -> Result {
 
 #[derive(Debug, Parser)]
 pub(crate) enum Subcommand {
+  #[clap(about = "List unspent outputs and inscriptions of an address")]
+  Address(address::Address),
   #[clap(about = "List the first satoshis of each reward epoch")]
   Epochs,
   #[clap(about = "Run an explorer server populated with inscriptions")]
   Preview(preview::Preview),
   #[clap(about = "Find a satoshi's current location")]
   Find(find::Find),
+  #[clap(about = "Find inscriptions with the same content as a file")]
+  FindContent(find_content::FindContent),
   #[clap(about = "Update the index")]
   Index,
   #[clap(about = "Display index statistics")]
@@ -40,6 +43,8 @@ pub(crate) enum Subcommand {
   List(list::List),
   #[clap(about = "Parse a satoshi from ordinal notation")]
   Parse(parse::Parse),
+  #[clap(about = "Search text inscriptions, ranked by relevance")]
+  SearchText(search_text::SearchText),
   #[clap(about = "Display information about a block's subsidy")]
   Subsidy(subsidy::Subsidy),
   #[clap(about = "Run the explorer server")]
@@ -50,20 +55,23 @@ pub(crate) enum Subcommand {
   Traits(traits::Traits),
   #[clap(subcommand, about = "Wallet commands")]
   Wallet(wallet::Wallet),
-  #[clap(about = "Export text records to a csv file")]
-  Export,
+  #[clap(about = "Export inscriptions to csv, json or jsonl")]
+  Export(Box<export::Export>),
 }
 
 impl Subcommand {
   pub(crate) fn run(self, options: Options) -> Result {
     match self {
+      Self::Address(address) => address.run(options),
       Self::Epochs => epochs::run(),
       Self::Preview(preview) => preview.run(),
       Self::Find(find) => find.run(options),
+      Self::FindContent(find_content) => find_content.run(options),
       Self::Index => index::run(options),
       Self::Info(info) => info.run(options),
       Self::List(list) => list.run(options),
       Self::Parse(parse) => parse.run(),
+      Self::SearchText(search_text) => search_text.run(options),
       Self::Subsidy(subsidy) => subsidy.run(),
       Self::Server(server) => {
         let index = Arc::new(Index::open(&options)?);
@@ -74,66 +82,7 @@ impl Subcommand {
       Self::Supply => supply::run(),
       Self::Traits(traits) => traits.run(),
       Self::Wallet(wallet) => wallet.run(options),
-      Self::Export => {
-        let index = Index::open(&options)?;
-        let file_name = chrono::offset::Utc::now().format("%d-%m-%Y_%H-%M.csv");
-        let file_name = format!("{file_name}");
-        let file = std::fs::File::create(file_name)?;
-        let buffer = BufWriter::new(file);
-        let mut csv = csv::Writer::from_writer(buffer);
-        csv.write_record(&["hash", "timestamp", "text", "link"])?;
-        let ref mut from = None;
-        let (_, prev, _) = index.get_latest_inscriptions_with_prev_and_next(1, None)?;
-        let progress_bar = ProgressBar::new(prev.expect("No inscriptions found."));
-        progress_bar.set_position(0);
-        progress_bar.set_style(
-          ProgressStyle::with_template("[exporting] {wide_bar} {pos}/{len}").unwrap(),
-        );
-        let mut seen = std::collections::hash_set::HashSet::new();
-        loop {
-          let (inscs, prev, _) = index.get_latest_inscriptions_with_prev_and_next(1000, *from)?;
-          if prev == None {
-            break;
-          }
-          *from = prev;
-          for insc in inscs {
-            let insc_data = index.get_inscription_by_id(insc)?;
-            let insc_time = index.get_inscription_entry(insc)?;
-            if let Some(data) = insc_data {
-              match data.media() {
-                Media::Text => {
-                  if let Some(text) = data.body() {
-                    let mut hasher = Sha3_256::new();
-                    hasher.update(text);
-                    let hash = hasher.finalize();
-                    let text = String::from_utf8_lossy(text).to_string();
-                    if seen.contains(&text) {
-                      continue;
-                    }
-                    seen.insert(text.clone());
-                    let datetime = chrono::NaiveDateTime::from_timestamp_millis(
-                      insc_time.unwrap().timestamp as i64 * 1000,
-                    )
-                    .unwrap();
-                    let datetime = DateTime::<Utc>::from_utc(datetime, Utc);
-                    csv.write_record(&[
-                      hash[..].to_hex(),
-                      datetime.to_rfc3339(),
-                      text,
-                      format!("https://ordinals.com/inscription/{insc}"),
-                    ])?;
-                  }
-                }
-                _ => { /* Ignore non-text records */ }
-              }
-            }
-            progress_bar.inc(1);
-          }
-          csv.flush()?;
-        }
-        progress_bar.finish_and_clear();
-        Ok(())
-      }
+      Self::Export(export) => export.run(options),
     }
   }
 }
@@ -0,0 +1,44 @@
+use super::*;
+
+#[derive(Debug, Parser)]
+pub(crate) struct Address {
+  #[clap(help = "List unspent outputs and inscriptions of <ADDRESS>.")]
+  address: bitcoin::Address,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub output: OutPoint,
+  pub value: u64,
+  pub inscriptions: Vec<InscriptionId>,
+}
+
+impl Address {
+  pub(crate) fn run(self, options: Options) -> Result {
+    if !self.address.is_valid_for_network(options.chain().network()) {
+      bail!(
+        "Address `{}` is not valid for {}",
+        self.address,
+        options.chain()
+      );
+    }
+
+    let index = Index::open(&options)?;
+
+    index.update()?;
+
+    let outputs = index
+      .get_outputs_by_script_pubkey(&self.address.script_pubkey())?
+      .into_iter()
+      .map(|(output, value)| {
+        Ok(Output {
+          output,
+          value,
+          inscriptions: index.get_inscriptions_on_output(output)?,
+        })
+      })
+      .collect::<Result<Vec<Output>>>()?;
+
+    print_json(outputs)
+  }
+}
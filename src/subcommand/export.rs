@@ -0,0 +1,1164 @@
+use {
+  self::{
+    bounds::{Bounds, Order},
+    checkpoint::Checkpoint,
+    column::{Column, Row},
+    compression::{Compression, Encoder},
+    dedupe::{Dedupe, Duplicates, Group, Keep},
+    digest::{normalize_text, Algorithm, DigestInput},
+    format::{Format, RecordWriter},
+    link::{Link, LinkRoute},
+    manifest::{Filters, Manifest},
+    media_filter::MediaFilter,
+    merkle::Merkle,
+    output::Output,
+    pipeline::Pipeline,
+    split::Split,
+    text::{InvalidUtf8, Structure},
+    text_filter::TextFilter,
+    verify::Verify,
+    walk::{Page, Walk},
+  },
+  super::*,
+  indicatif::{ProgressBar, ProgressStyle},
+  rustc_serialize::hex::ToHex,
+  serde_json::Value,
+  sha3::{Digest, Sha3_256},
+  std::{
+    borrow::Cow,
+    io::{BufWriter, Write},
+    num::NonZeroUsize,
+    rc::Rc,
+  },
+};
+
+mod bounds;
+mod checkpoint;
+mod column;
+mod compression;
+mod dedupe;
+mod digest;
+mod format;
+mod link;
+mod manifest;
+mod media_filter;
+mod merkle;
+mod output;
+mod pipeline;
+mod split;
+mod text;
+mod text_filter;
+mod verify;
+mod walk;
+
+#[derive(Debug, Parser)]
+pub(crate) struct Export {
+  #[clap(
+    long,
+    help = "Write export to <OUTPUT>, or to stdout if <OUTPUT> is `-`. [default: <DD-MM-YYYY_HH-MM>.<FORMAT>]"
+  )]
+  output: Option<PathBuf>,
+  #[clap(long, help = "Overwrite <OUTPUT> if it already exists.")]
+  force: bool,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "csv",
+    help = "Write records in <FORMAT>."
+  )]
+  format: Format,
+  #[clap(
+    long,
+    conflicts_with_all = &[
+      "bodies-dir",
+      "checkpoint",
+      "columns",
+      "dedupe",
+      "digest",
+      "digest-input",
+      "follow",
+      "invalid-utf8",
+      "jobs",
+      "keep",
+      "link-base",
+      "link-route",
+      "max-length",
+      "media",
+      "min-length",
+      "pattern",
+    ],
+    help = "Export the transfer ledger instead of inscriptions, one row of `number`, `id`, `height`, `timestamp`, `txid`, `old_satpoint`, `new_satpoint` and `lost` per transfer of an inscription after its reveal. Number bounds select the inscriptions transferred, and height and time bounds the transfers."
+  )]
+  transfers: bool,
+  #[clap(
+    long,
+    default_value = "text",
+    use_value_delimiter = true,
+    help = "Export inscriptions with <MEDIA>, either `all` or a comma-separated list of `audio`, `iframe`, `image`, `pdf`, `text`, `unknown` and `video`. Non-text bodies are written base64-encoded."
+  )]
+  media: Vec<MediaFilter>,
+  #[clap(
+    long,
+    help = "Write non-text inscription bodies to content-addressed files in <BODIES_DIR> instead of inlining them."
+  )]
+  bodies_dir: Option<PathBuf>,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "replace",
+    help = "Handle text bodies that aren't valid UTF-8 with <INVALID_UTF8>. `replace` exports them with invalid bytes replaced by U+FFFD, `skip` leaves them out, and `quarantine` exports them byte for byte like non-text bodies, base64-encoded or in <BODIES_DIR>."
+  )]
+  invalid_utf8: InvalidUtf8,
+  #[clap(flatten)]
+  text_filter: TextFilter,
+  #[clap(
+    long,
+    requires = "output",
+    help = "Record export progress in <CHECKPOINT> and append only newer inscriptions to <OUTPUT> on subsequent runs."
+  )]
+  checkpoint: Option<PathBuf>,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "ascending",
+    help = "Export inscriptions in <ORDER> of inscription number."
+  )]
+  order: Order,
+  #[clap(flatten)]
+  bounds: Bounds,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "text",
+    help = "De-duplicate inscriptions by <DEDUPE>. `text` compares text bodies ignoring line endings and surrounding whitespace and other bodies byte for byte, `hash` compares raw bodies and `none` keeps every inscription."
+  )]
+  dedupe: Dedupe,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "first",
+    help = "Keep the <KEEP> inscription, by number, of each set of duplicates. Keeping the copy not reached first in <ORDER>, or writing `duplicate_of` or `duplicate_count`, fetches every inscription twice, once to count duplicates and once to export them."
+  )]
+  keep: Keep,
+  #[clap(
+    long,
+    default_value = Column::DEFAULT,
+    use_value_delimiter = true,
+    help = "Write <COLUMNS>, a comma-separated list of `number`, `id`, `hash`, `timestamp`, `height`, `fee`, `sat`, `sat_name`, `rarity`, `satpoint`, `owner`, `media`, `content_type`, `content_length`, `utf8`, `structure`, `text`, `body`, `path`, `link`, `duplicate_of` and `duplicate_count`. Sat columns are empty unless the index was built with `--index-sats`, and `owner` unless it was built with `--index-addresses`."
+  )]
+  columns: Vec<Column>,
+  #[clap(
+    long,
+    help = "Link to inscriptions on the explorer at <LINK_BASE>. [default: ordinals.com for the selected chain, or http://localhost on regtest]"
+  )]
+  link_base: Option<String>,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "inscription",
+    help = "Link to the explorer's <LINK_ROUTE> route for each inscription."
+  )]
+  link_route: LinkRoute,
+  #[clap(
+    long,
+    arg_enum,
+    use_value_delimiter = true,
+    help = "Replace the `hash` column with one column per <DIGEST>, a comma-separated list of `sha256`, `sha3-256`, `sha512` and `sha3-512`. [default: SHA3-256 in the `hash` column]"
+  )]
+  digest: Vec<Algorithm>,
+  #[clap(
+    long,
+    arg_enum,
+    default_value = "body",
+    help = "Compute digests over the raw <DIGEST_INPUT> `body`, or over `text` decoded as UTF-8 with line endings normalized and surrounding whitespace removed. Non-text bodies are always digested raw."
+  )]
+  digest_input: DigestInput,
+  #[clap(
+    long,
+    help = "Keep running after exporting existing inscriptions, appending newly indexed inscriptions as blocks arrive, until interrupted."
+  )]
+  follow: bool,
+  #[clap(
+    long,
+    default_value = "5",
+    requires = "follow",
+    help = "Check for new blocks every <POLL_INTERVAL> seconds when following."
+  )]
+  poll_interval: u64,
+  #[clap(
+    long,
+    arg_enum,
+    help = "Compress output with <COMPRESS>. [default: inferred from a `.gz` or `.zst` <OUTPUT> extension]"
+  )]
+  compress: Option<Compression>,
+  #[clap(
+    long,
+    help = "Split output into numbered shards, each with its own header, of `rows:<N>` rows, approximately `bytes:<N>` bytes, or inscriptions revealed in `height:<N>` block buckets."
+  )]
+  split_by: Option<Split>,
+  #[clap(
+    long,
+    help = "Write a manifest of the chain, index tip, filters, row count, file digests and row Merkle root to <MANIFEST>. [default: <OUTPUT>.manifest.json, or none when writing to stdout]"
+  )]
+  manifest: Option<PathBuf>,
+  #[clap(
+    long,
+    help = "Fetch and parse inscriptions on <JOBS> worker threads. Output stays in inscription number order. [default: available parallelism]"
+  )]
+  jobs: Option<NonZeroUsize>,
+  #[clap(subcommand)]
+  mode: Option<Mode>,
+}
+
+#[derive(Debug, Parser)]
+pub(crate) enum Mode {
+  #[clap(about = "Check an export file against the index")]
+  Verify(Verify),
+}
+
+impl Export {
+  pub(crate) fn run(mut self, options: Options) -> Result {
+    if let Some(Mode::Verify(verify)) = self.mode.take() {
+      return verify.run(options);
+    }
+
+    self.check()?;
+
+    let duplicates = self.duplicates();
+
+    let checkpoint = match &self.checkpoint {
+      Some(path) => Checkpoint::load(path)?,
+      None => None,
+    };
+
+    let mut output = Output::new(&self, checkpoint.as_ref())?;
+
+    let manifest = self
+      .manifest
+      .clone()
+      .or_else(|| output.path().map(Manifest::default_path));
+
+    let index = Index::open(&options)?;
+
+    Self::update(&index)?;
+
+    if let Some(checkpoint) = &checkpoint {
+      checkpoint.verify(&index)?;
+    }
+
+    let link = Link::new(self.link_base.as_deref(), self.link_route, options.chain());
+
+    if self.transfers {
+      self.export_transfers(&index, &mut output, true)?;
+    } else {
+      self.export(
+        &index,
+        &mut output,
+        checkpoint,
+        duplicates,
+        &link,
+        options.chain(),
+        true,
+      )?;
+    }
+
+    let summary = output.finish()?;
+
+    if let Some(path) = manifest {
+      let height = index.height()?.map(|height| height.n());
+
+      Manifest {
+        ord_version: env!("CARGO_PKG_VERSION").into(),
+        chain: options.chain(),
+        schema_version: SCHEMA_VERSION,
+        height,
+        blockhash: index.block_hash(height)?,
+        filters: Filters::new(&self),
+        rows: summary.rows,
+        files: summary
+          .files
+          .iter()
+          .map(|file| Manifest::file(file))
+          .collect::<Result<Vec<_>>>()?,
+        merkle_root: summary.merkle.root(),
+      }
+      .save(&path)?;
+    }
+
+    Ok(())
+  }
+
+  /// Routes of `ord server` that stream exports, and the media each exports
+  /// unless overridden by a `media` query parameter.
+  const ROUTES: &[(&'static str, &'static str)] = &[("inscriptions", "all"), ("text", "text")];
+
+  /// Query parameters accepted by the export routes of `ord server`, each
+  /// setting the `ord export` option of the same name. Options that touch
+  /// files or keep running on the server host are left out.
+  const QUERY_PARAMETERS: &[&'static str] = &[
+    "columns",
+    "dedupe",
+    "digest",
+    "digest_input",
+    "from_height",
+    "from_number",
+    "ignore_case",
+    "invalid_utf8",
+    "keep",
+    "link_base",
+    "link_route",
+    "match",
+    "max_length",
+    "media",
+    "min_length",
+    "order",
+    "since",
+    "to_height",
+    "to_number",
+    "until",
+  ];
+
+  /// Parses an export route like `text.csv` and its query parameters with
+  /// the same options and checks as `ord export`, returning `None` for
+  /// unknown routes. A `media` query parameter replaces the route's media.
+  /// Route exports fetch inscriptions on a single worker thread, and can't
+  /// count duplicates before streaming.
+  pub(crate) fn from_route(name: &str, query: &BTreeMap<String, String>) -> Result<Option<Self>> {
+    let Some((route, extension)) = name.split_once('.') else {
+      return Ok(None);
+    };
+
+    let Some((_, media)) = Self::ROUTES.iter().find(|(name, _)| *name == route) else {
+      return Ok(None);
+    };
+
+    let Ok(format) = <Format as clap::ValueEnum>::from_str(extension, false) else {
+      return Ok(None);
+    };
+
+    let mut args = vec![
+      "export".to_string(),
+      format!("--format={}", format.extension()),
+      "--jobs=1".to_string(),
+    ];
+
+    if !query.contains_key("media") {
+      args.push(format!("--media={media}"));
+    }
+
+    for (key, value) in query {
+      if !Self::QUERY_PARAMETERS.contains(&key.as_str()) {
+        bail!("unknown query parameter `{key}`");
+      }
+
+      let flag = format!("--{}", key.replace('_', "-"));
+
+      if key == "ignore_case" {
+        match value.as_str() {
+          "" | "true" => args.push(flag),
+          "false" => {}
+          _ => bail!("invalid value `{value}` for query parameter `{key}`"),
+        }
+      } else {
+        args.push(format!("{flag}={value}"));
+      }
+    }
+
+    let mut export = Self::try_parse_from(args).map_err(|err| {
+      anyhow!(
+        "{}",
+        err
+          .to_string()
+          .lines()
+          .next()
+          .unwrap_or_default()
+          .trim_start_matches("error: ")
+      )
+    })?;
+
+    export.check()?;
+
+    if export.duplicates().needs_census() {
+      bail!("exports with duplicate columns, or that keep copies of duplicates not reached first, require `ord export`");
+    }
+
+    Ok(Some(export))
+  }
+
+  pub(crate) fn content_type(&self) -> &'static str {
+    match self.format {
+      Format::Csv => "text/csv; charset=utf-8",
+      Format::Jsonl => "application/x-ndjson",
+      Format::Json => "application/json",
+    }
+  }
+
+  /// Streams rows to `writer` for `ord server`, which keeps the index updated
+  /// itself. Takes an export returned by `from_route`.
+  pub(crate) fn stream(&self, index: &Index, chain: Chain, writer: Box<dyn Write>) -> Result {
+    let mut output = Output::stream(self, writer)?;
+
+    let link = Link::new(self.link_base.as_deref(), self.link_route, chain);
+
+    self.export(
+      index,
+      &mut output,
+      None,
+      self.duplicates(),
+      &link,
+      chain,
+      false,
+    )?;
+
+    output.finish()?;
+
+    Ok(())
+  }
+
+  fn check(&mut self) -> Result {
+    if self.checkpoint.is_some() && self.format == Format::Json {
+      bail!("`--checkpoint` requires `--format csv` or `--format jsonl`");
+    }
+
+    if self.checkpoint.is_some() && self.order == Order::Descending {
+      bail!("`--checkpoint` requires `--order ascending`");
+    }
+
+    if self.checkpoint.is_some() && self.split_by.is_some() {
+      bail!("`--checkpoint` cannot be combined with `--split-by`");
+    }
+
+    if self.follow && self.order == Order::Descending {
+      bail!("`--follow` requires `--order ascending`");
+    }
+
+    self.bounds.check()?;
+
+    self.text_filter.check()?;
+
+    if self.transfers {
+      self.columns = Column::TRANSFERS.to_vec();
+    }
+
+    self.expand_digest_columns();
+
+    for (i, column) in self.columns.iter().enumerate() {
+      if self.columns[..i].contains(column) {
+        bail!("column `{}` selected more than once", column.name());
+      }
+    }
+
+    if self.follow && self.duplicates().needs_census() {
+      bail!("`--follow` cannot be combined with `--keep last` or duplicate columns");
+    }
+
+    Ok(())
+  }
+
+  fn duplicates(&self) -> Duplicates {
+    Duplicates::new(
+      self.dedupe,
+      self.keep,
+      self.order,
+      self.columns.contains(&Column::DuplicateOf) || self.columns.contains(&Column::DuplicateCount),
+    )
+  }
+
+  /// Inscriptions in an index with content can be exported without Bitcoin
+  /// Core, so failing to reach it only means newer blocks are missing.
+  fn update(index: &Index) -> Result {
+    match index.update() {
+      Err(err) if !index.is_reorged() && index.has_content_index()? => {
+        eprintln!("warning: failed to update index, exporting indexed inscriptions: {err}");
+        Ok(())
+      }
+      result => result,
+    }
+  }
+
+  fn expand_digest_columns(&mut self) {
+    if self.digest.is_empty() {
+      return;
+    }
+
+    let digests = self.digest.iter().copied().map(Column::Digest);
+
+    match self
+      .columns
+      .iter()
+      .position(|column| *column == Column::Hash)
+    {
+      Some(i) => {
+        self.columns.splice(i..=i, digests);
+      }
+      None => self.columns.extend(digests),
+    }
+  }
+
+  fn export(
+    &self,
+    index: &Index,
+    output: &mut Output,
+    checkpoint: Option<Checkpoint>,
+    mut duplicates: Duplicates,
+    link: &Link,
+    chain: Chain,
+    progress: bool,
+  ) -> Result {
+    let order = self.order;
+
+    let mut from = *self.bounds.numbers().start();
+
+    let mut checkpoint = match &self.checkpoint {
+      Some(path) => {
+        let height = index
+          .height()?
+          .ok_or_else(|| anyhow!("cannot checkpoint export of empty index"))?
+          .n();
+
+        let checkpoint = Checkpoint {
+          number: checkpoint.and_then(|checkpoint| checkpoint.number),
+          height,
+          blockhash: index.block_hash(Some(height))?.unwrap(),
+          bytes: Some(output.checkpoint()?),
+          rows: output.rows(),
+          merkle: output.merkle().clone(),
+        };
+
+        checkpoint.save(path)?;
+
+        if let Some(number) = checkpoint.number {
+          from = from.max(number + 1);
+        }
+
+        Some((path, checkpoint))
+      }
+      None => None,
+    };
+
+    let resumed = checkpoint
+      .as_ref()
+      .and_then(|(_, checkpoint)| checkpoint.number);
+
+    if duplicates.needs_census() {
+      if !self.census(index, &mut duplicates, from, progress)? {
+        return Ok(());
+      }
+    } else if let Some(number) = resumed {
+      if self.dedupe != Dedupe::None && !self.replay(index, &mut duplicates, number, progress)? {
+        return Ok(());
+      }
+    }
+
+    let fetch = |inscription_id| self.get_inscription(index, inscription_id);
+
+    let mut tip = Self::tip(index)?;
+
+    loop {
+      let walk = Walk::new(index, &self.bounds, order, from);
+      let progress_bar = Self::progress_bar(progress, "exporting", walk.len()?);
+
+      Pipeline::new(self.jobs()).run(walk, fetch, |page| {
+        for (inscription_id, entry, inscription) in page.inscriptions {
+          self.export_inscription(
+            index,
+            output,
+            &mut duplicates,
+            inscription_id,
+            &entry,
+            inscription,
+            link,
+            chain,
+          )?;
+        }
+
+        progress_bar.inc(page.visited);
+
+        // A resumed export truncates the output to the length recorded in the
+        // checkpoint, so rows written after it was saved are exported again.
+        if let Some((path, checkpoint)) = &mut checkpoint {
+          checkpoint.number = Some(page.last);
+          checkpoint.bytes = Some(output.checkpoint()?);
+          checkpoint.rows = output.rows();
+          checkpoint.merkle = output.merkle().clone();
+          checkpoint.save(path)?;
+        } else {
+          output.flush()?;
+        }
+
+        from = page.last + 1;
+
+        Ok(INTERRUPTS.load(atomic::Ordering::Relaxed) == 0)
+      })?;
+
+      progress_bar.finish_and_clear();
+
+      if !self.follow || !self.wait_for_blocks() {
+        return Ok(());
+      }
+
+      Self::update(index)?;
+
+      // Updating may have rolled back blocks that exported rows came from,
+      // which appending newer inscriptions can't undo.
+      if let Some((height, blockhash)) = tip {
+        if index.block_hash(Some(height))? != Some(blockhash) {
+          bail!(
+            "reorg detected: exported block {blockhash} at height {height} is no longer in the index, export must be rebuilt"
+          );
+        }
+      }
+
+      tip = Self::tip(index)?;
+
+      if let Some((path, checkpoint)) = &mut checkpoint {
+        if let Some((height, blockhash)) = tip {
+          if height != checkpoint.height {
+            checkpoint.height = height;
+            checkpoint.blockhash = blockhash;
+            checkpoint.save(path)?;
+          }
+        }
+      }
+    }
+  }
+
+  /// Counts duplicates among the inscriptions exported from number `from` on,
+  /// returning `false` if interrupted.
+  fn census(
+    &self,
+    index: &Index,
+    duplicates: &mut Duplicates,
+    from: u64,
+    progress: bool,
+  ) -> Result<bool> {
+    let walk = Walk::new(index, &self.bounds, self.order, from);
+    let progress_bar = Self::progress_bar(progress, "counting duplicates", walk.len()?);
+
+    Pipeline::new(self.jobs()).run(
+      walk,
+      |inscription_id| self.get_inscription(index, inscription_id),
+      |page| {
+        for (inscription_id, entry, inscription) in page.inscriptions {
+          duplicates.count(duplicates.key(&inscription), entry.number, inscription_id);
+        }
+
+        progress_bar.inc(page.visited);
+
+        Ok(INTERRUPTS.load(atomic::Ordering::Relaxed) == 0)
+      },
+    )?;
+
+    if INTERRUPTS.load(atomic::Ordering::Relaxed) > 0 {
+      return Ok(false);
+    }
+
+    progress_bar.finish_and_clear();
+
+    Ok(true)
+  }
+
+  /// Marks the bodies of inscriptions numbered up to `to`, which a previous
+  /// run exported before saving the checkpoint being resumed from, as seen,
+  /// returning `false` if interrupted.
+  fn replay(
+    &self,
+    index: &Index,
+    duplicates: &mut Duplicates,
+    to: u64,
+    progress: bool,
+  ) -> Result<bool> {
+    let bounds = Bounds {
+      to_number: Some(to.min(*self.bounds.numbers().end())),
+      ..self.bounds
+    };
+
+    let walk = Walk::new(index, &bounds, self.order, *bounds.numbers().start());
+    let progress_bar = Self::progress_bar(progress, "replaying checkpoint", walk.len()?);
+
+    Pipeline::new(self.jobs()).run(
+      walk,
+      |inscription_id| self.get_inscription(index, inscription_id),
+      |page| {
+        for (_, entry, inscription) in page.inscriptions {
+          duplicates.keep(duplicates.key(&inscription), entry.number);
+        }
+
+        progress_bar.inc(page.visited);
+
+        Ok(INTERRUPTS.load(atomic::Ordering::Relaxed) == 0)
+      },
+    )?;
+
+    if INTERRUPTS.load(atomic::Ordering::Relaxed) > 0 {
+      return Ok(false);
+    }
+
+    progress_bar.finish_and_clear();
+
+    Ok(true)
+  }
+
+  fn tip(index: &Index) -> Result<Option<(u64, BlockHash)>> {
+    let height = index.height()?.map(|height| height.n());
+
+    Ok(height.zip(index.block_hash(height)?))
+  }
+
+  /// Writes a row per transfer in transfer number order, which is block
+  /// order, so height bounds end the walk like they do for inscriptions.
+  fn export_transfers(&self, index: &Index, output: &mut Output, progress: bool) -> Result {
+    const PAGE_SIZE: usize = 1000;
+
+    let order = self.order;
+
+    let progress_bar = Self::progress_bar(
+      progress,
+      "exporting transfers",
+      index
+        .get_transfers_to_number(u64::MAX, 1)?
+        .first()
+        .map(|(number, _)| number + 1)
+        .unwrap_or(0),
+    );
+
+    let mut cursor = Some(match order {
+      Order::Ascending => 0,
+      Order::Descending => u64::MAX,
+    });
+
+    while let Some(from) = cursor {
+      let transfers = match order {
+        Order::Ascending => index.get_transfers_from_number(from, PAGE_SIZE)?,
+        Order::Descending => index.get_transfers_to_number(from, PAGE_SIZE)?,
+      };
+
+      cursor = match (transfers.len() == PAGE_SIZE, transfers.last()) {
+        (true, Some((last, _))) => match order {
+          Order::Ascending => last.checked_add(1),
+          Order::Descending => last.checked_sub(1),
+        },
+        _ => None,
+      };
+
+      for (_, transfer) in transfers {
+        if self.bounds.exhausted(order, transfer.height) {
+          cursor = None;
+          break;
+        }
+
+        progress_bar.inc(1);
+
+        let entry = index
+          .get_inscription_entry(transfer.inscription_id)?
+          .ok_or_else(|| anyhow!("inscription {} has no index entry", transfer.inscription_id))?;
+
+        if !self.bounds.contains_transfer(entry.number, &transfer) {
+          continue;
+        }
+
+        let values = self
+          .columns
+          .iter()
+          .map(|column| match column {
+            Column::Height => transfer.height.into(),
+            Column::Id => transfer.inscription_id.to_string().into(),
+            Column::Lost => transfer.lost.into(),
+            Column::NewSatpoint => transfer.new_satpoint.to_string().into(),
+            Column::Number => entry.number.into(),
+            Column::OldSatpoint => transfer.old_satpoint.to_string().into(),
+            Column::Timestamp => timestamp(transfer.timestamp).to_rfc3339().into(),
+            Column::Txid => transfer.txid.to_string().into(),
+            _ => Value::Null,
+          })
+          .collect();
+
+        output.write(
+          &Row {
+            columns: &self.columns,
+            values,
+          },
+          transfer.height,
+        )?;
+      }
+
+      output.flush()?;
+
+      if INTERRUPTS.load(atomic::Ordering::Relaxed) > 0 {
+        break;
+      }
+    }
+
+    progress_bar.finish_and_clear();
+
+    Ok(())
+  }
+
+  fn jobs(&self) -> usize {
+    self
+      .jobs
+      .or_else(|| thread::available_parallelism().ok())
+      .map(NonZeroUsize::get)
+      .unwrap_or(1)
+  }
+
+  /// Sleep for the poll interval, returning `false` if interrupted.
+  fn wait_for_blocks(&self) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(self.poll_interval);
+
+    while Instant::now() < deadline {
+      if INTERRUPTS.load(atomic::Ordering::Relaxed) > 0 {
+        return false;
+      }
+
+      thread::sleep(Duration::from_millis(100));
+    }
+
+    INTERRUPTS.load(atomic::Ordering::Relaxed) == 0
+  }
+
+  fn get_inscription(
+    &self,
+    index: &Index,
+    inscription_id: InscriptionId,
+  ) -> Result<Option<Inscription>> {
+    let Some(inscription) = index.get_inscription_by_id(inscription_id)? else {
+      return Ok(None);
+    };
+
+    if !self
+      .media
+      .iter()
+      .any(|filter| filter.matches(inscription.media()))
+    {
+      return Ok(None);
+    }
+
+    if self.invalid_utf8 == InvalidUtf8::Skip
+      && inscription.media() == Media::Text
+      && std::str::from_utf8(inscription.body().unwrap_or_default()).is_err()
+    {
+      return Ok(None);
+    }
+
+    if !self.text_filter.matches(&inscription) {
+      return Ok(None);
+    }
+
+    Ok(Some(inscription))
+  }
+
+  fn export_inscription(
+    &self,
+    index: &Index,
+    output: &mut Output,
+    duplicates: &mut Duplicates,
+    inscription_id: InscriptionId,
+    entry: &InscriptionEntry,
+    inscription: Inscription,
+    link: &Link,
+    chain: Chain,
+  ) -> Result {
+    let key = duplicates.key(&inscription);
+
+    if !duplicates.keep(key, entry.number) {
+      return Ok(());
+    }
+
+    if let Some(bodies_dir) = &self.bodies_dir {
+      if self.text(&inscription).is_none() {
+        Self::write_body(
+          bodies_dir,
+          &Algorithm::Sha3_256.digest(inscription.body().unwrap_or_default()),
+          inscription.content_type(),
+          inscription.body().unwrap_or_default(),
+        )?;
+      }
+    }
+
+    let values = self.values(
+      index,
+      &self.columns,
+      duplicates.group(&key),
+      inscription_id,
+      entry,
+      &inscription,
+      link,
+      chain,
+    )?;
+
+    output.write(
+      &Row {
+        columns: &self.columns,
+        values,
+      },
+      entry.height,
+    )
+  }
+
+  /// Text bodies are exported as text, unless they aren't UTF-8 and are
+  /// quarantined.
+  fn text(&self, inscription: &Inscription) -> Option<String> {
+    if inscription.media() != Media::Text {
+      return None;
+    }
+
+    let body = inscription.body().unwrap_or_default();
+
+    match std::str::from_utf8(body) {
+      Ok(text) => Some(text.into()),
+      Err(_) if self.invalid_utf8 == InvalidUtf8::Quarantine => None,
+      Err(_) => Some(String::from_utf8_lossy(body).into()),
+    }
+  }
+
+  /// The values of `columns` for an inscription, as exported and as checked
+  /// by `ord export verify`.
+  fn values(
+    &self,
+    index: &Index,
+    columns: &[Column],
+    group: Option<Group>,
+    inscription_id: InscriptionId,
+    entry: &InscriptionEntry,
+    inscription: &Inscription,
+    link: &Link,
+    chain: Chain,
+  ) -> Result<Vec<Value>> {
+    let media = inscription.media();
+
+    let body = inscription.body().unwrap_or_default();
+
+    let hash = Algorithm::Sha3_256.digest(body);
+
+    let utf8 = (media == Media::Text).then(|| std::str::from_utf8(body).is_ok());
+
+    let text = self.text(inscription);
+
+    let (body, path) = if text.is_some() {
+      (None, None)
+    } else if self.bodies_dir.is_some() {
+      (
+        None,
+        Some(Self::body_path(&hash, inscription.content_type())),
+      )
+    } else {
+      (Some(base64::encode(body)), None)
+    };
+
+    let digest_input = self
+      .digest_input
+      .bytes(media, inscription.body().unwrap_or_default());
+
+    let mut values = Vec::with_capacity(columns.len());
+
+    for column in columns {
+      values.push(match column {
+        Column::Body => body.clone().into(),
+        Column::ContentLength => inscription.content_length().into(),
+        Column::ContentType => inscription.content_type().into(),
+        Column::DuplicateCount => group.map(|group| group.count).into(),
+        Column::DuplicateOf => group.map(|group| group.first.1.to_string()).into(),
+        Column::Fee => entry.fee.into(),
+        Column::Digest(algorithm) => algorithm.digest(&digest_input).into(),
+        Column::Hash => Algorithm::Sha3_256.digest(&digest_input).into(),
+        Column::Height => entry.height.into(),
+        Column::Id => inscription_id.to_string().into(),
+        Column::Link => link.url(inscription_id).into(),
+        Column::Media => media.to_string().into(),
+        Column::Number => entry.number.into(),
+        Column::Path => path.clone().into(),
+        Column::Owner => index
+          .get_inscription_satpoint_by_id(inscription_id)?
+          .map(|satpoint| index.get_output_script_pubkey(satpoint.outpoint))
+          .transpose()?
+          .flatten()
+          .and_then(|script_pubkey| chain.address_from_script(&script_pubkey).ok())
+          .map(|address| address.to_string())
+          .into(),
+        Column::Rarity => entry.sat.map(|sat| sat.rarity().to_string()).into(),
+        Column::Sat => entry.sat.map(Sat::n).into(),
+        Column::SatName => entry.sat.map(Sat::name).into(),
+        Column::Satpoint => index
+          .get_inscription_satpoint_by_id(inscription_id)?
+          .map(|satpoint| satpoint.to_string())
+          .into(),
+        Column::Structure => (media == Media::Text)
+          .then(|| {
+            Structure::detect(&String::from_utf8_lossy(
+              inscription.body().unwrap_or_default(),
+            ))
+            .to_string()
+          })
+          .into(),
+        Column::Text => text.clone().into(),
+        Column::Timestamp => timestamp(entry.timestamp).to_rfc3339().into(),
+        Column::Utf8 => utf8.into(),
+        Column::Lost | Column::NewSatpoint | Column::OldSatpoint | Column::Txid => Value::Null,
+      });
+    }
+
+    Ok(values)
+  }
+
+  fn progress_bar(progress: bool, message: &'static str, len: u64) -> ProgressBar {
+    if !progress || integration_test() {
+      return ProgressBar::hidden();
+    }
+
+    let progress_bar = ProgressBar::new(len);
+    progress_bar.set_style(ProgressStyle::with_template("[{msg}] {wide_bar} {pos}/{len}").unwrap());
+    progress_bar.set_message(message);
+    progress_bar
+  }
+
+  fn body_path(hash: &str, content_type: Option<&str>) -> String {
+    let mut path = format!("{}/{}/{hash}", &hash[0..2], &hash[2..4]);
+
+    if let Some(extension) = content_type.and_then(Media::extension_for_content_type) {
+      path.push('.');
+      path.push_str(extension);
+    }
+
+    path
+  }
+
+  fn write_body(
+    bodies_dir: &Path,
+    hash: &str,
+    content_type: Option<&str>,
+    body: &[u8],
+  ) -> Result<String> {
+    let path = Self::body_path(hash, content_type);
+
+    let destination = bodies_dir.join(&path);
+
+    if !destination.exists() {
+      let parent = destination.parent().unwrap();
+      fs::create_dir_all(parent)
+        .with_context(|| format!("I/O error creating `{}`", parent.display()))?;
+      fs::write(&destination, body)
+        .with_context(|| format!("I/O error writing `{}`", destination.display()))?;
+    }
+
+    Ok(path)
+  }
+}
+
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct Buffer(Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for Buffer {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.borrow_mut().write(buf)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Export {
+    match Arguments::try_parse_from(["ord", "export"].iter().chain(args))
+      .unwrap()
+      .subcommand
+    {
+      Subcommand::Export(export) => *export,
+      subcommand => panic!("unexpected subcommand: {subcommand:?}"),
+    }
+  }
+
+  #[test]
+  fn bodies_are_written_to_content_addressed_paths() {
+    let tempdir = TempDir::new().unwrap();
+
+    let hash = "abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789";
+
+    assert_eq!(
+      Export::write_body(tempdir.path(), hash, Some("image/png"), b"foo").unwrap(),
+      format!("ab/cd/{hash}.png"),
+    );
+
+    assert_eq!(
+      fs::read(tempdir.path().join("ab/cd").join(format!("{hash}.png"))).unwrap(),
+      b"foo"
+    );
+
+    assert_eq!(
+      Export::write_body(tempdir.path(), hash, None, b"foo").unwrap(),
+      format!("ab/cd/{hash}"),
+    );
+  }
+
+  #[test]
+  fn checkpoint_requires_output() {
+    Arguments::try_parse_from(["ord", "export", "--checkpoint", "foo.json"]).unwrap_err();
+  }
+
+  #[test]
+  fn digest_columns_replace_hash() {
+    let mut export = parse(&["--columns", "id,hash,text", "--digest", "sha256,sha3-256"]);
+    export.expand_digest_columns();
+    assert_eq!(
+      export.columns,
+      [
+        Column::Id,
+        Column::Digest(Algorithm::Sha256),
+        Column::Digest(Algorithm::Sha3_256),
+        Column::Text,
+      ]
+    );
+
+    let mut export = parse(&["--columns", "id", "--digest", "sha512"]);
+    export.expand_digest_columns();
+    assert_eq!(
+      export.columns,
+      [Column::Id, Column::Digest(Algorithm::Sha512)]
+    );
+
+    let mut export = parse(&[]);
+    export.expand_digest_columns();
+    assert_eq!(export.columns[0], Column::Hash);
+  }
+
+  #[test]
+  fn default_exports_count_no_duplicates() {
+    assert!(!parse(&[]).duplicates().needs_census());
+    assert!(!parse(&["--order", "descending", "--keep", "last"])
+      .duplicates()
+      .needs_census());
+    assert!(parse(&["--order", "descending"])
+      .duplicates()
+      .needs_census());
+  }
+
+  #[test]
+  fn keep_defaults_to_first_copy_in_either_order() {
+    assert_eq!(parse(&[]).order, Order::Ascending);
+    assert_eq!(parse(&[]).keep, Keep::First);
+    assert_eq!(parse(&["--order", "descending"]).keep, Keep::First);
+  }
+
+  #[test]
+  fn media_defaults_to_text() {
+    assert_eq!(parse(&[]).media, vec![MediaFilter::Media(Media::Text)]);
+  }
+
+  #[test]
+  fn media_accepts_comma_separated_list() {
+    assert_eq!(
+      parse(&["--media", "image,video", "--media", "pdf"]).media,
+      vec![
+        MediaFilter::Media(Media::Image),
+        MediaFilter::Media(Media::Video),
+        MediaFilter::Media(Media::Pdf),
+      ]
+    );
+  }
+}
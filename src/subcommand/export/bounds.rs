@@ -0,0 +1,186 @@
+use {super::*, clap::ValueEnum, std::ops::RangeInclusive};
+
+#[derive(Debug, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum Order {
+  Ascending,
+  Descending,
+}
+
+#[derive(Debug, Default, Parser)]
+pub(crate) struct Bounds {
+  #[clap(long, help = "Export inscriptions numbered <FROM_NUMBER> or higher.")]
+  pub(crate) from_number: Option<u64>,
+  #[clap(long, help = "Export inscriptions numbered <TO_NUMBER> or lower.")]
+  pub(crate) to_number: Option<u64>,
+  #[clap(
+    long,
+    help = "Export inscriptions revealed at <FROM_HEIGHT> or higher."
+  )]
+  pub(crate) from_height: Option<u64>,
+  #[clap(long, help = "Export inscriptions revealed at <TO_HEIGHT> or lower.")]
+  pub(crate) to_height: Option<u64>,
+  #[clap(
+    long,
+    help = "Export inscriptions revealed at or after RFC 3339 timestamp <SINCE>."
+  )]
+  pub(crate) since: Option<DateTime<Utc>>,
+  #[clap(
+    long,
+    help = "Export inscriptions revealed at or before RFC 3339 timestamp <UNTIL>."
+  )]
+  pub(crate) until: Option<DateTime<Utc>>,
+}
+
+impl Bounds {
+  pub(crate) fn check(&self) -> Result {
+    if self.from_number > self.to_number && self.to_number.is_some() {
+      bail!("`--from-number` must not be greater than `--to-number`");
+    }
+
+    if self.from_height > self.to_height && self.to_height.is_some() {
+      bail!("`--from-height` must not be greater than `--to-height`");
+    }
+
+    if self.since > self.until && self.until.is_some() {
+      bail!("`--since` must not be later than `--until`");
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn numbers(&self) -> RangeInclusive<u64> {
+    self.from_number.unwrap_or(0)..=self.to_number.unwrap_or(u64::MAX)
+  }
+
+  /// Inscription and transfer numbers are assigned in block order, so once
+  /// one falls beyond the height bound in the direction of travel, every
+  /// later one will too.
+  pub(crate) fn exhausted(&self, order: Order, height: u64) -> bool {
+    match order {
+      Order::Ascending => self.to_height.map_or(false, |to| height > to),
+      Order::Descending => self.from_height.map_or(false, |from| height < from),
+    }
+  }
+
+  pub(crate) fn contains(&self, entry: &InscriptionEntry) -> bool {
+    self.contains_event(entry.number, entry.height, entry.timestamp)
+  }
+
+  /// Transfers are selected by the number of the inscription transferred and
+  /// by the height and time of the transfer itself.
+  pub(crate) fn contains_transfer(&self, number: u64, transfer: &TransferEntry) -> bool {
+    self.contains_event(number, transfer.height, transfer.timestamp)
+  }
+
+  fn contains_event(&self, number: u64, height: u64, time: u32) -> bool {
+    let timestamp = timestamp(time);
+
+    self.numbers().contains(&number)
+      && self.from_height.map_or(true, |from| height >= from)
+      && self.to_height.map_or(true, |to| height <= to)
+      && self.since.map_or(true, |since| timestamp >= since)
+      && self.until.map_or(true, |until| timestamp <= until)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(number: u64, height: u64, timestamp: u32) -> InscriptionEntry {
+    InscriptionEntry {
+      fee: 0,
+      height,
+      number,
+      sat: None,
+      timestamp,
+    }
+  }
+
+  #[test]
+  fn unbounded_contains_everything() {
+    let bounds = Bounds::default();
+    assert_eq!(bounds.numbers(), 0..=u64::MAX);
+    assert!(bounds.contains(&entry(0, 0, 0)));
+    assert!(!bounds.exhausted(Order::Ascending, u64::MAX));
+    assert!(!bounds.exhausted(Order::Descending, 0));
+  }
+
+  #[test]
+  fn number_bounds_are_inclusive() {
+    let bounds = Bounds {
+      from_number: Some(1),
+      to_number: Some(2),
+      ..Default::default()
+    };
+    assert!(!bounds.contains(&entry(0, 0, 0)));
+    assert!(bounds.contains(&entry(1, 0, 0)));
+    assert!(bounds.contains(&entry(2, 0, 0)));
+    assert!(!bounds.contains(&entry(3, 0, 0)));
+  }
+
+  #[test]
+  fn height_bounds_are_inclusive() {
+    let bounds = Bounds {
+      from_height: Some(10),
+      to_height: Some(20),
+      ..Default::default()
+    };
+    assert!(!bounds.contains(&entry(0, 9, 0)));
+    assert!(bounds.contains(&entry(0, 10, 0)));
+    assert!(bounds.contains(&entry(0, 20, 0)));
+    assert!(!bounds.contains(&entry(0, 21, 0)));
+
+    assert!(!bounds.exhausted(Order::Ascending, 20));
+    assert!(bounds.exhausted(Order::Ascending, 21));
+    assert!(!bounds.exhausted(Order::Descending, 10));
+    assert!(bounds.exhausted(Order::Descending, 9));
+  }
+
+  #[test]
+  fn time_bounds_are_inclusive() {
+    let bounds = Bounds {
+      since: Some("1970-01-01T00:00:02Z".parse().unwrap()),
+      until: Some("1970-01-01T01:00:03+01:00".parse().unwrap()),
+      ..Default::default()
+    };
+    assert!(!bounds.contains(&entry(0, 0, 1)));
+    assert!(bounds.contains(&entry(0, 0, 2)));
+    assert!(bounds.contains(&entry(0, 0, 3)));
+    assert!(!bounds.contains(&entry(0, 0, 4)));
+  }
+
+  #[test]
+  fn inverted_bounds_are_rejected() {
+    assert_eq!(
+      Bounds {
+        from_number: Some(2),
+        to_number: Some(1),
+        ..Default::default()
+      }
+      .check()
+      .unwrap_err()
+      .to_string(),
+      "`--from-number` must not be greater than `--to-number`"
+    );
+
+    assert_eq!(
+      Bounds {
+        from_height: Some(2),
+        to_height: Some(1),
+        ..Default::default()
+      }
+      .check()
+      .unwrap_err()
+      .to_string(),
+      "`--from-height` must not be greater than `--to-height`"
+    );
+
+    Bounds {
+      from_number: Some(1),
+      ..Default::default()
+    }
+    .check()
+    .unwrap();
+  }
+}
@@ -0,0 +1,113 @@
+use super::*;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+  pub(crate) number: Option<u64>,
+  pub(crate) height: u64,
+  pub(crate) blockhash: BlockHash,
+  #[serde(default)]
+  pub(crate) bytes: Option<u64>,
+  #[serde(default)]
+  pub(crate) rows: u64,
+  #[serde(default)]
+  pub(crate) merkle: Merkle,
+}
+
+impl Checkpoint {
+  pub(crate) fn load(path: &Path) -> Result<Option<Self>> {
+    match fs::read(path) {
+      Ok(json) => Ok(Some(serde_json::from_slice(&json).with_context(|| {
+        format!("failed to parse checkpoint `{}`", path.display())
+      })?)),
+      Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+      Err(err) => Err(err).with_context(|| format!("I/O error reading `{}`", path.display())),
+    }
+  }
+
+  pub(crate) fn save(&self, path: &Path) -> Result {
+    let tmp = path.with_extension("tmp");
+
+    fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+      .with_context(|| format!("I/O error writing `{}`", tmp.display()))?;
+
+    fs::rename(&tmp, path).with_context(|| format!("I/O error writing `{}`", path.display()))?;
+
+    Ok(())
+  }
+
+  pub(crate) fn verify(&self, index: &Index) -> Result {
+    if index.block_hash(Some(self.height))? != Some(self.blockhash) {
+      bail!(
+        "reorg detected: checkpoint block {} at height {} is no longer in the index, export must be rebuilt",
+        self.blockhash,
+        self.height
+      );
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn missing_checkpoint_is_none() {
+    let tempdir = TempDir::new().unwrap();
+    assert_eq!(
+      Checkpoint::load(&tempdir.path().join("checkpoint.json")).unwrap(),
+      None
+    );
+  }
+
+  #[test]
+  fn save_and_load() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("checkpoint.json");
+
+    let checkpoint = Checkpoint {
+      number: Some(7),
+      height: 2,
+      blockhash: blockhash(1),
+      bytes: Some(100),
+      rows: 1,
+      merkle: Merkle::default(),
+    };
+
+    checkpoint.save(&path).unwrap();
+
+    assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+  }
+
+  #[test]
+  fn checkpoint_without_manifest_state_loads() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("checkpoint.json");
+    fs::write(
+      &path,
+      format!(
+        r#"{{"number":7,"height":2,"blockhash":"{}"}}"#,
+        blockhash(1)
+      ),
+    )
+    .unwrap();
+
+    let checkpoint = Checkpoint::load(&path).unwrap().unwrap();
+    assert_eq!(checkpoint.bytes, None);
+    assert_eq!(checkpoint.rows, 0);
+    assert_eq!(checkpoint.merkle, Merkle::default());
+  }
+
+  #[test]
+  fn invalid_checkpoint_is_error() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("checkpoint.json");
+    fs::write(&path, "foo").unwrap();
+
+    assert_regex_match!(
+      Checkpoint::load(&path).unwrap_err(),
+      "failed to parse checkpoint `.*checkpoint.json`"
+    );
+  }
+}
@@ -0,0 +1,178 @@
+use {super::*, serde::ser::SerializeMap, serde_json::Value};
+
+/// A column of exported rows. `Digest` columns are not selected by name, but
+/// take the place of `hash` when `--digest` is given, and transfer columns
+/// are only written by `--transfers`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Column {
+  Body,
+  ContentLength,
+  ContentType,
+  Digest(Algorithm),
+  DuplicateCount,
+  DuplicateOf,
+  Fee,
+  Hash,
+  Height,
+  Id,
+  Link,
+  Lost,
+  Media,
+  NewSatpoint,
+  Number,
+  OldSatpoint,
+  Owner,
+  Path,
+  Rarity,
+  Sat,
+  SatName,
+  Satpoint,
+  Structure,
+  Text,
+  Timestamp,
+  Txid,
+  Utf8,
+}
+
+impl Column {
+  pub(crate) const ALL: &'static [Self] = &[
+    Self::Number,
+    Self::Id,
+    Self::Hash,
+    Self::Timestamp,
+    Self::Height,
+    Self::Fee,
+    Self::Sat,
+    Self::SatName,
+    Self::Rarity,
+    Self::Satpoint,
+    Self::Owner,
+    Self::Media,
+    Self::ContentType,
+    Self::ContentLength,
+    Self::Utf8,
+    Self::Structure,
+    Self::Text,
+    Self::Body,
+    Self::Path,
+    Self::Link,
+    Self::DuplicateOf,
+    Self::DuplicateCount,
+  ];
+
+  pub(crate) const TRANSFERS: &'static [Self] = &[
+    Self::Number,
+    Self::Id,
+    Self::Height,
+    Self::Timestamp,
+    Self::Txid,
+    Self::OldSatpoint,
+    Self::NewSatpoint,
+    Self::Lost,
+  ];
+
+  pub(crate) const DEFAULT: &'static str = "hash,timestamp,text,link";
+
+  pub(crate) fn name(self) -> &'static str {
+    match self {
+      Self::Body => "body",
+      Self::ContentLength => "content_length",
+      Self::ContentType => "content_type",
+      Self::Digest(algorithm) => algorithm.column(),
+      Self::DuplicateCount => "duplicate_count",
+      Self::DuplicateOf => "duplicate_of",
+      Self::Fee => "fee",
+      Self::Hash => "hash",
+      Self::Height => "height",
+      Self::Id => "id",
+      Self::Link => "link",
+      Self::Lost => "lost",
+      Self::Media => "media",
+      Self::NewSatpoint => "new_satpoint",
+      Self::Number => "number",
+      Self::OldSatpoint => "old_satpoint",
+      Self::Owner => "owner",
+      Self::Path => "path",
+      Self::Rarity => "rarity",
+      Self::Sat => "sat",
+      Self::SatName => "sat_name",
+      Self::Satpoint => "satpoint",
+      Self::Structure => "structure",
+      Self::Text => "text",
+      Self::Timestamp => "timestamp",
+      Self::Txid => "txid",
+      Self::Utf8 => "utf8",
+    }
+  }
+}
+
+impl FromStr for Column {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    Self::ALL
+      .iter()
+      .find(|column| column.name() == s)
+      .copied()
+      .ok_or_else(|| anyhow!("invalid column: {s}"))
+  }
+}
+
+/// A record with one value per selected column. Serializes as a map from
+/// column name to value, for formats that name their fields.
+pub(crate) struct Row<'a> {
+  pub(crate) columns: &'a [Column],
+  pub(crate) values: Vec<Value>,
+}
+
+impl<'a> Serialize for Row<'a> {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+    for (column, value) in self.columns.iter().zip(&self.values) {
+      map.serialize_entry(column.name(), value)?;
+    }
+    map.end()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn names_round_trip() {
+    for column in Column::ALL {
+      assert_eq!(column.name().parse::<Column>().unwrap(), *column);
+    }
+  }
+
+  #[test]
+  fn default_columns_parse() {
+    for column in Column::DEFAULT.split(',') {
+      column.parse::<Column>().unwrap();
+    }
+  }
+
+  #[test]
+  fn invalid_column() {
+    assert_eq!(
+      "foo".parse::<Column>().unwrap_err().to_string(),
+      "invalid column: foo"
+    );
+  }
+
+  #[test]
+  fn row_serializes_as_map_in_column_order() {
+    assert_eq!(
+      serde_json::to_string(&Row {
+        columns: &[Column::Text, Column::Number],
+        values: vec![Value::Null, 1.into()],
+      })
+      .unwrap(),
+      r#"{"text":null,"number":1}"#
+    );
+  }
+}
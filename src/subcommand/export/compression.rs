@@ -0,0 +1,163 @@
+use {
+  super::*,
+  clap::ValueEnum,
+  flate2::{read::MultiGzDecoder, write::GzEncoder},
+  std::io::Read,
+};
+
+#[derive(Debug, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum Compression {
+  Gzip,
+  Zstd,
+}
+
+impl Compression {
+  pub(crate) fn extension(self) -> &'static str {
+    match self {
+      Self::Gzip => "gz",
+      Self::Zstd => "zst",
+    }
+  }
+
+  pub(crate) fn from_path(path: &Path) -> Option<Self> {
+    match path.extension()?.to_str()? {
+      "gz" => Some(Self::Gzip),
+      "zst" => Some(Self::Zstd),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn encoder(compression: Option<Self>, writer: Box<dyn Write>) -> Result<Encoder> {
+    Ok(match compression {
+      None => Encoder::Plain(writer),
+      Some(Self::Gzip) => Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default())),
+      Some(Self::Zstd) => Encoder::Zstd(zstd::Encoder::new(writer, 0)?),
+    })
+  }
+
+  /// Exports with `--checkpoint` end a gzip member at every checkpoint, so
+  /// every member is decoded.
+  pub(crate) fn decoder(compression: Option<Self>, reader: Box<dyn Read>) -> Result<Box<dyn Read>> {
+    Ok(match compression {
+      None => reader,
+      Some(Self::Gzip) => Box::new(MultiGzDecoder::new(reader)),
+      Some(Self::Zstd) => Box::new(zstd::Decoder::new(reader)?),
+    })
+  }
+}
+
+/// Compressed streams must be finished explicitly to write their trailers,
+/// which `Box<dyn Write>` can't express.
+pub(crate) enum Encoder {
+  Gzip(GzEncoder<Box<dyn Write>>),
+  Plain(Box<dyn Write>),
+  Zstd(zstd::Encoder<'static, Box<dyn Write>>),
+}
+
+impl Encoder {
+  pub(crate) fn finish(self) -> Result {
+    let mut writer = match self {
+      Self::Gzip(encoder) => encoder.finish()?,
+      Self::Plain(writer) => writer,
+      Self::Zstd(encoder) => encoder.finish()?,
+    };
+
+    writer.flush()?;
+
+    Ok(())
+  }
+}
+
+impl Write for Encoder {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    match self {
+      Self::Gzip(encoder) => encoder.write(buf),
+      Self::Plain(writer) => writer.write(buf),
+      Self::Zstd(encoder) => encoder.write(buf),
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    match self {
+      Self::Gzip(encoder) => encoder.flush(),
+      Self::Plain(writer) => writer.flush(),
+      Self::Zstd(encoder) => encoder.flush(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, flate2::read::GzDecoder};
+
+  fn compress(compression: Option<Compression>, data: &[u8]) -> Vec<u8> {
+    let buffer = Buffer::default();
+    let mut encoder = Compression::encoder(compression, Box::new(buffer.clone())).unwrap();
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap();
+    buffer.0.take()
+  }
+
+  #[test]
+  fn from_path() {
+    assert_eq!(
+      Compression::from_path("foo.csv.gz".as_ref()),
+      Some(Compression::Gzip)
+    );
+    assert_eq!(
+      Compression::from_path("foo.jsonl.zst".as_ref()),
+      Some(Compression::Zstd)
+    );
+    assert_eq!(Compression::from_path("foo.csv".as_ref()), None);
+    assert_eq!(Compression::from_path("-".as_ref()), None);
+  }
+
+  #[test]
+  fn plain() {
+    assert_eq!(compress(None, b"foo"), b"foo");
+  }
+
+  #[test]
+  fn gzip_round_trips() {
+    let mut decompressed = String::new();
+    GzDecoder::new(compress(Some(Compression::Gzip), b"foo").as_slice())
+      .read_to_string(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, "foo");
+  }
+
+  #[test]
+  fn zstd_round_trips() {
+    assert_eq!(
+      zstd::decode_all(compress(Some(Compression::Zstd), b"foo").as_slice()).unwrap(),
+      b"foo"
+    );
+  }
+
+  #[test]
+  fn concatenated_gzip_members_decompress_as_one_stream() {
+    let mut compressed = compress(Some(Compression::Gzip), b"foo");
+    compressed.extend(compress(Some(Compression::Gzip), b"bar"));
+
+    let mut decompressed = String::new();
+    MultiGzDecoder::new(compressed.as_slice())
+      .read_to_string(&mut decompressed)
+      .unwrap();
+    assert_eq!(decompressed, "foobar");
+  }
+
+  #[test]
+  fn decoders_round_trip() {
+    for compression in [None, Some(Compression::Gzip), Some(Compression::Zstd)] {
+      let mut decompressed = String::new();
+      Compression::decoder(
+        compression,
+        Box::new(io::Cursor::new(compress(compression, b"foo"))),
+      )
+      .unwrap()
+      .read_to_string(&mut decompressed)
+      .unwrap();
+      assert_eq!(decompressed, "foo");
+    }
+  }
+}
@@ -0,0 +1,186 @@
+use {super::*, clap::ValueEnum, std::collections::HashMap};
+
+#[derive(Debug, Default, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum Dedupe {
+  None,
+  #[default]
+  Text,
+  Hash,
+}
+
+#[derive(Debug, Default, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum Keep {
+  #[default]
+  First,
+  Last,
+}
+
+pub(crate) type Key = [u8; 32];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Group {
+  pub(crate) count: u64,
+  pub(crate) first: (u64, InscriptionId),
+  pub(crate) last: (u64, InscriptionId),
+}
+
+/// Tracks which exported bodies have been seen, by digest only. When the
+/// walk order lets the kept copy be decided as it is reached, a set of digests
+/// suffices. Otherwise, or when duplicate columns are requested, every group
+/// must be counted in a census before any rows are written.
+pub(crate) struct Duplicates {
+  dedupe: Dedupe,
+  groups: Option<HashMap<Key, Group>>,
+  keep: Keep,
+  seen: HashSet<Key>,
+}
+
+impl Duplicates {
+  pub(crate) fn new(dedupe: Dedupe, keep: Keep, order: Order, columns: bool) -> Self {
+    let streaming = matches!(
+      (keep, order),
+      (Keep::First, Order::Ascending) | (Keep::Last, Order::Descending)
+    );
+
+    Self {
+      dedupe,
+      groups: (columns || (dedupe != Dedupe::None && !streaming)).then(HashMap::new),
+      keep,
+      seen: HashSet::new(),
+    }
+  }
+
+  pub(crate) fn needs_census(&self) -> bool {
+    self.groups.is_some()
+  }
+
+  pub(crate) fn key(&self, inscription: &Inscription) -> Key {
+    let body = inscription.body().unwrap_or_default();
+
+    let mut hasher = Sha3_256::new();
+
+    if self.dedupe == Dedupe::Text && inscription.media() == Media::Text {
+      hasher.update(normalize_text(body));
+    } else {
+      hasher.update(body);
+    }
+
+    hasher.finalize().into()
+  }
+
+  pub(crate) fn count(&mut self, key: Key, number: u64, inscription_id: InscriptionId) {
+    let groups = self.groups.get_or_insert_with(HashMap::new);
+
+    let group = groups.entry(key).or_insert(Group {
+      count: 0,
+      first: (number, inscription_id),
+      last: (number, inscription_id),
+    });
+
+    group.count += 1;
+
+    if number < group.first.0 {
+      group.first = (number, inscription_id);
+    }
+
+    if number > group.last.0 {
+      group.last = (number, inscription_id);
+    }
+  }
+
+  pub(crate) fn group(&self, key: &Key) -> Option<Group> {
+    self.groups.as_ref()?.get(key).copied()
+  }
+
+  pub(crate) fn keep(&mut self, key: Key, number: u64) -> bool {
+    if self.dedupe == Dedupe::None {
+      return true;
+    }
+
+    match self.group(&key) {
+      Some(group) => {
+        let kept = match self.keep {
+          Keep::First => group.first,
+          Keep::Last => group.last,
+        };
+        kept.0 == number
+      }
+      None => self.seen.insert(key),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn streaming_when_order_matches_keep() {
+    assert!(!Duplicates::new(Dedupe::Text, Keep::First, Order::Ascending, false).needs_census());
+    assert!(!Duplicates::new(Dedupe::Text, Keep::Last, Order::Descending, false).needs_census());
+    assert!(!Duplicates::new(Dedupe::None, Keep::First, Order::Descending, false).needs_census());
+  }
+
+  #[test]
+  fn census_when_order_differs_from_keep_or_columns_requested() {
+    assert!(Duplicates::new(Dedupe::Text, Keep::First, Order::Descending, false).needs_census());
+    assert!(Duplicates::new(Dedupe::Hash, Keep::Last, Order::Ascending, false).needs_census());
+    assert!(Duplicates::new(Dedupe::None, Keep::First, Order::Ascending, true).needs_census());
+  }
+
+  #[test]
+  fn streaming_keeps_first_seen() {
+    let mut duplicates = Duplicates::new(Dedupe::Hash, Keep::First, Order::Ascending, false);
+    assert!(duplicates.keep([0; 32], 0));
+    assert!(!duplicates.keep([0; 32], 1));
+    assert!(duplicates.keep([1; 32], 2));
+  }
+
+  #[test]
+  fn census_keeps_policy_copy_regardless_of_order() {
+    for (keep, kept) in [(Keep::First, 0), (Keep::Last, 2)] {
+      let mut duplicates = Duplicates::new(Dedupe::Hash, keep, Order::Descending, true);
+
+      for number in (0..3).rev() {
+        duplicates.count([0; 32], number, inscription_id(number.try_into().unwrap()));
+      }
+
+      assert_eq!(
+        duplicates.group(&[0; 32]),
+        Some(Group {
+          count: 3,
+          first: (0, inscription_id(0)),
+          last: (2, inscription_id(2)),
+        })
+      );
+
+      for number in (0..3).rev() {
+        assert_eq!(duplicates.keep([0; 32], number), number == kept);
+      }
+    }
+  }
+
+  #[test]
+  fn none_keeps_everything() {
+    let mut duplicates = Duplicates::new(Dedupe::None, Keep::First, Order::Ascending, false);
+    assert!(duplicates.keep([0; 32], 0));
+    assert!(duplicates.keep([0; 32], 1));
+  }
+
+  #[test]
+  fn text_key_ignores_surrounding_whitespace() {
+    let text = Duplicates::new(Dedupe::Text, Keep::First, Order::Ascending, false);
+    let hash = Duplicates::new(Dedupe::Hash, Keep::First, Order::Ascending, false);
+
+    let a = inscription("text/plain;charset=utf-8", "foo");
+    let b = inscription("text/plain;charset=utf-8", " foo\n");
+
+    assert_eq!(text.key(&a), text.key(&b));
+    assert_ne!(hash.key(&a), hash.key(&b));
+
+    let a = inscription("image/png", "foo");
+    let b = inscription("image/png", " foo\n");
+
+    assert_ne!(text.key(&a), text.key(&b));
+  }
+}
@@ -0,0 +1,136 @@
+use {
+  super::*,
+  bitcoin::hashes::{sha256, sha512},
+  clap::ValueEnum,
+  sha3::Sha3_512,
+};
+
+#[derive(Debug, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum Algorithm {
+  Sha256,
+  #[clap(name = "sha3-256")]
+  Sha3_256,
+  #[clap(name = "sha3-512")]
+  Sha3_512,
+  Sha512,
+}
+
+impl Algorithm {
+  pub(crate) fn column(self) -> &'static str {
+    match self {
+      Self::Sha256 => "sha256",
+      Self::Sha3_256 => "sha3_256",
+      Self::Sha3_512 => "sha3_512",
+      Self::Sha512 => "sha512",
+    }
+  }
+
+  pub(crate) fn from_column(name: &str) -> Option<Self> {
+    Self::value_variants()
+      .iter()
+      .find(|algorithm| algorithm.column() == name)
+      .copied()
+  }
+
+  pub(crate) fn digest(self, data: &[u8]) -> String {
+    match self {
+      Self::Sha256 => sha256::Hash::hash(data)[..].to_hex(),
+      Self::Sha3_256 => Sha3_256::digest(data)[..].to_hex(),
+      Self::Sha3_512 => Sha3_512::digest(data)[..].to_hex(),
+      Self::Sha512 => sha512::Hash::hash(data)[..].to_hex(),
+    }
+  }
+}
+
+#[derive(Debug, Default, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum DigestInput {
+  #[default]
+  Body,
+  Text,
+}
+
+impl DigestInput {
+  /// Non-text bodies are always digested raw.
+  pub(crate) fn bytes(self, media: Media, body: &[u8]) -> Cow<'_, [u8]> {
+    match self {
+      Self::Text if media == Media::Text => Cow::Owned(normalize_text(body).into_bytes()),
+      _ => Cow::Borrowed(body),
+    }
+  }
+}
+
+/// Text as compared and digested by the exporter: decoded lossily, with line
+/// endings normalized and surrounding whitespace removed.
+pub(crate) fn normalize_text(body: &[u8]) -> String {
+  String::from_utf8_lossy(body)
+    .replace("\r\n", "\n")
+    .trim()
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn digests() {
+    assert_eq!(
+      Algorithm::Sha256.digest(b""),
+      "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+      Algorithm::Sha3_256.digest(b""),
+      "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+    );
+    assert_eq!(
+      Algorithm::Sha512.digest(b""),
+      "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+    );
+    assert_eq!(
+      Algorithm::Sha3_512.digest(b""),
+      "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26"
+    );
+  }
+
+  #[test]
+  fn algorithm_names() {
+    assert_eq!(
+      Algorithm::from_str("sha3-256", false).unwrap(),
+      Algorithm::Sha3_256
+    );
+    assert_eq!(
+      Algorithm::from_str("sha256", false).unwrap(),
+      Algorithm::Sha256
+    );
+  }
+
+  #[test]
+  fn algorithms_from_column_names() {
+    for algorithm in Algorithm::value_variants() {
+      assert_eq!(Algorithm::from_column(algorithm.column()), Some(*algorithm));
+    }
+    assert_eq!(Algorithm::from_column("hash"), None);
+  }
+
+  #[test]
+  fn text_input_only_normalizes_text() {
+    assert_eq!(
+      DigestInput::Text.bytes(Media::Text, b" foo\r\n"),
+      b"foo".as_slice()
+    );
+    assert_eq!(
+      DigestInput::Body.bytes(Media::Text, b" foo\r\n"),
+      b" foo\r\n".as_slice()
+    );
+    assert_eq!(
+      DigestInput::Text.bytes(Media::Image, b" foo"),
+      b" foo".as_slice()
+    );
+  }
+
+  #[test]
+  fn text_is_normalized() {
+    assert_eq!(normalize_text(b" foo\r\nbar\n"), "foo\nbar");
+    assert_eq!(normalize_text(b"\xff"), "\u{fffd}");
+  }
+}
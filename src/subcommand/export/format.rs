@@ -0,0 +1,214 @@
+use {super::*, clap::ValueEnum};
+
+#[derive(Debug, Default, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum Format {
+  #[default]
+  Csv,
+  Jsonl,
+  Json,
+}
+
+impl Format {
+  pub(crate) fn extension(self) -> &'static str {
+    match self {
+      Self::Csv => "csv",
+      Self::Jsonl => "jsonl",
+      Self::Json => "json",
+    }
+  }
+
+  /// The format of `export.csv` or of a compressed `export.csv.gz`.
+  pub(crate) fn from_path(path: &Path) -> Option<Self> {
+    let path = match Compression::from_path(path) {
+      Some(_) => Path::new(path.file_stem()?),
+      None => path,
+    };
+
+    match path.extension()?.to_str()? {
+      "csv" => Some(Self::Csv),
+      "jsonl" => Some(Self::Jsonl),
+      "json" => Some(Self::Json),
+      _ => None,
+    }
+  }
+}
+
+pub(crate) enum RecordWriter {
+  Csv(Box<csv::Writer<Encoder>>),
+  Jsonl(Encoder),
+  Json { writer: Encoder, first: bool },
+}
+
+impl RecordWriter {
+  pub(crate) fn new(format: Format, mut writer: Encoder, columns: &[Column]) -> Result<Self> {
+    Ok(match format {
+      Format::Csv => {
+        let mut csv = csv::WriterBuilder::new()
+          .has_headers(false)
+          .from_writer(writer);
+        csv.write_record(columns.iter().map(|column| column.name()))?;
+        Self::Csv(Box::new(csv))
+      }
+      Format::Jsonl => Self::Jsonl(writer),
+      Format::Json => {
+        writer.write_all(b"[")?;
+        Self::Json {
+          writer,
+          first: true,
+        }
+      }
+    })
+  }
+
+  pub(crate) fn append(format: Format, writer: Encoder) -> Result<Self> {
+    Ok(match format {
+      Format::Csv => Self::Csv(Box::new(
+        csv::WriterBuilder::new()
+          .has_headers(false)
+          .from_writer(writer),
+      )),
+      Format::Jsonl => Self::Jsonl(writer),
+      Format::Json => bail!("cannot append to `json` export"),
+    })
+  }
+
+  pub(crate) fn write(&mut self, row: &Row) -> Result {
+    match self {
+      Self::Csv(csv) => csv.serialize(&row.values)?,
+      Self::Jsonl(writer) => {
+        serde_json::to_writer(&mut *writer, row)?;
+        writer.write_all(b"\n")?;
+      }
+      Self::Json { writer, first } => {
+        writer.write_all(if *first { b"\n" } else { b",\n" })?;
+        serde_json::to_writer(&mut *writer, row)?;
+        *first = false;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn flush(&mut self) -> Result {
+    match self {
+      Self::Csv(csv) => csv.flush()?,
+      Self::Jsonl(writer) | Self::Json { writer, .. } => writer.flush()?,
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn finish(self) -> Result {
+    let mut writer = match self {
+      Self::Csv(mut csv) => {
+        csv.flush()?;
+        csv.into_inner().map_err(|err| anyhow!("{}", err.error()))?
+      }
+      Self::Jsonl(writer) => writer,
+      Self::Json { mut writer, .. } => {
+        writer.write_all(b"\n]\n")?;
+        writer
+      }
+    };
+
+    writer.flush()?;
+
+    writer.finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const COLUMNS: &[Column] = &[Column::Number, Column::Text];
+
+  fn row(number: u64, text: &str) -> Row<'static> {
+    Row {
+      columns: COLUMNS,
+      values: vec![number.into(), text.into()],
+    }
+  }
+
+  fn write(format: Format, rows: &[(u64, &str)]) -> String {
+    let buffer = Buffer::default();
+    let mut writer =
+      RecordWriter::new(format, Encoder::Plain(Box::new(buffer.clone())), COLUMNS).unwrap();
+    for (number, text) in rows {
+      writer.write(&row(*number, text)).unwrap();
+    }
+    writer.finish().unwrap();
+    String::from_utf8(buffer.0.take()).unwrap()
+  }
+
+  #[test]
+  fn csv() {
+    assert_eq!(
+      write(Format::Csv, &[(0, "foo"), (1, "bar\n\"baz\"")]),
+      "number,text\n0,foo\n1,\"bar\n\"\"baz\"\"\"\n"
+    );
+  }
+
+  #[test]
+  fn csv_header_is_written_without_rows() {
+    assert_eq!(write(Format::Csv, &[]), "number,text\n");
+  }
+
+  #[test]
+  fn csv_append_omits_header() {
+    let buffer = Buffer::default();
+    let mut writer =
+      RecordWriter::append(Format::Csv, Encoder::Plain(Box::new(buffer.clone()))).unwrap();
+    writer.write(&row(0, "foo")).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(String::from_utf8(buffer.0.take()).unwrap(), "0,foo\n");
+  }
+
+  #[test]
+  fn json_cannot_be_appended() {
+    assert!(
+      RecordWriter::append(Format::Json, Encoder::Plain(Box::new(Buffer::default()))).is_err()
+    );
+  }
+
+  #[test]
+  fn jsonl() {
+    assert_eq!(
+      write(Format::Jsonl, &[(0, "foo"), (1, "bar\n\"baz\"")]),
+      "{\"number\":0,\"text\":\"foo\"}\n{\"number\":1,\"text\":\"bar\\n\\\"baz\\\"\"}\n"
+    );
+  }
+
+  #[test]
+  fn json() {
+    let json = write(Format::Json, &[(0, "foo"), (1, "bar")]);
+    assert_eq!(
+      json,
+      "[\n{\"number\":0,\"text\":\"foo\"},\n{\"number\":1,\"text\":\"bar\"}\n]\n"
+    );
+    serde_json::from_str::<serde_json::Value>(&json).unwrap();
+  }
+
+  #[test]
+  fn json_without_rows_is_empty_array() {
+    assert_eq!(
+      serde_json::from_str::<serde_json::Value>(&write(Format::Json, &[])).unwrap(),
+      serde_json::json!([])
+    );
+  }
+
+  #[test]
+  fn from_path() {
+    assert_eq!(Format::from_path("export.csv".as_ref()), Some(Format::Csv));
+    assert_eq!(
+      Format::from_path("dir/export.jsonl.zst".as_ref()),
+      Some(Format::Jsonl)
+    );
+    assert_eq!(
+      Format::from_path("export.json.gz".as_ref()),
+      Some(Format::Json)
+    );
+    assert_eq!(Format::from_path("export.gz".as_ref()), None);
+    assert_eq!(Format::from_path("export".as_ref()), None);
+  }
+}
@@ -0,0 +1,75 @@
+use {super::*, clap::ValueEnum};
+
+#[derive(Debug, Default, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum LinkRoute {
+  Content,
+  #[default]
+  Inscription,
+  Preview,
+}
+
+impl LinkRoute {
+  fn path(self) -> &'static str {
+    match self {
+      Self::Content => "content",
+      Self::Inscription => "inscription",
+      Self::Preview => "preview",
+    }
+  }
+}
+
+pub(crate) struct Link {
+  base: String,
+  route: LinkRoute,
+}
+
+impl Link {
+  pub(crate) fn new(base: Option<&str>, route: LinkRoute, chain: Chain) -> Self {
+    Self {
+      base: base
+        .unwrap_or(chain.explorer())
+        .trim_end_matches('/')
+        .into(),
+      route,
+    }
+  }
+
+  pub(crate) fn url(&self, inscription_id: InscriptionId) -> String {
+    format!("{}/{}/{inscription_id}", self.base, self.route.path())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_base_depends_on_chain() {
+    assert_eq!(
+      Link::new(None, LinkRoute::Inscription, Chain::Mainnet).url(inscription_id(1)),
+      format!("https://ordinals.com/inscription/{}", inscription_id(1))
+    );
+
+    assert_eq!(
+      Link::new(None, LinkRoute::Inscription, Chain::Signet).url(inscription_id(1)),
+      format!(
+        "https://signet.ordinals.com/inscription/{}",
+        inscription_id(1)
+      )
+    );
+  }
+
+  #[test]
+  fn base_and_route_are_configurable() {
+    assert_eq!(
+      Link::new(Some("http://foo:8080/"), LinkRoute::Content, Chain::Mainnet)
+        .url(inscription_id(1)),
+      format!("http://foo:8080/content/{}", inscription_id(1))
+    );
+
+    assert_eq!(
+      Link::new(Some("http://foo"), LinkRoute::Preview, Chain::Regtest).url(inscription_id(1)),
+      format!("http://foo/preview/{}", inscription_id(1))
+    );
+  }
+}
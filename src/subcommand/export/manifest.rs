@@ -0,0 +1,314 @@
+use {super::*, bitcoin::hashes::sha256, clap::ValueEnum};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+  pub(crate) ord_version: String,
+  pub(crate) chain: Chain,
+  pub(crate) schema_version: u64,
+  pub(crate) height: Option<u64>,
+  pub(crate) blockhash: Option<BlockHash>,
+  pub(crate) filters: Filters,
+  pub(crate) rows: u64,
+  pub(crate) files: Vec<ManifestFile>,
+  pub(crate) merkle_root: Option<sha256::Hash>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ManifestFile {
+  pub(crate) path: String,
+  pub(crate) size: u64,
+  pub(crate) sha256: sha256::Hash,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Filters {
+  #[serde(default)]
+  pub(crate) transfers: bool,
+  pub(crate) media: Vec<String>,
+  pub(crate) invalid_utf8: String,
+  #[serde(default)]
+  pub(crate) r#match: Option<String>,
+  #[serde(default)]
+  pub(crate) ignore_case: bool,
+  #[serde(default)]
+  pub(crate) min_length: Option<usize>,
+  #[serde(default)]
+  pub(crate) max_length: Option<usize>,
+  pub(crate) order: String,
+  pub(crate) from_number: Option<u64>,
+  pub(crate) to_number: Option<u64>,
+  pub(crate) from_height: Option<u64>,
+  pub(crate) to_height: Option<u64>,
+  pub(crate) since: Option<String>,
+  pub(crate) until: Option<String>,
+  pub(crate) dedupe: String,
+  pub(crate) keep: String,
+  pub(crate) columns: Vec<String>,
+  pub(crate) digest_input: String,
+}
+
+impl Filters {
+  pub(crate) fn new(export: &Export) -> Self {
+    Self {
+      transfers: export.transfers,
+      media: export.media.iter().map(ToString::to_string).collect(),
+      invalid_utf8: Self::name(export.invalid_utf8),
+      r#match: export
+        .text_filter
+        .pattern
+        .as_ref()
+        .map(|pattern| pattern.as_str().into()),
+      ignore_case: export.text_filter.ignore_case,
+      min_length: export.text_filter.min_length,
+      max_length: export.text_filter.max_length,
+      order: Self::name(export.order),
+      from_number: export.bounds.from_number,
+      to_number: export.bounds.to_number,
+      from_height: export.bounds.from_height,
+      to_height: export.bounds.to_height,
+      since: export.bounds.since.map(|since| since.to_rfc3339()),
+      until: export.bounds.until.map(|until| until.to_rfc3339()),
+      dedupe: Self::name(export.dedupe),
+      keep: Self::name(export.keep),
+      columns: export
+        .columns
+        .iter()
+        .map(|column| column.name().into())
+        .collect(),
+      digest_input: Self::name(export.digest_input),
+    }
+  }
+
+  /// Parses the options these filters were recorded from, leaving columns
+  /// at their defaults.
+  pub(crate) fn export(&self) -> Result<Export> {
+    let mut args = vec![
+      "export".to_string(),
+      format!("--media={}", self.media.join(",")),
+      format!("--invalid-utf8={}", self.invalid_utf8),
+      format!("--order={}", self.order),
+      format!("--dedupe={}", self.dedupe),
+      format!("--keep={}", self.keep),
+      format!("--digest-input={}", self.digest_input),
+    ];
+
+    if let Some(pattern) = &self.r#match {
+      args.push(format!("--match={pattern}"));
+    }
+
+    if self.ignore_case {
+      args.push("--ignore-case".into());
+    }
+
+    for (flag, value) in [
+      (
+        "min-length",
+        self.min_length.map(|length| length.to_string()),
+      ),
+      (
+        "max-length",
+        self.max_length.map(|length| length.to_string()),
+      ),
+      (
+        "from-number",
+        self.from_number.map(|number| number.to_string()),
+      ),
+      ("to-number", self.to_number.map(|number| number.to_string())),
+      (
+        "from-height",
+        self.from_height.map(|height| height.to_string()),
+      ),
+      ("to-height", self.to_height.map(|height| height.to_string())),
+      ("since", self.since.clone()),
+      ("until", self.until.clone()),
+    ] {
+      if let Some(value) = value {
+        args.push(format!("--{flag}={value}"));
+      }
+    }
+
+    let mut export =
+      Export::try_parse_from(args).map_err(|err| anyhow!("invalid manifest filters: {err}"))?;
+
+    export.text_filter.check()?;
+
+    Ok(export)
+  }
+
+  fn name(value: impl ValueEnum) -> String {
+    value.to_possible_value().unwrap().get_name().into()
+  }
+}
+
+impl Manifest {
+  /// The manifest of `export.csv`, or of its shards `export-00000.csv` and so
+  /// on, is written to `export.csv.manifest.json`.
+  pub(crate) fn default_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".manifest.json");
+    path.into()
+  }
+
+  pub(crate) fn load(path: &Path) -> Result<Self> {
+    let json = fs::read(path).with_context(|| format!("I/O error reading `{}`", path.display()))?;
+
+    serde_json::from_slice(&json)
+      .with_context(|| format!("failed to parse manifest `{}`", path.display()))
+  }
+
+  pub(crate) fn file(path: &Path) -> Result<ManifestFile> {
+    let mut file =
+      File::open(path).with_context(|| format!("I/O error reading `{}`", path.display()))?;
+
+    let mut engine = sha256::Hash::engine();
+    let size = io::copy(&mut file, &mut engine)
+      .with_context(|| format!("I/O error reading `{}`", path.display()))?;
+
+    Ok(ManifestFile {
+      path: path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default(),
+      size,
+      sha256: sha256::Hash::from_engine(engine),
+    })
+  }
+
+  pub(crate) fn save(&self, path: &Path) -> Result {
+    let mut json = serde_json::to_vec_pretty(self)?;
+    json.push(b'\n');
+    fs::write(path, json).with_context(|| format!("I/O error writing `{}`", path.display()))
+  }
+
+  /// Rows are committed to by the SHA-256 of their JSON serialization, so the
+  /// Merkle root doesn't depend on output format or compression.
+  pub(crate) fn leaf(row: &Row) -> Result<sha256::Hash> {
+    let mut engine = sha256::Hash::engine();
+    serde_json::to_writer(&mut engine, row)?;
+    Ok(sha256::Hash::from_engine(engine))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_path() {
+    assert_eq!(
+      Manifest::default_path("dir/export.csv.gz".as_ref()),
+      Path::new("dir/export.csv.gz.manifest.json")
+    );
+  }
+
+  #[test]
+  fn file_digest() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("export.csv");
+    fs::write(&path, "").unwrap();
+
+    assert_eq!(
+      Manifest::file(&path).unwrap(),
+      ManifestFile {
+        path: "export.csv".into(),
+        size: 0,
+        sha256: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+          .parse()
+          .unwrap(),
+      }
+    );
+  }
+
+  #[test]
+  fn leaf_is_independent_of_format() {
+    let row = Row {
+      columns: &[Column::Number],
+      values: vec![0.into()],
+    };
+
+    assert_eq!(
+      Manifest::leaf(&row).unwrap(),
+      sha256::Hash::hash(br#"{"number":0}"#)
+    );
+  }
+
+  #[test]
+  fn filters_record_export_arguments() {
+    let filters = Filters::new(
+      &Arguments::try_parse_from([
+        "ord",
+        "export",
+        "--media",
+        "all",
+        "--from-height",
+        "5",
+        "--keep",
+        "last",
+        "--match",
+        "^foo",
+        "--ignore-case",
+        "--max-length",
+        "10",
+      ])
+      .map(|arguments| match arguments.subcommand {
+        Subcommand::Export(export) => *export,
+        _ => unreachable!(),
+      })
+      .unwrap(),
+    );
+
+    assert_eq!(filters.media, ["all"]);
+    assert_eq!(filters.invalid_utf8, "replace");
+    assert_eq!(filters.r#match.as_deref(), Some("^foo"));
+    assert!(filters.ignore_case);
+    assert_eq!(filters.min_length, None);
+    assert_eq!(filters.max_length, Some(10));
+    assert_eq!(filters.order, "ascending");
+    assert_eq!(filters.from_height, Some(5));
+    assert_eq!(filters.keep, "last");
+    assert_eq!(filters.dedupe, "text");
+    assert_eq!(filters.digest_input, "body");
+  }
+
+  #[test]
+  fn filters_round_trip() {
+    let filters = Filters::new(
+      &Filters::new(
+        &Arguments::try_parse_from([
+          "ord",
+          "export",
+          "--media",
+          "image,text",
+          "--match",
+          "^foo",
+          "--ignore-case",
+          "--since",
+          "2000-01-01T00:00:00Z",
+          "--to-number",
+          "10",
+          "--dedupe",
+          "hash",
+          "--digest-input",
+          "text",
+        ])
+        .map(|arguments| match arguments.subcommand {
+          Subcommand::Export(export) => *export,
+          _ => unreachable!(),
+        })
+        .unwrap(),
+      )
+      .export()
+      .unwrap(),
+    );
+
+    assert_eq!(filters.media, ["image", "text"]);
+    assert_eq!(filters.r#match.as_deref(), Some("^foo"));
+    assert!(filters.ignore_case);
+    assert_eq!(filters.since.as_deref(), Some("2000-01-01T00:00:00+00:00"));
+    assert_eq!(filters.to_number, Some(10));
+    assert_eq!(filters.order, "ascending");
+    assert_eq!(filters.dedupe, "hash");
+    assert_eq!(filters.keep, "first");
+    assert_eq!(filters.digest_input, "text");
+  }
+}
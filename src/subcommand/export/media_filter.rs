@@ -0,0 +1,89 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum MediaFilter {
+  All,
+  Media(Media),
+}
+
+impl MediaFilter {
+  pub(crate) fn matches(self, media: Media) -> bool {
+    match self {
+      Self::All => true,
+      Self::Media(filter) => filter == media,
+    }
+  }
+}
+
+impl Display for MediaFilter {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::All => write!(f, "all"),
+      Self::Media(media) => write!(f, "{media}"),
+    }
+  }
+}
+
+impl FromStr for MediaFilter {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(match s {
+      "all" => Self::All,
+      "audio" => Self::Media(Media::Audio),
+      "iframe" => Self::Media(Media::Iframe),
+      "image" => Self::Media(Media::Image),
+      "pdf" => Self::Media(Media::Pdf),
+      "text" => Self::Media(Media::Text),
+      "unknown" => Self::Media(Media::Unknown),
+      "video" => Self::Media(Media::Video),
+      _ => bail!("invalid media: {s}"),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn from_str() {
+    assert_eq!("all".parse::<MediaFilter>().unwrap(), MediaFilter::All);
+    assert_eq!(
+      "image".parse::<MediaFilter>().unwrap(),
+      MediaFilter::Media(Media::Image)
+    );
+    assert_eq!(
+      "foo".parse::<MediaFilter>().unwrap_err().to_string(),
+      "invalid media: foo"
+    );
+  }
+
+  #[test]
+  fn round_trips_media_display() {
+    for media in [
+      Media::Audio,
+      Media::Iframe,
+      Media::Image,
+      Media::Pdf,
+      Media::Text,
+      Media::Unknown,
+      Media::Video,
+    ] {
+      assert_eq!(
+        media.to_string().parse::<MediaFilter>().unwrap(),
+        MediaFilter::Media(media)
+      );
+      assert_eq!(MediaFilter::Media(media).to_string(), media.to_string());
+    }
+
+    assert_eq!(MediaFilter::All.to_string(), "all");
+  }
+
+  #[test]
+  fn matches() {
+    assert!(MediaFilter::All.matches(Media::Video));
+    assert!(MediaFilter::Media(Media::Text).matches(Media::Text));
+    assert!(!MediaFilter::Media(Media::Text).matches(Media::Image));
+  }
+}
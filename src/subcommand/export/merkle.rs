@@ -0,0 +1,119 @@
+use {
+  super::*,
+  bitcoin::hashes::{sha256, HashEngine},
+};
+
+/// Incremental Merkle tree over SHA-256 leaves, combining nodes as Bitcoin
+/// does, duplicating the last node of odd-length levels. Only one pending
+/// node per level is kept, so memory is logarithmic in the number of leaves.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Merkle(Vec<Option<sha256::Hash>>);
+
+impl Merkle {
+  fn combine(left: sha256::Hash, right: sha256::Hash) -> sha256::Hash {
+    let mut engine = sha256::Hash::engine();
+    engine.input(&left[..]);
+    engine.input(&right[..]);
+    sha256::Hash::from_engine(engine)
+  }
+
+  pub(crate) fn push(&mut self, leaf: sha256::Hash) {
+    let mut node = leaf;
+
+    for level in &mut self.0 {
+      match level.take() {
+        Some(left) => node = Self::combine(left, node),
+        None => {
+          *level = Some(node);
+          return;
+        }
+      }
+    }
+
+    self.0.push(Some(node));
+  }
+
+  pub(crate) fn root(&self) -> Option<sha256::Hash> {
+    let mut carry = None;
+
+    for (i, level) in self.0.iter().enumerate() {
+      let higher = self.0[i + 1..].iter().any(Option::is_some);
+
+      carry = match (*level, carry) {
+        (Some(left), Some(right)) => Some(Self::combine(left, right)),
+        (Some(node), None) | (None, Some(node)) if higher => Some(Self::combine(node, node)),
+        (Some(node), None) | (None, Some(node)) => return Some(node),
+        (None, None) => None,
+      };
+    }
+
+    carry
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn leaf(n: u8) -> sha256::Hash {
+    sha256::Hash::hash(&[n])
+  }
+
+  fn reference(mut level: Vec<sha256::Hash>) -> Option<sha256::Hash> {
+    if level.is_empty() {
+      return None;
+    }
+
+    while level.len() > 1 {
+      if level.len() % 2 == 1 {
+        level.push(*level.last().unwrap());
+      }
+
+      level = level
+        .chunks(2)
+        .map(|pair| Merkle::combine(pair[0], pair[1]))
+        .collect();
+    }
+
+    Some(level[0])
+  }
+
+  #[test]
+  fn empty() {
+    assert_eq!(Merkle::default().root(), None);
+  }
+
+  #[test]
+  fn single_leaf_is_root() {
+    let mut merkle = Merkle::default();
+    merkle.push(leaf(0));
+    assert_eq!(merkle.root(), Some(leaf(0)));
+  }
+
+  #[test]
+  fn matches_bitcoin_style_reference() {
+    for n in 1..=33 {
+      let leaves = (0..n).map(leaf).collect::<Vec<sha256::Hash>>();
+
+      let mut merkle = Merkle::default();
+      for leaf in &leaves {
+        merkle.push(*leaf);
+      }
+
+      assert_eq!(merkle.root(), reference(leaves), "{n} leaves");
+    }
+  }
+
+  #[test]
+  fn round_trips_through_json() {
+    let mut merkle = Merkle::default();
+    for n in 0..5 {
+      merkle.push(leaf(n));
+    }
+
+    assert_eq!(
+      serde_json::from_str::<Merkle>(&serde_json::to_string(&merkle).unwrap()).unwrap(),
+      merkle
+    );
+  }
+}
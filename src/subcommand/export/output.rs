@@ -0,0 +1,513 @@
+use {
+  super::*,
+  std::{cell::Cell, mem},
+};
+
+/// Destination of exported rows, which may be compressed and split into
+/// numbered shards, each with its own header.
+pub(crate) struct Output {
+  bucket: Option<u64>,
+  bytes: Rc<Cell<u64>>,
+  columns: Vec<Column>,
+  compression: Option<Compression>,
+  files: Vec<PathBuf>,
+  force: bool,
+  format: Format,
+  merkle: Merkle,
+  path: Option<PathBuf>,
+  rows: u64,
+  shard: u64,
+  shard_rows: u64,
+  split: Option<Split>,
+  writer: RecordWriter,
+}
+
+/// What was written, for the manifest.
+pub(crate) struct Summary {
+  pub(crate) files: Vec<PathBuf>,
+  pub(crate) merkle: Merkle,
+  pub(crate) rows: u64,
+}
+
+impl Output {
+  pub(crate) fn new(export: &Export, checkpoint: Option<&Checkpoint>) -> Result<Self> {
+    let append = checkpoint.is_some();
+
+    let compression = export
+      .compress
+      .or_else(|| export.output.as_deref().and_then(Compression::from_path));
+
+    let path = match &export.output {
+      Some(path) if path == Path::new("-") => None,
+      Some(path) => Some(path.clone()),
+      None => Some(
+        format!(
+          "{}{}",
+          Utc::now().format("%d-%m-%Y_%H-%M"),
+          Self::suffix(export.format, compression)
+        )
+        .into(),
+      ),
+    };
+
+    if export.split_by.is_some() && path.is_none() {
+      bail!("`--split-by` cannot be used when writing to stdout");
+    }
+
+    let shard = path
+      .as_deref()
+      .map(|path| Self::shard_path(path, export.format, compression, export.split_by, 0));
+
+    let bytes = Rc::new(Cell::new(match checkpoint {
+      Some(checkpoint) => Self::truncate(shard.as_deref(), checkpoint)?,
+      None => 0,
+    }));
+
+    let writer = Self::record_writer(
+      export.format,
+      compression,
+      &export.columns,
+      Self::open(shard.as_deref(), bytes.clone(), append, export.force)?,
+      append,
+    )?;
+
+    Ok(Self {
+      bucket: None,
+      bytes,
+      columns: export.columns.clone(),
+      compression,
+      files: shard.into_iter().collect(),
+      force: export.force,
+      format: export.format,
+      merkle: checkpoint
+        .map(|checkpoint| checkpoint.merkle.clone())
+        .unwrap_or_default(),
+      path,
+      rows: checkpoint.map(|checkpoint| checkpoint.rows).unwrap_or(0),
+      shard: 0,
+      shard_rows: 0,
+      split: export.split_by,
+      writer,
+    })
+  }
+
+  /// Uncompressed and unsplit output to `writer`, for `ord server`.
+  pub(crate) fn stream(export: &Export, writer: Box<dyn Write>) -> Result<Self> {
+    Ok(Self {
+      bucket: None,
+      bytes: Rc::new(Cell::new(0)),
+      columns: export.columns.clone(),
+      compression: None,
+      files: Vec::new(),
+      force: false,
+      format: export.format,
+      merkle: Merkle::default(),
+      path: None,
+      rows: 0,
+      shard: 0,
+      shard_rows: 0,
+      split: None,
+      writer: Self::record_writer(export.format, None, &export.columns, writer, false)?,
+    })
+  }
+
+  pub(crate) fn write(&mut self, row: &Row, height: u64) -> Result {
+    if let Some(split) = self.split {
+      let full = match split {
+        Split::Bytes(bytes) => self.bytes.get() >= bytes,
+        Split::Height(blocks) => self
+          .bucket
+          .map_or(false, |bucket| bucket != height / blocks),
+        Split::Rows(rows) => self.shard_rows >= rows,
+      };
+
+      if full && self.shard_rows > 0 {
+        self.rotate()?;
+      }
+
+      if let Split::Height(blocks) = split {
+        self.bucket = Some(height / blocks);
+      }
+    }
+
+    self.writer.write(row)?;
+    self.merkle.push(Manifest::leaf(row)?);
+    self.shard_rows += 1;
+    self.rows += 1;
+
+    Ok(())
+  }
+
+  pub(crate) fn flush(&mut self) -> Result {
+    self.writer.flush()
+  }
+
+  /// Writes out everything written so far, ending the current compressed
+  /// stream so that the output can be truncated to the returned length and
+  /// appended to with a new one.
+  pub(crate) fn checkpoint(&mut self) -> Result<u64> {
+    if self.compression.is_some() {
+      mem::replace(
+        &mut self.writer,
+        RecordWriter::Jsonl(Encoder::Plain(Box::new(io::sink()))),
+      )
+      .finish()?;
+
+      self.writer = Self::record_writer(
+        self.format,
+        self.compression,
+        &self.columns,
+        Self::open(
+          self.files.last().map(PathBuf::as_path),
+          self.bytes.clone(),
+          true,
+          false,
+        )?,
+        true,
+      )?;
+    } else {
+      self.writer.flush()?;
+    }
+
+    Ok(self.bytes.get())
+  }
+
+  pub(crate) fn finish(self) -> Result<Summary> {
+    self.writer.finish()?;
+
+    Ok(Summary {
+      files: self.files,
+      merkle: self.merkle,
+      rows: self.rows,
+    })
+  }
+
+  pub(crate) fn merkle(&self) -> &Merkle {
+    &self.merkle
+  }
+
+  pub(crate) fn path(&self) -> Option<&Path> {
+    self.path.as_deref()
+  }
+
+  pub(crate) fn rows(&self) -> u64 {
+    self.rows
+  }
+
+  fn rotate(&mut self) -> Result {
+    self.shard += 1;
+    self.shard_rows = 0;
+    self.bytes.set(0);
+
+    let path = Self::shard_path(
+      self.path.as_deref().unwrap(),
+      self.format,
+      self.compression,
+      self.split,
+      self.shard,
+    );
+
+    let writer = Self::record_writer(
+      self.format,
+      self.compression,
+      &self.columns,
+      Self::open(Some(&path), self.bytes.clone(), false, self.force)?,
+      false,
+    )?;
+
+    self.files.push(path);
+
+    mem::replace(&mut self.writer, writer).finish()
+  }
+
+  /// Drops anything written to `path` after `checkpoint` was saved, returning
+  /// the length of the output it covers.
+  fn truncate(path: Option<&Path>, checkpoint: &Checkpoint) -> Result<u64> {
+    let Some(path) = path else {
+      bail!("`--checkpoint` cannot be used when writing to stdout");
+    };
+
+    let Some(bytes) = checkpoint.bytes else {
+      bail!(
+        "checkpoint does not record the length of `{}`, export must be rebuilt",
+        path.display()
+      );
+    };
+
+    let file = match File::options().write(true).open(path) {
+      Ok(file) => file,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => bail!(
+        "output file `{}` recorded in checkpoint is missing, export must be rebuilt",
+        path.display()
+      ),
+      Err(err) => {
+        return Err(err).with_context(|| format!("I/O error opening `{}`", path.display()))
+      }
+    };
+
+    let len = file.metadata()?.len();
+
+    if len < bytes {
+      bail!(
+        "output file `{}` is {len} bytes, shorter than the {bytes} bytes recorded in checkpoint, export must be rebuilt",
+        path.display()
+      );
+    }
+
+    file
+      .set_len(bytes)
+      .with_context(|| format!("I/O error truncating `{}`", path.display()))?;
+
+    Ok(bytes)
+  }
+
+  fn open(
+    path: Option<&Path>,
+    bytes: Rc<Cell<u64>>,
+    append: bool,
+    force: bool,
+  ) -> Result<Box<dyn Write>> {
+    Ok(match path {
+      Some(path) => Box::new(Counter {
+        bytes,
+        inner: Self::create(path, append, force)?,
+      }),
+      None => Box::new(io::stdout().lock()),
+    })
+  }
+
+  fn record_writer(
+    format: Format,
+    compression: Option<Compression>,
+    columns: &[Column],
+    writer: Box<dyn Write>,
+    append: bool,
+  ) -> Result<RecordWriter> {
+    let encoder = Compression::encoder(compression, writer)?;
+
+    if append {
+      RecordWriter::append(format, encoder)
+    } else {
+      RecordWriter::new(format, encoder, columns)
+    }
+  }
+
+  fn create(path: &Path, append: bool, force: bool) -> Result<Box<dyn Write>> {
+    let file = if append {
+      File::options().append(true).create(true).open(path)
+    } else if force {
+      File::create(path)
+    } else {
+      File::options().write(true).create_new(true).open(path)
+    };
+
+    match file {
+      Ok(file) => Ok(Box::new(BufWriter::new(file))),
+      Err(err) if err.kind() == io::ErrorKind::AlreadyExists => bail!(
+        "output file `{}` already exists, use `--force` to overwrite it",
+        path.display()
+      ),
+      Err(err) => Err(err).with_context(|| format!("I/O error creating `{}`", path.display())),
+    }
+  }
+
+  fn suffix(format: Format, compression: Option<Compression>) -> String {
+    match compression {
+      Some(compression) => format!(".{}.{}", format.extension(), compression.extension()),
+      None => format!(".{}", format.extension()),
+    }
+  }
+
+  /// Shards are numbered before the format and compression extensions, so
+  /// `export.csv.gz` is split into `export-00000.csv.gz`, `export-00001.csv.gz`
+  /// and so on.
+  fn shard_path(
+    path: &Path,
+    format: Format,
+    compression: Option<Compression>,
+    split: Option<Split>,
+    shard: u64,
+  ) -> PathBuf {
+    if split.is_none() {
+      return path.into();
+    }
+
+    let name = path
+      .file_name()
+      .map(|name| name.to_string_lossy().into_owned())
+      .unwrap_or_default();
+
+    let suffix = Self::suffix(format, compression);
+
+    let (stem, suffix) = match name.strip_suffix(&suffix) {
+      Some(stem) => (stem, suffix.as_str()),
+      None => (name.as_str(), ""),
+    };
+
+    path.with_file_name(format!("{stem}-{shard:05}{suffix}"))
+  }
+}
+
+struct Counter {
+  bytes: Rc<Cell<u64>>,
+  inner: Box<dyn Write>,
+}
+
+impl Write for Counter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let n = self.inner.write(buf)?;
+    self.bytes.set(self.bytes.get() + u64::try_from(n).unwrap());
+    Ok(n)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn append_to_existing_output() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("export.csv");
+    fs::write(&path, "foo").unwrap();
+
+    Output::create(&path, true, false)
+      .unwrap()
+      .write_all(b"bar")
+      .unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "foobar");
+  }
+
+  fn checkpoint(bytes: Option<u64>) -> Checkpoint {
+    Checkpoint {
+      number: Some(0),
+      height: 0,
+      blockhash: blockhash(0),
+      bytes,
+      rows: 1,
+      merkle: Merkle::default(),
+    }
+  }
+
+  #[test]
+  fn resume_truncates_output_to_checkpoint() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("export.csv");
+    fs::write(&path, "id\nfoo\nbar\n").unwrap();
+
+    assert_eq!(
+      Output::truncate(Some(&path), &checkpoint(Some(7))).unwrap(),
+      7
+    );
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id\nfoo\n");
+  }
+
+  #[test]
+  fn resume_requires_output_covering_checkpoint() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("export.csv");
+
+    assert_regex_match!(
+      Output::truncate(Some(&path), &checkpoint(Some(7))).unwrap_err(),
+      "output file `.*export.csv` recorded in checkpoint is missing, export must be rebuilt"
+    );
+
+    fs::write(&path, "id\n").unwrap();
+
+    assert_regex_match!(
+      Output::truncate(Some(&path), &checkpoint(Some(7))).unwrap_err(),
+      "output file `.*export.csv` is 3 bytes, shorter than the 7 bytes recorded in checkpoint, export must be rebuilt"
+    );
+
+    assert_regex_match!(
+      Output::truncate(Some(&path), &checkpoint(None)).unwrap_err(),
+      "checkpoint does not record the length of `.*export.csv`, export must be rebuilt"
+    );
+
+    assert_eq!(
+      Output::truncate(None, &checkpoint(Some(7)))
+        .unwrap_err()
+        .to_string(),
+      "`--checkpoint` cannot be used when writing to stdout"
+    );
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id\n");
+  }
+
+  #[test]
+  fn refuse_to_overwrite_existing_output() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("export.csv");
+    fs::write(&path, "foo").unwrap();
+
+    assert_regex_match!(
+      Output::create(&path, false, false).err().unwrap(),
+      "output file `.*export.csv` already exists, use `--force` to overwrite it"
+    );
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "foo");
+  }
+
+  #[test]
+  fn force_overwrites_existing_output() {
+    let tempdir = TempDir::new().unwrap();
+    let path = tempdir.path().join("export.csv");
+    fs::write(&path, "foo").unwrap();
+
+    Output::create(&path, false, true)
+      .unwrap()
+      .write_all(b"bar")
+      .unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "bar");
+  }
+
+  #[test]
+  fn shard_paths() {
+    let split = Some(Split::Rows(1));
+
+    assert_eq!(
+      Output::shard_path("dir/export.csv".as_ref(), Format::Csv, None, None, 0),
+      Path::new("dir/export.csv")
+    );
+
+    assert_eq!(
+      Output::shard_path("dir/export.csv".as_ref(), Format::Csv, None, split, 1),
+      Path::new("dir/export-00001.csv")
+    );
+
+    assert_eq!(
+      Output::shard_path(
+        "export.jsonl.zst".as_ref(),
+        Format::Jsonl,
+        Some(Compression::Zstd),
+        split,
+        2
+      ),
+      Path::new("export-00002.jsonl.zst")
+    );
+
+    assert_eq!(
+      Output::shard_path("export".as_ref(), Format::Csv, None, split, 0),
+      Path::new("export-00000")
+    );
+  }
+
+  #[test]
+  fn counter_counts_written_bytes() {
+    let bytes = Rc::new(Cell::new(0));
+    let mut counter = Counter {
+      bytes: bytes.clone(),
+      inner: Box::new(io::sink()),
+    };
+    counter.write_all(b"foo").unwrap();
+    counter.write_all(b"bar").unwrap();
+    assert_eq!(bytes.get(), 6);
+  }
+}
@@ -0,0 +1,312 @@
+use {
+  super::*,
+  std::sync::{
+    atomic::{AtomicBool, AtomicUsize},
+    mpsc,
+  },
+};
+
+/// A page of the walk with its inscriptions fetched, in walk order, without
+/// the inscriptions that `fetch` skipped.
+pub(crate) struct Fetched {
+  pub(crate) inscriptions: Vec<(InscriptionId, InscriptionEntry, Inscription)>,
+  pub(crate) last: u64,
+  pub(crate) visited: u64,
+}
+
+/// Exports run as a pipeline: a producer thread walks the index a page at a
+/// time, `jobs` workers fetch and parse each page's inscriptions concurrently,
+/// and the consumer receives fetched pages in walk order. The producer stays
+/// at most one page ahead of the consumer.
+pub(crate) struct Pipeline {
+  jobs: usize,
+  stop: AtomicBool,
+}
+
+impl Pipeline {
+  pub(crate) fn new(jobs: usize) -> Self {
+    Self {
+      jobs: jobs.max(1),
+      stop: AtomicBool::new(false),
+    }
+  }
+
+  /// Runs `consume` on each fetched page until the pages run out, an error
+  /// occurs, or `consume` returns `false`.
+  pub(crate) fn run<F, C>(
+    &self,
+    pages: impl Iterator<Item = Result<Page>> + Send,
+    fetch: F,
+    mut consume: C,
+  ) -> Result
+  where
+    F: Fn(InscriptionId) -> Result<Option<Inscription>> + Sync,
+    C: FnMut(Fetched) -> Result<bool>,
+  {
+    thread::scope(|scope| {
+      let (sender, receiver) = mpsc::sync_channel(1);
+
+      let fetch = &fetch;
+
+      scope.spawn(move || {
+        for page in pages {
+          let fetched = page.and_then(|page| self.fetch(page, fetch));
+          let failed = fetched.is_err();
+
+          if sender.send(fetched).is_err() || failed {
+            break;
+          }
+        }
+      });
+
+      let mut result = Ok(());
+
+      for fetched in receiver {
+        match fetched.and_then(&mut consume) {
+          Ok(true) => {}
+          Ok(false) => break,
+          Err(err) => {
+            result = Err(err);
+            break;
+          }
+        }
+      }
+
+      // Stop the producer's workers early, instead of finishing a page nobody
+      // will consume. Dropping the receiver unblocks a producer waiting to send.
+      self.stop.store(true, atomic::Ordering::Relaxed);
+
+      result
+    })
+  }
+
+  fn fetch<F>(&self, page: Page, fetch: &F) -> Result<Fetched>
+  where
+    F: Fn(InscriptionId) -> Result<Option<Inscription>> + Sync,
+  {
+    let next = AtomicUsize::new(0);
+
+    let results = thread::scope(|scope| {
+      let workers = (0..self.jobs.min(page.inscriptions.len()))
+        .map(|_| {
+          scope.spawn(|| {
+            let mut fetched = Vec::new();
+
+            while !self.stop.load(atomic::Ordering::Relaxed) {
+              let i = next.fetch_add(1, atomic::Ordering::Relaxed);
+
+              let Some((inscription_id, _)) = page.inscriptions.get(i) else {
+                break;
+              };
+
+              match fetch(*inscription_id) {
+                Ok(inscription) => fetched.push((i, inscription)),
+                Err(err) => {
+                  self.stop.store(true, atomic::Ordering::Relaxed);
+                  return Err(err);
+                }
+              }
+            }
+
+            Ok(fetched)
+          })
+        })
+        .collect::<Vec<_>>();
+
+      workers
+        .into_iter()
+        .map(|worker| worker.join().unwrap())
+        .collect::<Result<Vec<Vec<(usize, Option<Inscription>)>>>>()
+    })?;
+
+    let mut inscriptions = page
+      .inscriptions
+      .iter()
+      .map(|_| None)
+      .collect::<Vec<Option<Inscription>>>();
+
+    for (i, inscription) in results.into_iter().flatten() {
+      inscriptions[i] = inscription;
+    }
+
+    Ok(Fetched {
+      inscriptions: page
+        .inscriptions
+        .into_iter()
+        .zip(inscriptions)
+        .filter_map(|((inscription_id, entry), inscription)| {
+          Some((inscription_id, entry, inscription?))
+        })
+        .collect(),
+      last: page.last,
+      visited: page.visited,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn id(n: u32) -> InscriptionId {
+    InscriptionId {
+      txid: txid(1),
+      index: n,
+    }
+  }
+
+  fn page(numbers: std::ops::Range<u32>) -> Result<Page> {
+    Ok(Page {
+      inscriptions: numbers
+        .clone()
+        .map(|n| {
+          (
+            id(n),
+            InscriptionEntry {
+              fee: 0,
+              height: 0,
+              number: n.into(),
+              sat: None,
+              timestamp: 0,
+            },
+          )
+        })
+        .collect(),
+      last: (numbers.end - 1).into(),
+      visited: numbers.len().try_into().unwrap(),
+    })
+  }
+
+  fn numbers(pages: &[Fetched]) -> Vec<u64> {
+    pages
+      .iter()
+      .flat_map(|page| page.inscriptions.iter().map(|(_, entry, _)| entry.number))
+      .collect()
+  }
+
+  #[test]
+  fn pages_are_consumed_in_order() {
+    let channels = (0..8)
+      .map(|_| {
+        let (sender, receiver) = mpsc::channel();
+        (sender, Mutex::new(receiver))
+      })
+      .collect::<Vec<(mpsc::Sender<()>, Mutex<mpsc::Receiver<()>>)>>();
+
+    let finished = Mutex::new(Vec::new());
+
+    let mut pages = Vec::new();
+
+    Pipeline::new(4)
+      .run(
+        [page(0..4), page(4..8)].into_iter(),
+        |inscription_id| {
+          // Finish each page's inscriptions last to first, with one worker
+          // per inscription so that none waits on one it has yet to fetch.
+          let n = usize::try_from(inscription_id.index).unwrap();
+          if n % 4 != 3 {
+            channels[n + 1].1.lock().unwrap().recv().unwrap();
+          }
+          finished.lock().unwrap().push(n);
+          channels[n].0.send(()).unwrap();
+          Ok(Some(inscription("text/plain;charset=utf-8", "foo")))
+        },
+        |page| {
+          pages.push(page);
+          Ok(true)
+        },
+      )
+      .unwrap();
+
+    assert_eq!(*finished.lock().unwrap(), [3, 2, 1, 0, 7, 6, 5, 4]);
+    assert_eq!(numbers(&pages), (0..8).collect::<Vec<u64>>());
+    assert_eq!(pages[0].last, 3);
+    assert_eq!(pages[1].visited, 4);
+  }
+
+  #[test]
+  fn skipped_inscriptions_are_omitted() {
+    let mut pages = Vec::new();
+
+    Pipeline::new(2)
+      .run(
+        [page(1..10)].into_iter(),
+        |inscription_id| {
+          Ok(
+            (inscription_id.index % 2 == 0).then(|| inscription("text/plain;charset=utf-8", "foo")),
+          )
+        },
+        |page| {
+          pages.push(page);
+          Ok(true)
+        },
+      )
+      .unwrap();
+
+    assert_eq!(numbers(&pages), [2, 4, 6, 8]);
+    assert_eq!(pages[0].visited, 9);
+  }
+
+  #[test]
+  fn consumer_can_stop_early() {
+    let mut consumed = 0;
+
+    Pipeline::new(2)
+      .run(
+        (0..100).map(|i| page(i * 10..i * 10 + 10)),
+        |_| Ok(Some(inscription("text/plain;charset=utf-8", "foo"))),
+        |_| {
+          consumed += 1;
+          Ok(consumed < 2)
+        },
+      )
+      .unwrap();
+
+    assert_eq!(consumed, 2);
+  }
+
+  #[test]
+  fn errors_are_returned() {
+    assert_eq!(
+      Pipeline::new(2)
+        .run(
+          [page(1..10)].into_iter(),
+          |inscription_id| {
+            if inscription_id.index == 5 {
+              Err(anyhow!("failed to fetch {inscription_id}"))
+            } else {
+              Ok(None)
+            }
+          },
+          |_| Ok(true),
+        )
+        .unwrap_err()
+        .to_string(),
+      format!("failed to fetch {}", id(5))
+    );
+
+    assert_eq!(
+      Pipeline::new(2)
+        .run(
+          [page(1..10), Err(anyhow!("bad page"))].into_iter(),
+          |_| Ok(None),
+          |_| Ok(true),
+        )
+        .unwrap_err()
+        .to_string(),
+      "bad page"
+    );
+
+    assert_eq!(
+      Pipeline::new(2)
+        .run(
+          [page(1..10)].into_iter(),
+          |_| Ok(None),
+          |_| Err(anyhow!("bad write")),
+        )
+        .unwrap_err()
+        .to_string(),
+      "bad write"
+    );
+  }
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Split {
+  Bytes(u64),
+  Height(u64),
+  Rows(u64),
+}
+
+impl FromStr for Split {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let (kind, n) = s.split_once(':').ok_or_else(|| {
+      anyhow!("invalid split `{s}`, expected `rows:<N>`, `bytes:<N>` or `height:<N>`")
+    })?;
+
+    let n = n
+      .parse::<u64>()
+      .with_context(|| format!("invalid split size `{n}`"))?;
+
+    if n == 0 {
+      bail!("split size must be greater than zero");
+    }
+
+    Ok(match kind {
+      "bytes" => Self::Bytes(n),
+      "height" => Self::Height(n),
+      "rows" => Self::Rows(n),
+      _ => bail!("invalid split `{s}`, expected `rows:<N>`, `bytes:<N>` or `height:<N>`"),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn from_str() {
+    assert_eq!("rows:10".parse::<Split>().unwrap(), Split::Rows(10));
+    assert_eq!("bytes:1000".parse::<Split>().unwrap(), Split::Bytes(1000));
+    assert_eq!(
+      "height:10000".parse::<Split>().unwrap(),
+      Split::Height(10000)
+    );
+  }
+
+  #[test]
+  fn invalid() {
+    assert_eq!(
+      "rows".parse::<Split>().unwrap_err().to_string(),
+      "invalid split `rows`, expected `rows:<N>`, `bytes:<N>` or `height:<N>`"
+    );
+    assert_eq!(
+      "blocks:1".parse::<Split>().unwrap_err().to_string(),
+      "invalid split `blocks:1`, expected `rows:<N>`, `bytes:<N>` or `height:<N>`"
+    );
+    assert_eq!(
+      "rows:foo".parse::<Split>().unwrap_err().to_string(),
+      "invalid split size `foo`"
+    );
+    assert_eq!(
+      "rows:0".parse::<Split>().unwrap_err().to_string(),
+      "split size must be greater than zero"
+    );
+  }
+}
@@ -0,0 +1,103 @@
+use {super::*, clap::ValueEnum, serde_json::Value};
+
+#[derive(Debug, Default, ValueEnum, Copy, Clone, PartialEq)]
+pub(crate) enum InvalidUtf8 {
+  #[default]
+  Replace,
+  Skip,
+  Quarantine,
+}
+
+/// The shape of a text body, detected from its contents rather than its
+/// declared content type, which is often wrong.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Structure {
+  Empty,
+  JsonArray,
+  JsonObject,
+  MultiLine,
+  Pgp,
+  SingleLine,
+  Yaml,
+}
+
+impl Structure {
+  pub(crate) fn detect(text: &str) -> Self {
+    let text = text.trim();
+
+    if text.is_empty() {
+      return Self::Empty;
+    }
+
+    if text.starts_with("-----BEGIN PGP ") {
+      return Self::Pgp;
+    }
+
+    match serde_json::from_str::<Value>(text) {
+      Ok(Value::Object(_)) => return Self::JsonObject,
+      Ok(Value::Array(_)) => return Self::JsonArray,
+      _ => {}
+    }
+
+    if !text.contains('\n') {
+      return Self::SingleLine;
+    }
+
+    match serde_yaml::from_str::<serde_yaml::Value>(text) {
+      Ok(serde_yaml::Value::Mapping(_) | serde_yaml::Value::Sequence(_)) => Self::Yaml,
+      _ => Self::MultiLine,
+    }
+  }
+}
+
+impl Display for Structure {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{}",
+      match self {
+        Self::Empty => "empty",
+        Self::JsonArray => "json_array",
+        Self::JsonObject => "json_object",
+        Self::MultiLine => "multi_line",
+        Self::Pgp => "pgp",
+        Self::SingleLine => "single_line",
+        Self::Yaml => "yaml",
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detect() {
+    #[track_caller]
+    fn case(text: &str, structure: Structure) {
+      assert_eq!(Structure::detect(text), structure, "{text:?}");
+    }
+
+    case("", Structure::Empty);
+    case(" \n ", Structure::Empty);
+    case("foo", Structure::SingleLine);
+    case("foo: bar", Structure::SingleLine);
+    case("123", Structure::SingleLine);
+    case("foo\nbar", Structure::MultiLine);
+    case("{\"p\":\"brc-20\"}", Structure::JsonObject);
+    case(" [1,\n2]\n", Structure::JsonArray);
+    case("foo: bar\nbaz: 1\n", Structure::Yaml);
+    case("- foo\n- bar\n", Structure::Yaml);
+    case(
+      "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA256\n\nfoo\n",
+      Structure::Pgp,
+    );
+  }
+
+  #[test]
+  fn display() {
+    assert_eq!(Structure::JsonObject.to_string(), "json_object");
+    assert_eq!(Structure::SingleLine.to_string(), "single_line");
+  }
+}
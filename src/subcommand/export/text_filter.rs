@@ -0,0 +1,153 @@
+use {super::*, regex::RegexBuilder};
+
+#[derive(Debug, Default, Parser)]
+pub(crate) struct TextFilter {
+  #[clap(
+    long = "match",
+    value_name = "REGEX",
+    help = "Export only text inscriptions matching regular expression <REGEX> anywhere in their text."
+  )]
+  pub(crate) pattern: Option<Regex>,
+  #[clap(
+    long,
+    requires = "pattern",
+    help = "Match `--match` <REGEX> case-insensitively."
+  )]
+  pub(crate) ignore_case: bool,
+  #[clap(
+    long,
+    help = "Export only text inscriptions at least <MIN_LENGTH> characters long."
+  )]
+  pub(crate) min_length: Option<usize>,
+  #[clap(
+    long,
+    help = "Export only text inscriptions at most <MAX_LENGTH> characters long."
+  )]
+  pub(crate) max_length: Option<usize>,
+}
+
+impl TextFilter {
+  /// Checks the length bounds and applies `--ignore-case` to the pattern,
+  /// which clap parses before it knows whether the flag was passed.
+  pub(crate) fn check(&mut self) -> Result {
+    if self.min_length > self.max_length && self.max_length.is_some() {
+      bail!("`--min-length` must not be greater than `--max-length`");
+    }
+
+    if self.ignore_case {
+      if let Some(pattern) = &self.pattern {
+        self.pattern = Some(
+          RegexBuilder::new(pattern.as_str())
+            .case_insensitive(true)
+            .build()?,
+        );
+      }
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn is_active(&self) -> bool {
+    self.pattern.is_some() || self.min_length.is_some() || self.max_length.is_some()
+  }
+
+  /// Text is matched after decoding, with invalid UTF-8 replaced by U+FFFD.
+  /// Non-text inscriptions have no text, so match no active filter.
+  pub(crate) fn matches(&self, inscription: &Inscription) -> bool {
+    if !self.is_active() {
+      return true;
+    }
+
+    if inscription.media() != Media::Text {
+      return false;
+    }
+
+    let text = String::from_utf8_lossy(inscription.body().unwrap_or_default());
+
+    let length = text.chars().count();
+
+    self.min_length.map_or(true, |min| length >= min)
+      && self.max_length.map_or(true, |max| length <= max)
+      && self
+        .pattern
+        .as_ref()
+        .map_or(true, |pattern| pattern.is_match(&text))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn filter(args: &[&str]) -> TextFilter {
+    let mut filter =
+      TextFilter::try_parse_from(std::iter::once("filter").chain(args.iter().copied())).unwrap();
+    filter.check().unwrap();
+    filter
+  }
+
+  fn text(body: &str) -> Inscription {
+    inscription("text/plain;charset=utf-8", body)
+  }
+
+  #[test]
+  fn inactive_filter_matches_everything() {
+    let filter = filter(&[]);
+    assert!(!filter.is_active());
+    assert!(filter.matches(&text("foo")));
+    assert!(filter.matches(&inscription("image/png", [1; 100])));
+  }
+
+  #[test]
+  fn active_filter_excludes_non_text() {
+    assert!(!filter(&["--min-length", "0"]).matches(&inscription("image/png", [1; 100])));
+  }
+
+  #[test]
+  fn pattern_matches_anywhere() {
+    let filter = filter(&["--match", r"ordinals\.com"]);
+    assert!(filter.matches(&text("see https://ordinals.com/")));
+    assert!(!filter.matches(&text("see https://ordinalsXcom/")));
+    assert!(!filter.matches(&text("ORDINALS.COM")));
+  }
+
+  #[test]
+  fn ignore_case() {
+    let filter = filter(&["--match", "^foo$", "--ignore-case"]);
+    assert!(filter.matches(&text("FOO")));
+    assert!(filter.matches(&text("foo")));
+    assert!(!filter.matches(&text("foobar")));
+    assert_eq!(filter.pattern.unwrap().as_str(), "^foo$");
+  }
+
+  #[test]
+  fn ignore_case_requires_match() {
+    assert!(TextFilter::try_parse_from(["filter", "--ignore-case"]).is_err());
+  }
+
+  #[test]
+  fn invalid_pattern_is_rejected() {
+    assert!(TextFilter::try_parse_from(["filter", "--match", "("]).is_err());
+  }
+
+  #[test]
+  fn lengths_are_inclusive_and_count_characters() {
+    let filter = filter(&["--min-length", "2", "--max-length", "3"]);
+    assert!(!filter.matches(&text("a")));
+    assert!(filter.matches(&text("ab")));
+    assert!(filter.matches(&text("äöü")));
+    assert!(!filter.matches(&text("abcd")));
+  }
+
+  #[test]
+  fn min_length_must_not_exceed_max_length() {
+    assert_eq!(
+      TextFilter::try_parse_from(["filter", "--min-length", "2", "--max-length", "1"])
+        .unwrap()
+        .check()
+        .unwrap_err()
+        .to_string(),
+      "`--min-length` must not be greater than `--max-length`"
+    );
+  }
+}
@@ -0,0 +1,625 @@
+use {
+  super::*,
+  bitcoin::hashes::sha256,
+  serde_json::Value,
+  std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+  },
+};
+
+#[derive(Debug, Parser)]
+pub(crate) struct Verify {
+  #[clap(help = "Verify export <FILE>.")]
+  file: PathBuf,
+  #[clap(
+    long,
+    arg_enum,
+    help = "Read records in <FORMAT>. [default: inferred from the <FILE> extension]"
+  )]
+  format: Option<Format>,
+  #[clap(
+    long,
+    arg_enum,
+    help = "Decompress <FILE> with <COMPRESS>. [default: inferred from a `.gz` or `.zst` <FILE> extension]"
+  )]
+  compress: Option<Compression>,
+  #[clap(
+    long,
+    arg_enum,
+    help = "Recompute digests over <DIGEST_INPUT>. [default: as recorded in the manifest, or `body`]"
+  )]
+  digest_input: Option<DigestInput>,
+  #[clap(
+    long,
+    help = "Check <FILE> against the chain, tip, filters, file digests, row count and Merkle root recorded in <MANIFEST>. [default: <FILE>.manifest.json, if it exists]"
+  )]
+  manifest: Option<PathBuf>,
+  #[clap(
+    long,
+    help = "Read non-text bodies of an export written with `--bodies-dir` from <BODIES_DIR>."
+  )]
+  bodies_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Failure {
+  pub(crate) row: u64,
+  pub(crate) inscription: Option<InscriptionId>,
+  pub(crate) columns: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct Output {
+  pub(crate) rows: u64,
+  pub(crate) verified: u64,
+  pub(crate) missing: Vec<Failure>,
+  pub(crate) altered: Vec<Failure>,
+  pub(crate) reorged: Vec<Failure>,
+  /// Files, `rows` or `merkle_root` recorded in the manifest that don't match
+  /// the export.
+  pub(crate) manifest: Vec<String>,
+}
+
+/// Exported values by column name, with empty values as `None`.
+type Record = HashMap<String, Option<String>>;
+
+#[derive(Debug, PartialEq)]
+enum Status {
+  Altered(Vec<String>),
+  Missing,
+  Reorged(Vec<String>),
+  Verified,
+}
+
+impl Verify {
+  pub(crate) fn run(self, options: Options) -> Result {
+    let compression = self.compress.or_else(|| Compression::from_path(&self.file));
+
+    let Some(format) = self.format.or_else(|| Format::from_path(&self.file)) else {
+      bail!(
+        "cannot infer format of `{}`, use `--format`",
+        self.file.display()
+      );
+    };
+
+    let manifest_path = self
+      .manifest
+      .clone()
+      .or_else(|| Some(Manifest::default_path(&self.file)).filter(|path| path.exists()));
+
+    let manifest = manifest_path.as_deref().map(Manifest::load).transpose()?;
+
+    if let Some(manifest) = &manifest {
+      if manifest.chain != options.chain() {
+        bail!(
+          "export manifest is for {}, not {}",
+          manifest.chain,
+          options.chain()
+        );
+      }
+
+      if manifest.filters.transfers {
+        bail!("cannot verify a transfer ledger exported with `--transfers`");
+      }
+    }
+
+    // Rows are regenerated by an export with the options recorded in the
+    // manifest, counting duplicates no later than the tip it was written at.
+    let mut export = match &manifest {
+      Some(manifest) => {
+        let mut export = manifest.filters.export()?;
+
+        if let Some(height) = manifest.height {
+          export.bounds.to_height = Some(export.bounds.to_height.unwrap_or(height).min(height));
+        }
+
+        export
+      }
+      None => Export::try_parse_from(["export"])?,
+    };
+
+    if let Some(digest_input) = self.digest_input {
+      export.digest_input = digest_input;
+    }
+
+    export.bodies_dir = self.bodies_dir.clone();
+
+    let index = Index::open(&options)?;
+
+    Export::update(&index)?;
+
+    // Inscriptions missing from an export whose tip is no longer in the index
+    // were reorged out rather than never having existed.
+    let reorg = match &manifest {
+      Some(Manifest {
+        height: Some(height),
+        blockhash: Some(blockhash),
+        ..
+      }) => index.block_hash(Some(*height))? != Some(*blockhash),
+      _ => false,
+    };
+
+    let file = File::open(&self.file)
+      .with_context(|| format!("I/O error reading `{}`", self.file.display()))?;
+
+    let reader = Compression::decoder(compression, Box::new(BufReader::new(file)))?;
+
+    let mut records = Self::records(format, reader)?.peekable();
+
+    export.columns = match (&manifest, records.peek()) {
+      (Some(manifest), _) => manifest
+        .filters
+        .columns
+        .iter()
+        .map(|name| Self::column(name))
+        .collect::<Result<Vec<Column>>>()?,
+      (None, Some(Ok(record))) => {
+        let mut names = record.keys().collect::<Vec<&String>>();
+        names.sort();
+        names
+          .into_iter()
+          .map(|name| Self::column(name))
+          .collect::<Result<Vec<Column>>>()?
+      }
+      (None, _) => Vec::new(),
+    };
+
+    let mut duplicates = export.duplicates();
+
+    if export
+      .columns
+      .iter()
+      .any(|column| matches!(column, Column::DuplicateOf | Column::DuplicateCount))
+      && !export.census(
+        &index,
+        &mut duplicates,
+        *export.bounds.numbers().start(),
+        false,
+      )?
+    {
+      return Ok(());
+    }
+
+    let mut output = Output::default();
+
+    let mut merkle = Merkle::default();
+
+    for (row, record) in (1..).zip(records) {
+      let record = record
+        .with_context(|| format!("failed to parse row {row} of `{}`", self.file.display()))?;
+
+      let (inscription, status, expected) = Self::check(
+        &index,
+        &export,
+        &duplicates,
+        options.chain(),
+        reorg,
+        &record,
+      )?;
+
+      merkle.push(Self::leaf(&export.columns, &record, expected.as_deref())?);
+
+      output.rows += 1;
+
+      let failure = |columns| Failure {
+        row,
+        inscription,
+        columns,
+      };
+
+      match status {
+        Status::Altered(columns) => output.altered.push(failure(columns)),
+        Status::Missing => output.missing.push(failure(Vec::new())),
+        Status::Reorged(columns) => output.reorged.push(failure(columns)),
+        Status::Verified => output.verified += 1,
+      }
+    }
+
+    if let (Some(path), Some(manifest)) = (&manifest_path, &manifest) {
+      let dir = path.parent().unwrap_or(Path::new(""));
+
+      for file in &manifest.files {
+        if !matches!(Manifest::file(&dir.join(&file.path)), Ok(actual) if actual == *file) {
+          output.manifest.push(file.path.clone());
+        }
+      }
+
+      // Rows and the Merkle root cover every shard, so they can only be
+      // checked against an export written to a single file.
+      if manifest.files.len() <= 1 {
+        if output.rows != manifest.rows {
+          output.manifest.push("rows".into());
+        }
+
+        if merkle.root() != manifest.merkle_root {
+          output.manifest.push("merkle_root".into());
+        }
+      }
+    }
+
+    print_json(&output)?;
+
+    let failed = output.rows - output.verified;
+
+    if failed > 0 {
+      bail!(
+        "{failed} of {} rows failed verification: {} missing, {} altered, {} reorged",
+        output.rows,
+        output.missing.len(),
+        output.altered.len(),
+        output.reorged.len(),
+      );
+    }
+
+    if !output.manifest.is_empty() {
+      bail!(
+        "export does not match manifest: {}",
+        output.manifest.join(", ")
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Digest columns are named after their algorithm.
+  fn column(name: &str) -> Result<Column> {
+    match Algorithm::from_column(name) {
+      Some(algorithm) => Ok(Column::Digest(algorithm)),
+      None => name.parse(),
+    }
+  }
+
+  fn records(
+    format: Format,
+    reader: Box<dyn Read>,
+  ) -> Result<Box<dyn Iterator<Item = Result<Record>>>> {
+    Ok(match format {
+      Format::Csv => Box::new(
+        csv::Reader::from_reader(reader)
+          .into_deserialize::<HashMap<String, String>>()
+          .map(|record| {
+            Ok(
+              record?
+                .into_iter()
+                .map(|(column, value)| (column, Some(value).filter(|value| !value.is_empty())))
+                .collect(),
+            )
+          }),
+      ),
+      Format::Jsonl => Box::new(
+        BufReader::new(reader)
+          .lines()
+          .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+          .map(|line| Ok(Self::record(serde_json::from_str(&line?)?))),
+      ),
+      Format::Json => Box::new(
+        serde_json::from_reader::<_, Vec<HashMap<String, Value>>>(reader)?
+          .into_iter()
+          .map(|record| Ok(Self::record(record))),
+      ),
+    })
+  }
+
+  fn record(record: HashMap<String, Value>) -> Record {
+    record
+      .into_iter()
+      .map(|(column, value)| {
+        let value = match value {
+          Value::Null => None,
+          Value::String(value) => Some(value),
+          value => Some(value.to_string()),
+        };
+        (column, value)
+      })
+      .collect()
+  }
+
+  /// Rows are identified by their `id` column, or by the inscription ID at the
+  /// end of their `link` column.
+  fn inscription_id(record: &Record) -> Result<Option<InscriptionId>> {
+    let id = match (record.get("id"), record.get("link")) {
+      (Some(id), _) => id.as_deref(),
+      (None, Some(link)) => link.as_deref().and_then(|link| link.rsplit('/').next()),
+      (None, None) => bail!("export has neither an `id` nor a `link` column"),
+    };
+
+    Ok(id.and_then(|id| id.parse().ok()))
+  }
+
+  /// Compares every column of a row with the values the index exports for
+  /// its inscription, except `owner` and `satpoint`, which change whenever
+  /// it's transferred, and `link`, whose base isn't recorded. Digests are
+  /// also recomputed from the exported text or body. Returns the expected
+  /// values, if the inscription is indexed.
+  fn check(
+    index: &Index,
+    export: &Export,
+    duplicates: &Duplicates,
+    chain: Chain,
+    reorg: bool,
+    record: &Record,
+  ) -> Result<(Option<InscriptionId>, Status, Option<Vec<Value>>)> {
+    let Some(inscription_id) = Self::inscription_id(record)? else {
+      let column = if record.contains_key("id") {
+        "id"
+      } else {
+        "link"
+      };
+      return Ok((None, Status::Altered(vec![column.into()]), None));
+    };
+
+    let (Some(entry), Some(inscription)) = (
+      index.get_inscription_entry(inscription_id)?,
+      index.get_inscription_by_id(inscription_id)?,
+    ) else {
+      return Ok((
+        Some(inscription_id),
+        if reorg {
+          Status::Reorged(Vec::new())
+        } else {
+          Status::Missing
+        },
+        None,
+      ));
+    };
+
+    let expected = export.values(
+      index,
+      &export.columns,
+      duplicates.group(&duplicates.key(&inscription)),
+      inscription_id,
+      &entry,
+      &inscription,
+      &Link::new(None, LinkRoute::default(), chain),
+      chain,
+    )?;
+
+    let mut moved = Vec::new();
+    let mut altered = Vec::new();
+
+    for (column, value) in export.columns.iter().zip(&expected) {
+      let cell = Self::cell(record, column.name());
+
+      let matches = match column {
+        Column::Link => cell.map_or(false, |link| link.ends_with(&format!("/{inscription_id}"))),
+        Column::Owner | Column::Satpoint => true,
+        _ => cell.map(str::to_string) == Self::value(value),
+      };
+
+      if !matches {
+        if matches!(column, Column::Height | Column::Number) {
+          moved.push(column.name().to_string());
+        } else {
+          altered.push(column.name().to_string());
+        }
+      }
+    }
+
+    if !moved.is_empty() {
+      moved.sort();
+      return Ok((Some(inscription_id), Status::Reorged(moved), Some(expected)));
+    }
+
+    let media = inscription.media();
+    let body = inscription.body().unwrap_or_default();
+
+    if let Some((column, content)) = Self::content(record, export.bodies_dir.as_deref()) {
+      // Text that isn't UTF-8 is exported lossily, so its raw body can't be
+      // recovered from it.
+      let lossy = column == "text"
+        && export.digest_input == DigestInput::Body
+        && std::str::from_utf8(body).is_err();
+
+      let recomputed = match &content {
+        Some(content) if !lossy => export
+          .columns
+          .iter()
+          .filter_map(|column| {
+            match column {
+              Column::Hash => Some(Algorithm::Sha3_256),
+              Column::Digest(algorithm) => Some(*algorithm),
+              _ => None,
+            }
+            .map(|algorithm| (column.name(), algorithm))
+          })
+          .all(|(column, algorithm)| {
+            Self::cell(record, column)
+              == Some(
+                algorithm
+                  .digest(&export.digest_input.bytes(media, content))
+                  .as_str(),
+              )
+          }),
+        Some(_) => true,
+        None => false,
+      };
+
+      if !recomputed && !altered.iter().any(|altered| altered == column) {
+        altered.push(column.into());
+      }
+    }
+
+    if !altered.is_empty() {
+      altered.sort();
+      return Ok((
+        Some(inscription_id),
+        Status::Altered(altered),
+        Some(expected),
+      ));
+    }
+
+    Ok((Some(inscription_id), Status::Verified, Some(expected)))
+  }
+
+  fn cell<'a>(record: &'a Record, column: &str) -> Option<&'a str> {
+    record
+      .get(column)
+      .and_then(Option::as_deref)
+      .filter(|cell| !cell.is_empty())
+  }
+
+  /// Values as they're written to CSV.
+  fn value(value: &Value) -> Option<String> {
+    match value {
+      Value::Null => None,
+      Value::String(value) => Some(value.clone()).filter(|value| !value.is_empty()),
+      value => Some(value.to_string()),
+    }
+  }
+
+  /// The exported content of a row and the column it was read from: its
+  /// text, its base64-encoded body, or the file at its path in
+  /// `--bodies-dir`, or `None` if that can't be read.
+  fn content(
+    record: &Record,
+    bodies_dir: Option<&Path>,
+  ) -> Option<(&'static str, Option<Vec<u8>>)> {
+    if let Some(text) = Self::cell(record, "text") {
+      return Some(("text", Some(text.as_bytes().to_vec())));
+    }
+
+    if let Some(body) = Self::cell(record, "body") {
+      return Some(("body", base64::decode(body).ok()));
+    }
+
+    match (Self::cell(record, "path"), bodies_dir) {
+      (Some(path), Some(bodies_dir)) => Some(("path", fs::read(bodies_dir.join(path)).ok())),
+      _ => None,
+    }
+  }
+
+  /// CSV doesn't distinguish numbers and booleans from strings, or null from
+  /// empty strings, so cells take the type of the expected values, when the
+  /// inscription is indexed, to recompute the row's Merkle leaf.
+  fn leaf(columns: &[Column], record: &Record, expected: Option<&[Value]>) -> Result<sha256::Hash> {
+    let values = columns
+      .iter()
+      .enumerate()
+      .map(|(i, column)| {
+        let expected = expected.map(|expected| &expected[i]);
+
+        match (record.get(column.name()).cloned().flatten(), expected) {
+          (None, Some(Value::String(value))) if value.is_empty() => Value::String(String::new()),
+          (None, _) => Value::Null,
+          (Some(cell), Some(Value::Number(_) | Value::Bool(_))) => {
+            serde_json::from_str(&cell).unwrap_or(Value::String(cell))
+          }
+          (Some(cell), _) => Value::String(cell),
+        }
+      })
+      .collect();
+
+    Manifest::leaf(&Row { columns, values })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn records(format: Format, data: &'static str) -> Vec<Record> {
+    Verify::records(format, Box::new(data.as_bytes()))
+      .unwrap()
+      .collect::<Result<Vec<Record>>>()
+      .unwrap()
+  }
+
+  fn record(fields: &[(&str, Option<&str>)]) -> Record {
+    fields
+      .iter()
+      .map(|(column, value)| (column.to_string(), value.map(str::to_string)))
+      .collect()
+  }
+
+  #[test]
+  fn reads_every_format() {
+    let expected = vec![
+      record(&[("number", Some("0")), ("text", Some("foo"))]),
+      record(&[("number", Some("1")), ("text", None)]),
+    ];
+
+    assert_eq!(records(Format::Csv, "number,text\n0,foo\n1,\n"), expected);
+    assert_eq!(
+      records(
+        Format::Jsonl,
+        "{\"number\":0,\"text\":\"foo\"}\n{\"number\":1,\"text\":null}\n"
+      ),
+      expected
+    );
+    assert_eq!(
+      records(
+        Format::Json,
+        "[\n{\"number\":0,\"text\":\"foo\"},\n{\"number\":1,\"text\":null}\n]\n"
+      ),
+      expected
+    );
+  }
+
+  #[test]
+  fn leaf_types_cells_like_expected_values() {
+    let columns = [Column::Number, Column::Text, Column::Sat, Column::Utf8];
+    let values = vec![0.into(), "".into(), Value::Null, true.into()];
+
+    assert_eq!(
+      Verify::leaf(
+        &columns,
+        &record(&[
+          ("number", Some("0")),
+          ("text", None),
+          ("sat", None),
+          ("utf8", Some("true"))
+        ]),
+        Some(&values),
+      )
+      .unwrap(),
+      Manifest::leaf(&Row {
+        columns: &columns,
+        values: values.clone(),
+      })
+      .unwrap()
+    );
+
+    assert_ne!(
+      Verify::leaf(
+        &columns,
+        &record(&[("number", Some("0")), ("utf8", Some("true"))]),
+        None,
+      )
+      .unwrap(),
+      Manifest::leaf(&Row {
+        columns: &columns,
+        values,
+      })
+      .unwrap()
+    );
+  }
+
+  #[test]
+  fn inscription_id_from_id_or_link() {
+    let id = inscription_id(1);
+
+    assert_eq!(
+      Verify::inscription_id(&record(&[("id", Some(&id.to_string()))])).unwrap(),
+      Some(id)
+    );
+    assert_eq!(
+      Verify::inscription_id(&record(&[(
+        "link",
+        Some(&format!("https://ordinals.com/inscription/{id}"))
+      )]))
+      .unwrap(),
+      Some(id)
+    );
+    assert_eq!(
+      Verify::inscription_id(&record(&[("id", Some("foo"))])).unwrap(),
+      None
+    );
+    assert_eq!(
+      Verify::inscription_id(&record(&[("text", Some("foo"))]))
+        .unwrap_err()
+        .to_string(),
+      "export has neither an `id` nor a `link` column"
+    );
+  }
+}
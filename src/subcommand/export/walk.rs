@@ -0,0 +1,115 @@
+use {super::*, std::ops::RangeInclusive};
+
+pub(crate) struct Page {
+  pub(crate) inscriptions: Vec<(InscriptionId, InscriptionEntry)>,
+  pub(crate) last: u64,
+  pub(crate) visited: u64,
+}
+
+/// Pages through `INSCRIPTION_NUMBER_TO_INSCRIPTION_ID` in `order`, yielding
+/// the inscriptions that fall within `bounds`.
+pub(crate) struct Walk<'a> {
+  bounds: &'a Bounds,
+  cursor: Option<u64>,
+  index: &'a Index,
+  numbers: RangeInclusive<u64>,
+  order: Order,
+}
+
+impl<'a> Walk<'a> {
+  const PAGE_SIZE: usize = 1000;
+
+  pub(crate) fn new(index: &'a Index, bounds: &'a Bounds, order: Order, from: u64) -> Self {
+    let numbers = from..=*bounds.numbers().end();
+
+    Self {
+      bounds,
+      cursor: Some(match order {
+        Order::Ascending => *numbers.start(),
+        Order::Descending => *numbers.end(),
+      }),
+      index,
+      numbers,
+      order,
+    }
+  }
+
+  pub(crate) fn len(&self) -> Result<u64> {
+    Ok(
+      self
+        .index
+        .get_feed_inscriptions(1)?
+        .first()
+        .map(|(latest, _)| {
+          (*latest.min(self.numbers.end()) + 1).saturating_sub(*self.numbers.start())
+        })
+        .unwrap_or(0),
+    )
+  }
+
+  fn next_page(&mut self) -> Result<Option<Page>> {
+    let Some(cursor) = self.cursor else {
+      return Ok(None);
+    };
+
+    let numbers = match self.order {
+      Order::Ascending => self
+        .index
+        .get_inscriptions_from_number(cursor, Self::PAGE_SIZE)?,
+      Order::Descending => self
+        .index
+        .get_inscriptions_to_number(cursor, Self::PAGE_SIZE)?,
+    };
+
+    let mut page = Page {
+      inscriptions: Vec::new(),
+      last: cursor,
+      visited: 0,
+    };
+
+    let mut exhausted = numbers.is_empty();
+
+    for (number, inscription_id) in numbers {
+      if !self.numbers.contains(&number) {
+        exhausted = true;
+        break;
+      }
+
+      let entry = self
+        .index
+        .get_inscription_entry(inscription_id)?
+        .ok_or_else(|| anyhow!("inscription {inscription_id} has no index entry"))?;
+
+      if self.bounds.exhausted(self.order, entry.height) {
+        exhausted = true;
+        break;
+      }
+
+      page.last = number;
+      page.visited += 1;
+
+      if self.bounds.contains(&entry) {
+        page.inscriptions.push((inscription_id, entry));
+      }
+    }
+
+    self.cursor = if exhausted {
+      None
+    } else {
+      match self.order {
+        Order::Ascending => page.last.checked_add(1),
+        Order::Descending => page.last.checked_sub(1),
+      }
+    };
+
+    Ok((page.visited > 0).then_some(page))
+  }
+}
+
+impl<'a> Iterator for Walk<'a> {
+  type Item = Result<Page>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.next_page().transpose()
+  }
+}
@@ -0,0 +1,44 @@
+use {super::*, bitcoin::hashes::sha256};
+
+#[derive(Debug, Parser)]
+pub(crate) struct FindContent {
+  #[clap(help = "Find inscriptions whose content matches <FILE>.")]
+  file: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Match {
+  pub inscription: InscriptionId,
+  pub number: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub content_hash: sha256::Hash,
+  pub inscriptions: Vec<Match>,
+}
+
+impl FindContent {
+  pub(crate) fn run(self, options: Options) -> Result {
+    let content = fs::read(&self.file)
+      .with_context(|| format!("I/O error reading `{}`", self.file.display()))?;
+
+    let content_hash = sha256::Hash::hash(&content);
+
+    let index = Index::open(&options)?;
+
+    index.update()?;
+
+    print_json(Output {
+      content_hash,
+      inscriptions: index
+        .get_inscriptions_by_content_hash(content_hash)?
+        .into_iter()
+        .map(|(number, inscription)| Match {
+          inscription,
+          number,
+        })
+        .collect(),
+    })
+  }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+#[derive(Debug, Parser)]
+pub(crate) struct SearchText {
+  #[clap(
+    required = true,
+    help = "Find text inscriptions containing any of the words in <QUERY>."
+  )]
+  query: Vec<String>,
+  #[clap(long, default_value = "0", help = "Show page <PAGE> of results.")]
+  page: usize,
+  #[clap(long, default_value = "100", help = "Show <LIMIT> results per page.")]
+  limit: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Match {
+  pub inscription: InscriptionId,
+  pub number: u64,
+  pub score: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+  pub matches: Vec<Match>,
+  pub page: usize,
+  pub more: bool,
+  pub total: usize,
+}
+
+impl SearchText {
+  pub(crate) fn run(self, options: Options) -> Result {
+    if self.limit == 0 {
+      bail!("`--limit` must be greater than zero");
+    }
+
+    let index = Index::open(&options)?;
+
+    index.update()?;
+
+    let search = index.search_text(&self.query.join(" "), self.page, self.limit)?;
+
+    print_json(Output {
+      more: (self.page + 1).saturating_mul(self.limit) < search.total,
+      matches: search
+        .matches
+        .into_iter()
+        .map(|text_match| Match {
+          inscription: text_match.inscription_id,
+          number: text_match.number,
+          score: text_match.score,
+        })
+        .collect(),
+      page: self.page,
+      total: search.total,
+    })
+  }
+}
@@ -1,17 +1,19 @@
 use {
   self::{
+    body_writer::BodyWriter,
     deserialize_from_str::DeserializeFromStr,
     error::{OptionExt, ServerError, ServerResult},
   },
-  super::*,
+  super::{export::Export, *},
   crate::page_config::PageConfig,
   crate::templates::{
-    BlockHtml, ClockSvg, HomeHtml, InputHtml, InscriptionHtml, InscriptionsHtml, OutputHtml,
-    PageContent, PageHtml, PreviewAudioHtml, PreviewImageHtml, PreviewPdfHtml, PreviewTextHtml,
-    PreviewUnknownHtml, PreviewVideoHtml, RangeHtml, RareTxt, SatHtml, TransactionHtml,
+    AddressHtml, BlockHtml, ClockSvg, ContentHashHtml, HomeHtml, InputHtml, InscriptionHtml,
+    InscriptionsHtml, OutputHtml, PageContent, PageHtml, PreviewAudioHtml, PreviewImageHtml,
+    PreviewPdfHtml, PreviewTextHtml, PreviewUnknownHtml, PreviewVideoHtml, RangeHtml, RareTxt,
+    SatHtml, TextSearchHtml, TransactionHtml,
   },
   axum::{
-    body,
+    body::{self, StreamBody},
     extract::{Extension, Path, Query},
     headers::UserAgent,
     http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
@@ -20,6 +22,7 @@ use {
     Router, TypedHeader,
   },
   axum_server::Handle,
+  bitcoin::hashes::sha256,
   rust_embed::RustEmbed,
   rustls_acme::{
     acme::{LETS_ENCRYPT_PRODUCTION_DIRECTORY, LETS_ENCRYPT_STAGING_DIRECTORY},
@@ -27,7 +30,8 @@ use {
     caches::DirCache,
     AcmeConfig,
   },
-  std::{cmp::Ordering, str},
+  std::{cmp::Ordering, num::NonZeroUsize, str},
+  tokio::sync::Semaphore,
   tokio_stream::StreamExt,
   tower_http::{
     compression::CompressionLayer,
@@ -36,6 +40,7 @@ use {
   },
 };
 
+mod body_writer;
 mod error;
 
 enum BlockQuery {
@@ -66,6 +71,15 @@ struct Search {
   query: String,
 }
 
+/// `/search?query=` resolves sats, blocks, transactions, outputs and
+/// inscriptions, while `/search?q=` searches the text of inscriptions.
+#[derive(Deserialize)]
+struct SearchQuery {
+  query: Option<String>,
+  q: Option<String>,
+  page: Option<usize>,
+}
+
 #[derive(RustEmbed)]
 #[folder = "static"]
 struct StaticAssets;
@@ -121,6 +135,11 @@ pub(crate) struct Server {
   https: bool,
   #[clap(long, help = "Redirect HTTP traffic to HTTPS.")]
   redirect_http_to_https: bool,
+  #[clap(
+    long,
+    help = "Stream at most <MAX_EXPORTS> `/export` responses at once, each fetching inscriptions on one worker thread. [default: available parallelism]"
+  )]
+  max_exports: Option<NonZeroUsize>,
 }
 
 impl Server {
@@ -144,11 +163,14 @@ impl Server {
 
       let router = Router::new()
         .route("/", get(Self::home))
+        .route("/address/:address", get(Self::address))
         .route("/block-count", get(Self::block_count))
         .route("/block/:query", get(Self::block))
         .route("/bounties", get(Self::bounties))
         .route("/clock", get(Self::clock))
         .route("/content/:inscription_id", get(Self::content))
+        .route("/content-hash/:content_hash", get(Self::content_hash))
+        .route("/export/:file", get(Self::export))
         .route("/faq", get(Self::faq))
         .route("/favicon.ico", get(Self::favicon))
         .route("/feed.xml", get(Self::feed))
@@ -171,6 +193,7 @@ impl Server {
         .layer(Extension(index))
         .layer(Extension(page_config))
         .layer(Extension(Arc::new(config)))
+        .layer(Extension(Arc::new(Semaphore::new(self.max_exports()))))
         .layer(SetResponseHeaderLayer::if_not_present(
           header::CONTENT_SECURITY_POLICY,
           HeaderValue::from_static("default-src 'self'"),
@@ -293,6 +316,14 @@ impl Server {
     Ok(acme_cache)
   }
 
+  fn max_exports(&self) -> usize {
+    self
+      .max_exports
+      .or_else(|| thread::available_parallelism().ok())
+      .map(NonZeroUsize::get)
+      .unwrap_or(1)
+  }
+
   fn acme_domains(&self) -> Result<Vec<String>> {
     if !self.acme_domain.is_empty() {
       Ok(self.acme_domain.clone())
@@ -438,6 +469,55 @@ impl Server {
     )
   }
 
+  async fn address(
+    Extension(page_config): Extension<Arc<PageConfig>>,
+    Extension(index): Extension<Arc<Index>>,
+    Path(DeserializeFromStr(address)): Path<DeserializeFromStr<Address>>,
+  ) -> ServerResult<PageHtml<AddressHtml>> {
+    if !index.has_address_index()? {
+      return Err(ServerError::NotFound(
+        "address lookup requires index created with `--index-addresses` flag".into(),
+      ));
+    }
+
+    if !address.is_valid_for_network(page_config.chain.network()) {
+      return Err(ServerError::BadRequest(format!(
+        "address {address} is not valid for {}",
+        page_config.chain
+      )));
+    }
+
+    let outputs = index.get_outputs_by_script_pubkey(&address.script_pubkey())?;
+
+    let mut inscriptions = Vec::new();
+    for (outpoint, _value) in &outputs {
+      inscriptions.extend(index.get_inscriptions_on_output(*outpoint)?);
+    }
+
+    Ok(
+      AddressHtml {
+        address,
+        outputs,
+        inscriptions,
+      }
+      .page(page_config, index.has_sat_index()?),
+    )
+  }
+
+  async fn content_hash(
+    Extension(page_config): Extension<Arc<PageConfig>>,
+    Extension(index): Extension<Arc<Index>>,
+    Path(DeserializeFromStr(content_hash)): Path<DeserializeFromStr<sha256::Hash>>,
+  ) -> ServerResult<PageHtml<ContentHashHtml>> {
+    Ok(
+      ContentHashHtml {
+        content_hash,
+        inscriptions: index.get_inscriptions_by_content_hash(content_hash)?,
+      }
+      .page(page_config, index.has_sat_index()?),
+    )
+  }
+
   async fn range(
     Extension(page_config): Extension<Arc<PageConfig>>,
     Extension(index): Extension<Arc<Index>>,
@@ -546,10 +626,51 @@ impl Server {
   }
 
   async fn search_by_query(
+    Extension(page_config): Extension<Arc<PageConfig>>,
     Extension(index): Extension<Arc<Index>>,
-    Query(search): Query<Search>,
-  ) -> ServerResult<Redirect> {
-    Self::search(&index, &search.query).await
+    Query(search): Query<SearchQuery>,
+  ) -> ServerResult<Response> {
+    match (search.q, search.query) {
+      (Some(q), _) => {
+        Ok(Self::search_text(page_config, &index, q, search.page.unwrap_or(0))?.into_response())
+      }
+      (None, Some(query)) => Ok(Self::search(&index, &query).await?.into_response()),
+      (None, None) => Err(ServerError::BadRequest(
+        "missing `query` or `q` parameter".into(),
+      )),
+    }
+  }
+
+  fn search_text(
+    page_config: Arc<PageConfig>,
+    index: &Index,
+    query: String,
+    page: usize,
+  ) -> ServerResult<PageHtml<TextSearchHtml>> {
+    const PAGE_SIZE: usize = 100;
+
+    if !index.has_text_index()? {
+      return Err(ServerError::NotFound(
+        "full-text search requires index created with `--index-text` flag".into(),
+      ));
+    }
+
+    let search = index.search_text(&query, page, PAGE_SIZE)?;
+
+    Ok(
+      TextSearchHtml {
+        query,
+        inscriptions: search
+          .matches
+          .into_iter()
+          .map(|text_match| text_match.inscription_id)
+          .collect(),
+        total: search.total,
+        prev: page.checked_sub(1),
+        next: ((page + 1).saturating_mul(PAGE_SIZE) < search.total).then_some(page + 1),
+      }
+      .page(page_config, index.has_sat_index()?),
+    )
   }
 
   async fn search_by_path(
@@ -582,6 +703,8 @@ impl Server {
       Ok(Redirect::to(&format!("/output/{query}")))
     } else if INSCRIPTION_ID.is_match(query) {
       Ok(Redirect::to(&format!("/inscription/{query}")))
+    } else if query.parse::<Address>().is_ok() {
+      Ok(Redirect::to(&format!("/address/{query}")))
     } else {
       Ok(Redirect::to(&format!("/sat/{query}")))
     }
@@ -704,6 +827,47 @@ impl Server {
     Ok(InputHtml { path, input }.page(page_config, index.has_sat_index()?))
   }
 
+  async fn export(
+    Extension(page_config): Extension<Arc<PageConfig>>,
+    Extension(index): Extension<Arc<Index>>,
+    Extension(exports): Extension<Arc<Semaphore>>,
+    Path(file): Path<String>,
+    Query(query): Query<BTreeMap<String, String>>,
+  ) -> ServerResult<Response> {
+    let export = Export::from_route(&file, &query)
+      .map_err(|err| ServerError::BadRequest(err.to_string()))?
+      .ok_or_not_found(|| format!("export {file}"))?;
+
+    let permit = exports.try_acquire_owned().map_err(|_| {
+      ServerError::ServiceUnavailable("too many exports in progress, try again later".into())
+    })?;
+
+    let content_type = export.content_type();
+
+    let (writer, body) = BodyWriter::new();
+
+    let abort = writer.clone();
+
+    task::spawn_blocking(move || {
+      let _permit = permit;
+
+      let writer = Box::new(io::BufWriter::new(writer));
+
+      if let Err(err) = export.stream(&index, page_config.chain, writer) {
+        log::warn!("error streaming export {file}: {err}");
+        abort.abort(err);
+      }
+    });
+
+    Ok(
+      (
+        [(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+        StreamBody::new(body),
+      )
+        .into_response(),
+    )
+  }
+
   async fn faq() -> Redirect {
     Redirect::to("https://docs.ordinals.com/faq/")
   }
@@ -849,9 +1013,18 @@ impl Server {
 
     let next = index.get_inscription_id_by_inscription_number(entry.number + 1)?;
 
+    let content_hash = inscription.content_hash();
+
+    let duplicates = match content_hash {
+      Some(content_hash) => index.get_inscriptions_by_content_hash(content_hash)?,
+      None => Vec::new(),
+    };
+
     Ok(
       InscriptionHtml {
         chain: page_config.chain,
+        content_hash,
+        duplicates,
         genesis_fee: entry.fee,
         genesis_height: entry.height,
         inscription,
@@ -863,6 +1036,7 @@ impl Server {
         sat: entry.sat,
         satpoint,
         timestamp: timestamp(entry.timestamp),
+        transfers: index.get_transfers(inscription_id)?,
       }
       .page(page_config, index.has_sat_index()?),
     )
@@ -1311,6 +1485,53 @@ mod tests {
     );
   }
 
+  #[test]
+  fn search_by_text_returns_matching_inscriptions() {
+    let server = TestServer::new_with_args(&["--index-text"], &[]);
+    server.mine_blocks(1);
+
+    let txid = server.bitcoin_rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0)],
+      witness: inscription("text/plain;charset=utf-8", "hello world").to_witness(),
+      ..Default::default()
+    });
+
+    server.mine_blocks(1);
+
+    server.assert_response_regex(
+      "/search?q=Hello+there",
+      StatusCode::OK,
+      format!(
+        ".*<title>Search: Hello there</title>.*<p>1 matching text inscription</p>.*<a href=/inscription/{}>.*",
+        InscriptionId::from(txid)
+      ),
+    );
+
+    server.assert_response_regex(
+      "/search?q=goodbye",
+      StatusCode::OK,
+      ".*<p>0 matching text inscriptions</p>.*",
+    );
+  }
+
+  #[test]
+  fn search_by_text_requires_text_index() {
+    TestServer::new().assert_response(
+      "/search?q=hello",
+      StatusCode::NOT_FOUND,
+      "full-text search requires index created with `--index-text` flag",
+    );
+  }
+
+  #[test]
+  fn search_requires_query() {
+    TestServer::new().assert_response(
+      "/search",
+      StatusCode::BAD_REQUEST,
+      "missing `query` or `q` parameter",
+    );
+  }
+
   #[test]
   fn search_is_whitespace_insensitive() {
     TestServer::new().assert_redirect("/search/ 0 ", "/sat/0");
@@ -1353,6 +1574,44 @@ mod tests {
     );
   }
 
+  #[test]
+  fn search_for_address_returns_address() {
+    TestServer::new().assert_redirect(
+      "/search/bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+      "/address/bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+    );
+  }
+
+  #[test]
+  fn address_page_lists_outputs() {
+    let server = TestServer::new_with_args(&["--index-addresses"], &[]);
+    server.mine_blocks(1);
+
+    server.assert_response_regex(
+      "/address/bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw",
+      StatusCode::OK,
+      ".*<title>Address bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw</title>.*<h2>0 Outputs</h2>.*",
+    );
+  }
+
+  #[test]
+  fn address_page_requires_address_index() {
+    TestServer::new().assert_response(
+      "/address/bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+      StatusCode::NOT_FOUND,
+      "address lookup requires index created with `--index-addresses` flag",
+    );
+  }
+
+  #[test]
+  fn address_page_rejects_address_for_other_chain() {
+    TestServer::new_with_args(&["--index-addresses"], &[]).assert_response(
+      "/address/bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+      StatusCode::BAD_REQUEST,
+      "address bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4 is not valid for regtest",
+    );
+  }
+
   #[test]
   fn http_to_https_redirect_with_path() {
     TestServer::new_with_args(&[], &["--redirect-http-to-https", "--https"]).assert_redirect(
@@ -1746,7 +2005,7 @@ mod tests {
   }
 
   #[test]
-  fn detect_reorg() {
+  fn shallow_reorg_is_rolled_back() {
     let test_server = TestServer::new();
 
     test_server.mine_blocks(1);
@@ -1756,6 +2015,24 @@ mod tests {
     test_server.bitcoin_rpc_server.invalidate_tip();
     test_server.bitcoin_rpc_server.mine_blocks(2);
 
+    test_server.assert_response("/status", StatusCode::OK, "OK");
+    test_server.assert_response("/block-count", StatusCode::OK, "3");
+  }
+
+  #[test]
+  fn deep_reorg_is_detected() {
+    let test_server = TestServer::new();
+
+    test_server.mine_blocks(10);
+
+    test_server.assert_response("/status", StatusCode::OK, "OK");
+
+    for _ in 0..7 {
+      test_server.bitcoin_rpc_server.invalidate_tip();
+    }
+
+    test_server.bitcoin_rpc_server.mine_blocks(8);
+
     test_server.assert_response_regex("/status", StatusCode::OK, "reorg detected.*");
   }
 
@@ -2284,6 +2561,69 @@ mod tests {
     );
   }
 
+  #[test]
+  fn inscription_page_links_to_first_duplicate() {
+    let server = TestServer::new();
+    server.mine_blocks(2);
+
+    let first = server.bitcoin_rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0)],
+      witness: inscription("text/plain", "hello").to_witness(),
+      ..Default::default()
+    });
+
+    server.mine_blocks(1);
+
+    let second = server.bitcoin_rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(2, 0, 0)],
+      witness: inscription("text/foo", "hello").to_witness(),
+      ..Default::default()
+    });
+
+    server.mine_blocks(1);
+
+    server.assert_response_regex(
+      format!("/inscription/{}", InscriptionId::from(second)),
+      StatusCode::OK,
+      format!(
+        r".*<dt>content hash</dt>\s*<dd><a class=monospace href=/content-hash/2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824>.*<dt>duplicates</dt>\s*<dd>1 duplicate, first is <a href=/inscription/{}>#0</a></dd>.*",
+        InscriptionId::from(first)
+      ),
+    );
+  }
+
+  #[test]
+  fn content_hash_page_lists_inscriptions_with_content() {
+    let server = TestServer::new();
+    server.mine_blocks(1);
+
+    let txid = server.bitcoin_rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0)],
+      witness: inscription("text/plain", "hello").to_witness(),
+      ..Default::default()
+    });
+
+    server.mine_blocks(1);
+
+    server.assert_response_regex(
+      "/content-hash/2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+      StatusCode::OK,
+      format!(
+        ".*<title>Content Hash 2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824</title>.*<p>1 inscription, first is <a href=/inscription/{}>#0</a></p>.*",
+        InscriptionId::from(txid)
+      ),
+    );
+  }
+
+  #[test]
+  fn content_hash_page_rejects_invalid_hash() {
+    TestServer::new().assert_response_regex(
+      "/content-hash/foo",
+      StatusCode::BAD_REQUEST,
+      "Invalid URL: .*",
+    );
+  }
+
   #[test]
   fn inscription_with_unknown_type_and_no_body_has_unknown_preview() {
     let server = TestServer::new_with_sat_index();
@@ -2476,4 +2816,135 @@ mod tests {
       &fs::read_to_string("templates/preview-unknown.html").unwrap(),
     );
   }
+
+  #[test]
+  fn export_streams_rows() {
+    let server = TestServer::new();
+    server.mine_blocks(1);
+
+    let text = server.bitcoin_rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(1, 0, 0)],
+      witness: inscription("text/plain;charset=utf-8", "hello").to_witness(),
+      ..Default::default()
+    });
+
+    server.mine_blocks(1);
+
+    let image = server.bitcoin_rpc_server.broadcast_tx(TransactionTemplate {
+      inputs: &[(2, 0, 0)],
+      witness: inscription("image/png", [1; 4]).to_witness(),
+      ..Default::default()
+    });
+
+    server.mine_blocks(1);
+
+    let response = server.get("/export/text.csv?columns=number,id,text");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+      response.headers().get(header::CONTENT_TYPE).unwrap(),
+      "text/csv; charset=utf-8"
+    );
+    assert_eq!(
+      response.text().unwrap(),
+      format!("number,id,text\n0,{},hello\n", InscriptionId::from(text))
+    );
+
+    server.assert_response(
+      "/export/inscriptions.jsonl?columns=number,media&order=ascending",
+      StatusCode::OK,
+      "{\"number\":0,\"media\":\"text\"}\n{\"number\":1,\"media\":\"image\"}\n",
+    );
+
+    server.assert_response(
+      "/export/inscriptions.json?columns=id&from_height=3",
+      StatusCode::OK,
+      &format!("[\n{{\"id\":\"{}\"}}\n]\n", InscriptionId::from(image)),
+    );
+
+    server.assert_response(
+      "/export/inscriptions.csv?columns=number&media=text&match=^HELLO$&ignore_case",
+      StatusCode::OK,
+      "number\n0\n",
+    );
+
+    server.assert_response(
+      "/export/inscriptions.csv?columns=number&media=image",
+      StatusCode::OK,
+      "number\n1\n",
+    );
+
+    server.assert_response(
+      "/export/text.csv?columns=number&media=image",
+      StatusCode::OK,
+      "number\n1\n",
+    );
+  }
+
+  #[test]
+  fn export_is_refused_while_too_many_are_in_progress() {
+    let server = TestServer::new();
+
+    let response = Runtime::new()
+      .unwrap()
+      .block_on(Server::export(
+        Extension(Arc::new(PageConfig {
+          chain: Chain::Regtest,
+          domain: None,
+        })),
+        Extension(server.index.clone()),
+        Extension(Arc::new(Semaphore::new(0))),
+        Path("text.csv".into()),
+        Query(BTreeMap::new()),
+      ))
+      .into_response();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+  }
+
+  #[test]
+  fn export_rejects_bad_queries() {
+    let server = TestServer::new();
+
+    server.assert_response(
+      "/export/text.csv?output=foo.csv",
+      StatusCode::BAD_REQUEST,
+      "unknown query parameter `output`",
+    );
+
+    server.assert_response(
+      "/export/text.csv?from_height=2&to_height=1",
+      StatusCode::BAD_REQUEST,
+      "`--from-height` must not be greater than `--to-height`",
+    );
+
+    server.assert_response_regex(
+      "/export/text.csv?from_height=foo",
+      StatusCode::BAD_REQUEST,
+      ".*--from-height.*",
+    );
+
+    server.assert_response(
+      "/export/text.csv?columns=number,duplicate_count",
+      StatusCode::BAD_REQUEST,
+      "exports with duplicate columns, or that keep copies of duplicates not reached first, require `ord export`",
+    );
+
+    server.assert_response(
+      "/export/text.csv?order=descending",
+      StatusCode::BAD_REQUEST,
+      "exports with duplicate columns, or that keep copies of duplicates not reached first, require `ord export`",
+    );
+
+    server.assert_response(
+      "/export/blocks.csv",
+      StatusCode::NOT_FOUND,
+      "export blocks.csv not found",
+    );
+
+    server.assert_response(
+      "/export/text.csv.gz",
+      StatusCode::NOT_FOUND,
+      "export text.csv.gz not found",
+    );
+  }
 }
@@ -0,0 +1,80 @@
+use {
+  super::*,
+  axum::body::Bytes,
+  std::io::Write,
+  tokio::sync::mpsc::{self, Receiver, Sender},
+  tokio_stream::wrappers::ReceiverStream,
+};
+
+/// Writes a response body from a blocking thread. Each write becomes a chunk,
+/// and writes block while `CHUNKS` chunks wait to be sent to the client, so
+/// slow clients hold up the writer instead of buffering in memory. Writes fail
+/// once the client disconnects.
+#[derive(Clone)]
+pub(super) struct BodyWriter(Sender<io::Result<Bytes>>);
+
+impl BodyWriter {
+  const CHUNKS: usize = 16;
+
+  pub(super) fn new() -> (Self, ReceiverStream<io::Result<Bytes>>) {
+    let (sender, receiver): (_, Receiver<io::Result<Bytes>>) = mpsc::channel(Self::CHUNKS);
+    (Self(sender), ReceiverStream::new(receiver))
+  }
+
+  /// Aborts the response, so the client sees a truncated body instead of one
+  /// that looks complete.
+  pub(super) fn abort(self, err: Error) {
+    self
+      .0
+      .blocking_send(Err(io::Error::new(io::ErrorKind::Other, err.to_string())))
+      .ok();
+  }
+}
+
+impl Write for BodyWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self
+      .0
+      .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+      .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, tokio_stream::StreamExt};
+
+  #[test]
+  fn writes_become_chunks() {
+    let (mut writer, mut stream) = BodyWriter::new();
+
+    thread::spawn(move || {
+      writer.write_all(b"foo").unwrap();
+      writer.write_all(b"bar").unwrap();
+      writer.abort(anyhow!("baz"));
+    });
+
+    Runtime::new().unwrap().block_on(async {
+      assert_eq!(stream.next().await.unwrap().unwrap(), "foo");
+      assert_eq!(stream.next().await.unwrap().unwrap(), "bar");
+      assert_eq!(stream.next().await.unwrap().unwrap_err().to_string(), "baz");
+      assert!(stream.next().await.is_none());
+    });
+  }
+
+  #[test]
+  fn writes_fail_after_disconnect() {
+    let (mut writer, stream) = BodyWriter::new();
+    drop(stream);
+    assert_eq!(
+      writer.write(b"foo").unwrap_err().kind(),
+      io::ErrorKind::BrokenPipe
+    );
+  }
+}
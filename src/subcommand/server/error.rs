@@ -4,6 +4,7 @@ pub(super) enum ServerError {
   Internal(Error),
   BadRequest(String),
   NotFound(String),
+  ServiceUnavailable(String),
 }
 
 pub(super) type ServerResult<T> = Result<T, ServerError>;
@@ -23,6 +24,9 @@ impl IntoResponse for ServerError {
       }
       Self::NotFound(message) => (StatusCode::NOT_FOUND, message).into_response(),
       Self::BadRequest(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+      Self::ServiceUnavailable(message) => {
+        (StatusCode::SERVICE_UNAVAILABLE, message).into_response()
+      }
     }
   }
 }
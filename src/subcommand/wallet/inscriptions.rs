@@ -14,12 +14,7 @@ pub(crate) fn run(options: Options) -> Result {
   let inscriptions = index.get_inscriptions(None)?;
   let unspent_outputs = index.get_unspent_outputs(Wallet::load(&options)?)?;
 
-  let explorer = match options.chain() {
-    Chain::Mainnet => "https://ordinals.com/inscription/",
-    Chain::Regtest => "http://localhost/inscription/",
-    Chain::Signet => "https://signet.ordinals.com/inscription/",
-    Chain::Testnet => "https://testnet.ordinals.com/inscription/",
-  };
+  let explorer = options.chain().explorer();
 
   let mut output = Vec::new();
 
@@ -28,7 +23,7 @@ pub(crate) fn run(options: Options) -> Result {
       output.push(Output {
         location,
         inscription,
-        explorer: format!("{explorer}{inscription}"),
+        explorer: format!("{explorer}/inscription/{inscription}"),
       });
     }
   }
@@ -1,8 +1,10 @@
 use {super::*, boilerplate::Boilerplate};
 
 pub(crate) use {
+  address::AddressHtml,
   block::BlockHtml,
   clock::ClockSvg,
+  content_hash::ContentHashHtml,
   home::HomeHtml,
   iframe::Iframe,
   input::InputHtml,
@@ -17,11 +19,14 @@ pub(crate) use {
   range::RangeHtml,
   rare::RareTxt,
   sat::SatHtml,
+  text_search::TextSearchHtml,
   transaction::TransactionHtml,
 };
 
+mod address;
 mod block;
 mod clock;
+mod content_hash;
 mod home;
 mod iframe;
 mod input;
@@ -32,6 +37,7 @@ mod preview;
 mod range;
 mod rare;
 mod sat;
+mod text_search;
 mod transaction;
 
 #[derive(Boilerplate)]
@@ -0,0 +1,63 @@
+use super::*;
+
+#[derive(Boilerplate)]
+pub(crate) struct AddressHtml {
+  pub(crate) address: Address,
+  pub(crate) outputs: Vec<(OutPoint, u64)>,
+  pub(crate) inscriptions: Vec<InscriptionId>,
+}
+
+impl PageContent for AddressHtml {
+  fn title(&self) -> String {
+    format!("Address {}", self.address)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn without_outputs() {
+    assert_regex_match!(
+      AddressHtml {
+        address: address(),
+        outputs: Vec::new(),
+        inscriptions: Vec::new(),
+      },
+      "
+        <h1>Address <span class=monospace>bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4</span></h1>
+        <h2>0 Outputs</h2>
+        <ul class=monospace>
+        </ul>
+      "
+      .unindent()
+    );
+  }
+
+  #[test]
+  fn with_outputs_and_inscriptions() {
+    assert_regex_match!(
+      AddressHtml {
+        address: address(),
+        outputs: vec![(outpoint(1), 1), (outpoint(2), 2)],
+        inscriptions: vec![inscription_id(1)],
+      },
+      "
+        <h1>Address <span class=monospace>bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4</span></h1>
+        <dl>
+          <dt>inscriptions</dt>
+          <dd class=thumbnails>
+            <a href=/inscription/1{64}i1><iframe .* src=/preview/1{64}i1></iframe></a>
+          </dd>
+        </dl>
+        <h2>2 Outputs</h2>
+        <ul class=monospace>
+          <li><a href=/output/1{64}:1>1{64}:1</a> 1</li>
+          <li><a href=/output/2{64}:2>2{64}:2</a> 2</li>
+        </ul>
+      "
+      .unindent()
+    );
+  }
+}
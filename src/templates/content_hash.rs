@@ -0,0 +1,54 @@
+use {super::*, bitcoin::hashes::sha256};
+
+#[derive(Boilerplate)]
+pub(crate) struct ContentHashHtml {
+  pub(crate) content_hash: sha256::Hash,
+  pub(crate) inscriptions: Vec<(u64, InscriptionId)>,
+}
+
+impl PageContent for ContentHashHtml {
+  fn title(&self) -> String {
+    format!("Content Hash {}", self.content_hash)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn without_inscriptions() {
+    assert_regex_match!(
+      ContentHashHtml {
+        content_hash: sha256::Hash::hash(b"foo"),
+        inscriptions: Vec::new(),
+      },
+      "
+        <h1>Content Hash <span class=monospace>2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae</span></h1>
+        <p>0 inscriptions</p>
+        <div class=thumbnails>
+        </div>
+      "
+      .unindent()
+    );
+  }
+
+  #[test]
+  fn with_inscriptions() {
+    assert_regex_match!(
+      ContentHashHtml {
+        content_hash: sha256::Hash::hash(b"foo"),
+        inscriptions: vec![(0, inscription_id(1)), (5, inscription_id(2))],
+      },
+      "
+        <h1>Content Hash <span class=monospace>2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae</span></h1>
+        <p>2 inscriptions, first is <a href=/inscription/1{64}i1>#0</a></p>
+        <div class=thumbnails>
+          <a href=/inscription/1{64}i1><iframe .* src=/preview/1{64}i1></iframe></a>
+          <a href=/inscription/2{64}i2><iframe .* src=/preview/2{64}i2></iframe></a>
+        </div>
+      "
+      .unindent()
+    );
+  }
+}
@@ -1,8 +1,10 @@
-use super::*;
+use {super::*, bitcoin::hashes::sha256};
 
 #[derive(Boilerplate)]
 pub(crate) struct InscriptionHtml {
   pub(crate) chain: Chain,
+  pub(crate) content_hash: Option<sha256::Hash>,
+  pub(crate) duplicates: Vec<(u64, InscriptionId)>,
   pub(crate) genesis_fee: u64,
   pub(crate) genesis_height: u64,
   pub(crate) inscription: Inscription,
@@ -14,6 +16,7 @@ pub(crate) struct InscriptionHtml {
   pub(crate) sat: Option<Sat>,
   pub(crate) satpoint: SatPoint,
   pub(crate) timestamp: DateTime<Utc>,
+  pub(crate) transfers: Vec<TransferEntry>,
 }
 
 impl PageContent for InscriptionHtml {
@@ -35,6 +38,8 @@ mod tests {
     assert_regex_match!(
       InscriptionHtml {
         chain: Chain::Mainnet,
+        content_hash: None,
+        duplicates: Vec::new(),
         genesis_fee: 1,
         genesis_height: 0,
         inscription: inscription("text/plain;charset=utf-8", "HELLOWORLD"),
@@ -46,6 +51,7 @@ mod tests {
         sat: None,
         satpoint: satpoint(1, 0),
         timestamp: timestamp(0),
+        transfers: Vec::new(),
       },
       "
         <h1>Inscription 1</h1>
@@ -94,6 +100,8 @@ mod tests {
     assert_regex_match!(
       InscriptionHtml {
         chain: Chain::Mainnet,
+        content_hash: None,
+        duplicates: Vec::new(),
         genesis_fee: 1,
         genesis_height: 0,
         inscription: inscription("text/plain;charset=utf-8", "HELLOWORLD"),
@@ -105,6 +113,7 @@ mod tests {
         sat: Some(Sat(1)),
         satpoint: satpoint(1, 0),
         timestamp: timestamp(0),
+        transfers: Vec::new(),
       },
       "
         <h1>Inscription 1</h1>
@@ -126,6 +135,8 @@ mod tests {
     assert_regex_match!(
       InscriptionHtml {
         chain: Chain::Mainnet,
+        content_hash: None,
+        duplicates: Vec::new(),
         genesis_fee: 1,
         genesis_height: 0,
         inscription: inscription("text/plain;charset=utf-8", "HELLOWORLD"),
@@ -137,6 +148,7 @@ mod tests {
         sat: None,
         satpoint: satpoint(1, 0),
         timestamp: timestamp(0),
+        transfers: Vec::new(),
       },
       "
         <h1>Inscription 1</h1>
@@ -150,4 +162,138 @@ mod tests {
       .unindent()
     );
   }
+
+  #[test]
+  fn with_transfers() {
+    assert_regex_match!(
+      InscriptionHtml {
+        chain: Chain::Mainnet,
+        content_hash: None,
+        duplicates: Vec::new(),
+        genesis_fee: 1,
+        genesis_height: 0,
+        inscription: inscription("text/plain;charset=utf-8", "HELLOWORLD"),
+        inscription_id: inscription_id(1),
+        next: None,
+        number: 1,
+        output: tx_out(1, address()),
+        previous: None,
+        sat: None,
+        satpoint: satpoint(3, 0),
+        timestamp: timestamp(0),
+        transfers: vec![
+          TransferEntry {
+            height: 1,
+            inscription_id: inscription_id(1),
+            lost: false,
+            new_satpoint: satpoint(2, 0),
+            old_satpoint: satpoint(1, 0),
+            timestamp: 0,
+            txid: txid(2),
+          },
+          TransferEntry {
+            height: 2,
+            inscription_id: inscription_id(1),
+            lost: true,
+            new_satpoint: satpoint(3, 0),
+            old_satpoint: satpoint(2, 0),
+            timestamp: 0,
+            txid: txid(4),
+          },
+        ],
+      },
+      "
+        <h1>Inscription 1</h1>
+        .*
+          <dt>offset</dt>
+          <dd>0</dd>
+          <dt>transfers</dt>
+          <dd>
+            <ol>
+              <li>
+                <a href=/block/1>1</a>
+                <a class=monospace href=/tx/2{64}>2{64}</a>
+                <span class=monospace>1{64}:1:0</span> → <span class=monospace>2{64}:2:0</span>
+              </li>
+              <li>
+                <a href=/block/2>2</a>
+                <a class=monospace href=/tx/4{64}>4{64}</a>
+                <span class=monospace>2{64}:2:0</span> → <span class=monospace>3{64}:3:0</span>
+                lost to fees
+              </li>
+            </ol>
+          </dd>
+        </dl>
+      "
+      .unindent()
+    );
+  }
+
+  #[test]
+  fn with_duplicates() {
+    assert_regex_match!(
+      InscriptionHtml {
+        chain: Chain::Mainnet,
+        content_hash: Some(sha256::Hash::hash(b"HELLOWORLD")),
+        duplicates: vec![(0, inscription_id(2)), (1, inscription_id(1)), (2, inscription_id(3))],
+        genesis_fee: 1,
+        genesis_height: 0,
+        inscription: inscription("text/plain;charset=utf-8", "HELLOWORLD"),
+        inscription_id: inscription_id(1),
+        next: None,
+        number: 1,
+        output: tx_out(1, address()),
+        previous: None,
+        sat: None,
+        satpoint: satpoint(1, 0),
+        timestamp: timestamp(0),
+        transfers: Vec::new(),
+      },
+      "
+        <h1>Inscription 1</h1>
+        .*
+          <dt>content type</dt>
+          <dd>text/plain;charset=utf-8</dd>
+          <dt>content hash</dt>
+          <dd><a class=monospace href=/content-hash/0b21b7db59cd154904fac6336fa7d2be1bab38d632794f281549584068cdcb74>0b21b7db59cd154904fac6336fa7d2be1bab38d632794f281549584068cdcb74</a></dd>
+          <dt>duplicates</dt>
+          <dd>2 duplicates, first is <a href=/inscription/2{64}i2>#0</a></dd>
+          <dt>timestamp</dt>
+        .*
+      "
+      .unindent()
+    );
+  }
+
+  #[test]
+  fn with_content_hash_and_no_duplicates() {
+    assert_regex_match!(
+      InscriptionHtml {
+        chain: Chain::Mainnet,
+        content_hash: Some(sha256::Hash::hash(b"HELLOWORLD")),
+        duplicates: vec![(1, inscription_id(1))],
+        genesis_fee: 1,
+        genesis_height: 0,
+        inscription: inscription("text/plain;charset=utf-8", "HELLOWORLD"),
+        inscription_id: inscription_id(1),
+        next: None,
+        number: 1,
+        output: tx_out(1, address()),
+        previous: None,
+        sat: None,
+        satpoint: satpoint(1, 0),
+        timestamp: timestamp(0),
+        transfers: Vec::new(),
+      },
+      "
+        <h1>Inscription 1</h1>
+        .*
+          <dt>content hash</dt>
+          <dd><a class=monospace href=/content-hash/[[:xdigit:]]{64}>[[:xdigit:]]{64}</a></dd>
+          <dt>timestamp</dt>
+        .*
+      "
+      .unindent()
+    );
+  }
 }
@@ -0,0 +1,106 @@
+use super::*;
+
+#[derive(Boilerplate)]
+pub(crate) struct TextSearchHtml {
+  pub(crate) query: String,
+  pub(crate) inscriptions: Vec<InscriptionId>,
+  pub(crate) total: usize,
+  pub(crate) prev: Option<usize>,
+  pub(crate) next: Option<usize>,
+}
+
+impl TextSearchHtml {
+  fn href(&self, page: usize) -> String {
+    let mut href = "/search?q=".to_string();
+
+    for byte in self.query.bytes() {
+      match byte {
+        b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b'-' | b'.' | b'_' | b'~' => {
+          href.push(byte.into())
+        }
+        b' ' => href.push('+'),
+        _ => href.push_str(&format!("%{byte:02X}")),
+      }
+    }
+
+    href.push_str(&format!("&page={page}"));
+
+    href
+  }
+}
+
+impl PageContent for TextSearchHtml {
+  fn title(&self) -> String {
+    format!("Search: {}", self.query)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn without_prev_and_next() {
+    assert_regex_match!(
+      TextSearchHtml {
+        query: "foo".into(),
+        inscriptions: vec![inscription_id(1)],
+        total: 1,
+        prev: None,
+        next: None,
+      },
+      r#"
+        <h1>Search</h1>
+        <form action=/search method=get>
+          <input .* name=q spellcheck=false value="foo">
+          .*
+        </form>
+        <p>1 matching text inscription</p>
+        <div class=thumbnails>
+          <a href=/inscription/1{64}i1><iframe .* src=/preview/1{64}i1></iframe></a>
+        </div>
+        .*
+        prev
+        next
+        .*
+      "#
+      .unindent()
+    );
+  }
+
+  #[test]
+  fn with_prev_and_next() {
+    assert_regex_match!(
+      TextSearchHtml {
+        query: "foo bär&".into(),
+        inscriptions: vec![inscription_id(1), inscription_id(2)],
+        total: 250,
+        prev: Some(0),
+        next: Some(2),
+      },
+      r#"
+        .*
+        <p>250 matching text inscriptions</p>
+        .*
+        <a class=prev href="/search\?q=foo\+b%C3%A4r%26&amp;page=0">prev</a>
+        <a class=next href="/search\?q=foo\+b%C3%A4r%26&amp;page=2">next</a>
+        .*
+      "#
+      .unindent()
+    );
+  }
+
+  #[test]
+  fn query_is_escaped() {
+    assert_regex_match!(
+      TextSearchHtml {
+        query: "\"><script>".into(),
+        inscriptions: Vec::new(),
+        total: 0,
+        prev: None,
+        next: None,
+      },
+      ".*value=\"&quot;&gt;&lt;script&gt;\".*<p>0 matching text inscriptions</p>.*"
+    );
+  }
+}
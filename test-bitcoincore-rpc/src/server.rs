@@ -344,10 +344,16 @@ impl Api for Server {
   ) -> Result<Value, jsonrpc_core::Error> {
     assert_eq!(blockhash, None, "Blockhash param is unsupported");
     if verbose.unwrap_or(false) {
-      match self.state().transactions.get(&txid) {
+      let state = self.state();
+      match state.transactions.get(&txid) {
         Some(_) => Ok(
           serde_json::to_value(GetRawTransactionResult {
-            in_active_chain: Some(true),
+            in_active_chain: Some(
+              state
+                .hashes
+                .iter()
+                .any(|hash| state.blocks[hash].txdata.iter().any(|tx| tx.txid() == txid)),
+            ),
             hex: Vec::new(),
             txid: Txid::all_zeros(),
             hash: Wtxid::all_zeros(),
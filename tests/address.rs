@@ -0,0 +1,67 @@
+use {super::*, ord::subcommand::address::Output};
+
+#[test]
+fn address_lists_outputs_and_inscriptions() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  rpc_server.mine_blocks(1);
+
+  let send = CommandBuilder::new(format!(
+    "wallet send --fee-rate 1 bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4 {inscription}",
+  ))
+  .rpc_server(&rpc_server)
+  .stdout_regex(r".*")
+  .run();
+
+  rpc_server.mine_blocks(1);
+
+  let output =
+    CommandBuilder::new("--index-addresses address bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+      .rpc_server(&rpc_server)
+      .output::<Vec<Output>>();
+
+  assert_eq!(output.len(), 1);
+  assert_eq!(
+    output[0].output,
+    OutPoint {
+      txid: send.trim().parse().unwrap(),
+      vout: 0,
+    }
+  );
+  assert_eq!(
+    output[0]
+      .inscriptions
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<String>>(),
+    [inscription]
+  );
+}
+
+#[test]
+fn address_requires_address_index() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("address bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: address lookup requires index created with `--index-addresses` flag\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn address_must_be_valid_for_chain() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("--index-addresses address tb1q6en7qjxgw4ev8xwx94pzdry6a6ky7wlfeqzunz")
+    .rpc_server(&rpc_server)
+    .expected_stderr(
+      "error: Address `tb1q6en7qjxgw4ev8xwx94pzdry6a6ky7wlfeqzunz` is not valid for mainnet\n",
+    )
+    .expected_exit_code(1)
+    .run();
+}
@@ -0,0 +1,1093 @@
+use {super::*, std::io::Read};
+
+#[test]
+fn refuses_to_overwrite_existing_output() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output foo.csv")
+    .write("foo.csv", "bar")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: output file `foo.csv` already exists, use `--force` to overwrite it\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn checkpoint_appends_newer_inscriptions() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+  let checkpoint = tempdir.path().join("checkpoint.json");
+
+  let command = format!(
+    "export --output {} --checkpoint {}",
+    output.display(),
+    checkpoint.display()
+  );
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  CommandBuilder::new(command.clone())
+    .rpc_server(&rpc_server)
+    .run();
+
+  assert_regex_match!(
+    fs::read_to_string(&output).unwrap(),
+    format!(
+      "hash,timestamp,text,link\n[[:xdigit:]]{{64}},1970-01-01T00:00:02\\+00:00,FOO,https://ordinals.com/inscription/{inscription}\n"
+    )
+  );
+
+  inscribe(&rpc_server);
+
+  CommandBuilder::new(command.clone())
+    .rpc_server(&rpc_server)
+    .run();
+
+  assert_eq!(fs::read_to_string(&output).unwrap().lines().count(), 2);
+
+  rpc_server.mine_blocks(1);
+
+  let Inscribe {
+    inscription: bar, ..
+  } = CommandBuilder::new("wallet inscribe bar.txt")
+    .write("bar.txt", "BAR")
+    .rpc_server(&rpc_server)
+    .output();
+
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(command.clone())
+    .rpc_server(&rpc_server)
+    .run();
+
+  assert_regex_match!(
+    fs::read_to_string(&output).unwrap(),
+    format!(
+      "hash,.*/{inscription}\n[[:xdigit:]]{{64}},.*,BAR,https://ordinals.com/inscription/{bar}\n"
+    )
+  );
+
+  CommandBuilder::new(command).rpc_server(&rpc_server).run();
+
+  assert_eq!(fs::read_to_string(&output).unwrap().lines().count(), 3);
+}
+
+#[test]
+fn checkpoint_detects_reorg() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output foo.csv --checkpoint checkpoint.json")
+    .write(
+      "checkpoint.json",
+      r#"{"number":null,"height":0,"blockhash":"1111111111111111111111111111111111111111111111111111111111111111","bytes":0}"#,
+    )
+    .write("foo.csv", "")
+    .rpc_server(&rpc_server)
+    .expected_stderr(
+      "error: reorg detected: checkpoint block 1111111111111111111111111111111111111111111111111111111111111111 at height 0 is no longer in the index, export must be rebuilt\n",
+    )
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn checkpoint_resumes_compressed_output() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv.gz");
+  let checkpoint = tempdir.path().join("checkpoint.json");
+
+  let command = format!(
+    "export --output {} --checkpoint {} --columns id --dedupe none",
+    output.display(),
+    checkpoint.display()
+  );
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  CommandBuilder::new(command.clone())
+    .rpc_server(&rpc_server)
+    .run();
+
+  let Inscribe {
+    inscription: second,
+    ..
+  } = inscribe(&rpc_server);
+
+  CommandBuilder::new(command).rpc_server(&rpc_server).run();
+
+  let mut csv = String::new();
+  flate2::read::MultiGzDecoder::new(fs::File::open(&output).unwrap())
+    .read_to_string(&mut csv)
+    .unwrap();
+
+  assert_eq!(csv, format!("id\n{inscription}\n{second}\n"));
+}
+
+#[test]
+fn checkpoint_requires_output_file() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output foo.csv --checkpoint checkpoint.json")
+    .write(
+      "checkpoint.json",
+      r#"{"number":null,"height":0,"blockhash":"1111111111111111111111111111111111111111111111111111111111111111","bytes":3}"#,
+    )
+    .rpc_server(&rpc_server)
+    .expected_stderr(
+      "error: output file `foo.csv` recorded in checkpoint is missing, export must be rebuilt\n",
+    )
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn checkpoint_requires_appendable_format() {
+  CommandBuilder::new("export --output foo.json --format json --checkpoint checkpoint.json")
+    .expected_stderr("error: `--checkpoint` requires `--format csv` or `--format jsonl`\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn empty_index_exports_header_only() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output -")
+    .rpc_server(&rpc_server)
+    .stdout_regex("hash,timestamp,text,link\n")
+    .run();
+}
+
+#[test]
+fn order_includes_lowest_inscription() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+  let Inscribe {
+    inscription: latest,
+    ..
+  } = inscribe(&rpc_server);
+
+  CommandBuilder::new("export --output - --order ascending")
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      "hash,.*\n[[:xdigit:]]{{64}},.*,FOO,https://ordinals.com/inscription/{inscription}\n"
+    ))
+    .run();
+
+  CommandBuilder::new("export --output - --order descending --keep last")
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      "hash,.*\n[[:xdigit:]]{{64}},.*,FOO,https://ordinals.com/inscription/{latest}\n"
+    ))
+    .run();
+
+  CommandBuilder::new("export --output - --order descending --from-number 0 --to-number 0")
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      "hash,.*\n[[:xdigit:]]{{64}},.*,FOO,https://ordinals.com/inscription/{inscription}\n"
+    ))
+    .run();
+}
+
+#[test]
+fn range_filters_exclude_inscriptions() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  for filter in [
+    "--from-number 1",
+    "--from-height 1000",
+    "--to-height 0",
+    "--since 2000-01-01T00:00:00Z",
+    "--until 1970-01-01T00:00:01Z",
+  ] {
+    CommandBuilder::new(format!("export --output - {filter}"))
+      .rpc_server(&rpc_server)
+      .stdout_regex("hash,timestamp,text,link\n")
+      .run();
+  }
+}
+
+#[test]
+fn checkpoint_requires_ascending_order() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output foo.csv --checkpoint checkpoint.json --order descending")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: `--checkpoint` requires `--order ascending`\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn keep_selects_copy_of_duplicates_in_either_order() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+  let Inscribe {
+    inscription: latest,
+    ..
+  } = inscribe(&rpc_server);
+
+  for (args, kept) in [
+    ("", &inscription),
+    ("--order descending", &inscription),
+    ("--order ascending --keep first", &inscription),
+    ("--order descending --keep first", &inscription),
+    ("--order ascending --keep last", &latest),
+    ("--order descending --keep last", &latest),
+  ] {
+    CommandBuilder::new(format!("export --output - {args}"))
+      .rpc_server(&rpc_server)
+      .stdout_regex(format!(
+        "hash,.*\n[[:xdigit:]]{{64}},.*,FOO,https://ordinals.com/inscription/{kept}\n"
+      ))
+      .run();
+  }
+}
+
+#[test]
+fn dedupe_none_exports_duplicates_with_duplicate_columns() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+  let Inscribe {
+    inscription: latest,
+    ..
+  } = inscribe(&rpc_server);
+
+  CommandBuilder::new(
+    "export --output - --order ascending --dedupe none --columns id,text,duplicate_of,duplicate_count",
+  )
+  .rpc_server(&rpc_server)
+  .stdout_regex(format!(
+    "id,text,duplicate_of,duplicate_count
+{inscription},FOO,{inscription},2
+{latest},FOO,{inscription},2
+"
+  ))
+    .run();
+}
+
+#[test]
+fn columns_select_index_fields() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe {
+    inscription,
+    reveal,
+    ..
+  } = inscribe(&rpc_server);
+
+  CommandBuilder::new(
+    "export --output - --format jsonl --columns number,id,height,sat,satpoint,content_type,content_length,text",
+  )
+  .rpc_server(&rpc_server)
+  .stdout_regex(format!(
+    r#"\{{"number":0,"id":"{inscription}","height":2,"sat":null,"satpoint":"{reveal}:0:0","content_type":"text/plain;charset=utf-8","content_length":3,"text":"FOO"\}}
+"#
+  ))
+  .run();
+}
+
+#[test]
+fn columns_must_be_unique() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output - --columns id,text,id")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: column `id` selected more than once\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn link_base_and_route() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  CommandBuilder::new(
+    "export --output - --columns link --link-base http://example.com:8080/ --link-route content",
+  )
+  .rpc_server(&rpc_server)
+  .stdout_regex(format!(
+    "link\nhttp://example.com:8080/content/{inscription}\n"
+  ))
+  .run();
+}
+
+#[test]
+fn digest_columns() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  CommandBuilder::new("export --output - --columns text,hash --digest sha256,sha3-256")
+    .rpc_server(&rpc_server)
+    .stdout_regex(
+      "text,sha256,sha3_256\nFOO,9520437ce8902eb379a7d8aaa98fc4c94eeb07b6684854868fa6f72bf34b0fd3,[[:xdigit:]]{64}\n",
+    )
+    .run();
+}
+
+#[test]
+fn follow_appends_new_inscriptions_until_interrupted() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+  let checkpoint = tempdir.path().join("checkpoint.json");
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let builder = CommandBuilder::new(format!(
+    "export --output {} --checkpoint {} --follow --poll-interval 1 --columns id --dedupe none",
+    output.display(),
+    checkpoint.display()
+  ))
+  .rpc_server(&rpc_server);
+
+  let child = builder.command().spawn().unwrap();
+
+  let wait_for = |expected: &str| {
+    for attempt in 0.. {
+      if fs::read_to_string(&output).unwrap_or_default() == expected {
+        break;
+      }
+
+      if attempt == 100 {
+        panic!(
+          "export did not contain:\n{expected}\nfound:\n{}",
+          fs::read_to_string(&output).unwrap_or_default()
+        );
+      }
+
+      thread::sleep(Duration::from_millis(100));
+    }
+  };
+
+  wait_for(&format!("id\n{inscription}\n"));
+
+  let Inscribe {
+    inscription: second,
+    ..
+  } = inscribe(&rpc_server);
+
+  wait_for(&format!("id\n{inscription}\n{second}\n"));
+
+  assert!(Command::new("kill")
+    .args(["-INT", &child.id().to_string()])
+    .status()
+    .unwrap()
+    .success());
+
+  let output = child.wait_with_output().unwrap();
+
+  assert!(
+    output.status.success(),
+    "{}",
+    str::from_utf8(&output.stderr).unwrap()
+  );
+}
+
+#[test]
+fn follow_stops_when_exported_blocks_are_reorged() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let builder = CommandBuilder::new(format!(
+    "export --output {} --follow --poll-interval 1 --columns id --dedupe none",
+    output.display(),
+  ))
+  .rpc_server(&rpc_server);
+
+  let child = builder.command().spawn().unwrap();
+
+  for attempt in 0.. {
+    if fs::read_to_string(&output).unwrap_or_default() == format!("id\n{inscription}\n") {
+      break;
+    }
+
+    assert!(attempt < 100, "export did not contain {inscription}");
+
+    thread::sleep(Duration::from_millis(100));
+  }
+
+  rpc_server.invalidate_tip();
+  rpc_server.mine_blocks(2);
+
+  let output = child.wait_with_output().unwrap();
+
+  assert_eq!(output.status.code(), Some(1));
+  assert_regex_match!(
+    str::from_utf8(&output.stderr).unwrap(),
+    "error: reorg detected: exported block [[:xdigit:]]{64} at height 2 is no longer in the index, export must be rebuilt\n"
+  );
+}
+
+#[test]
+fn follow_requires_streaming_dedupe() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output - --follow --keep last")
+    .rpc_server(&rpc_server)
+    .expected_stderr(
+      "error: `--follow` cannot be combined with `--keep last` or duplicate columns\n",
+    )
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn compression_is_inferred_from_extension() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+
+  CommandBuilder::new(format!(
+    "export --output {} --columns id",
+    tempdir.path().join("export.csv.gz").display()
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  let mut csv = String::new();
+  flate2::read::GzDecoder::new(fs::File::open(tempdir.path().join("export.csv.gz")).unwrap())
+    .read_to_string(&mut csv)
+    .unwrap();
+  assert_eq!(csv, format!("id\n{inscription}\n"));
+
+  CommandBuilder::new(format!(
+    "export --output {} --columns id --compress zstd",
+    tempdir.path().join("export.csv").display()
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  assert_eq!(
+    zstd::decode_all(fs::File::open(tempdir.path().join("export.csv")).unwrap()).unwrap(),
+    format!("id\n{inscription}\n").as_bytes()
+  );
+}
+
+#[test]
+fn split_by_rows_writes_shards_with_headers() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+  let Inscribe {
+    inscription: second,
+    ..
+  } = inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+
+  CommandBuilder::new(format!(
+    "export --output {} --columns id --order ascending --dedupe none --split-by rows:1",
+    tempdir.path().join("export.csv").display()
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("export-00000.csv")).unwrap(),
+    format!("id\n{inscription}\n")
+  );
+
+  assert_eq!(
+    fs::read_to_string(tempdir.path().join("export-00001.csv")).unwrap(),
+    format!("id\n{second}\n")
+  );
+
+  assert!(!tempdir.path().join("export-00002.csv").exists());
+  assert!(!tempdir.path().join("export.csv").exists());
+}
+
+#[test]
+fn split_by_requires_output_file() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --output - --split-by rows:1")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: `--split-by` cannot be used when writing to stdout\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn manifest_records_tip_and_integrity_metadata() {
+  use bitcoin::hashes::{sha256, Hash};
+
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+
+  for name in ["export.csv", "export.jsonl"] {
+    CommandBuilder::new(format!(
+      "export --output {} --format {}",
+      tempdir.path().join(name).display(),
+      name.rsplit('.').next().unwrap(),
+    ))
+    .rpc_server(&rpc_server)
+    .run();
+  }
+
+  let manifest = |name: &str| {
+    serde_json::from_slice::<serde_json::Value>(
+      &fs::read(tempdir.path().join(format!("{name}.manifest.json"))).unwrap(),
+    )
+    .unwrap()
+  };
+
+  let csv = manifest("export.csv");
+
+  assert_eq!(csv["chain"], "mainnet");
+  assert!(csv["schema_version"].is_u64());
+  assert_eq!(csv["height"], 2);
+  assert_regex_match!(csv["blockhash"].as_str().unwrap(), "[[:xdigit:]]{64}");
+  assert_eq!(csv["rows"], 1);
+  assert_eq!(csv["filters"]["media"], serde_json::json!(["text"]));
+  assert_eq!(csv["filters"]["order"], "ascending");
+  assert_eq!(csv["files"][0]["path"], "export.csv");
+  assert_eq!(
+    csv["files"][0]["sha256"],
+    sha256::Hash::hash(&fs::read(tempdir.path().join("export.csv")).unwrap()).to_string()
+  );
+
+  let jsonl = manifest("export.jsonl");
+
+  assert_eq!(jsonl["files"][0]["path"], "export.jsonl");
+  assert_ne!(jsonl["files"][0]["sha256"], csv["files"][0]["sha256"]);
+  assert_eq!(jsonl["merkle_root"], csv["merkle_root"]);
+  assert!(csv["merkle_root"].is_string());
+}
+
+#[test]
+fn manifest_covers_every_shard() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+  inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+
+  CommandBuilder::new(format!(
+    "export --output {} --dedupe none --split-by rows:1 --manifest {}",
+    tempdir.path().join("export.csv").display(),
+    tempdir.path().join("manifest.json").display(),
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  let manifest = serde_json::from_slice::<serde_json::Value>(
+    &fs::read(tempdir.path().join("manifest.json")).unwrap(),
+  )
+  .unwrap();
+
+  assert_eq!(manifest["rows"], 2);
+  assert_eq!(manifest["files"][0]["path"], "export-00000.csv");
+  assert_eq!(manifest["files"][1]["path"], "export-00001.csv");
+  assert!(!tempdir.path().join("export.csv.manifest.json").exists());
+}
+
+#[test]
+fn verify_accepts_unmodified_export() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.jsonl.gz");
+
+  CommandBuilder::new(format!(
+    "export --output {} --format jsonl --digest sha256,sha3-512",
+    output.display()
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  CommandBuilder::new(format!("export verify {}", output.display()))
+    .rpc_server(&rpc_server)
+    .stdout_regex(
+      r#"\{
+  "rows": 1,
+  "verified": 1,
+  "missing": \[\],
+  "altered": \[\],
+  "reorged": \[\],
+  "manifest": \[\]
+\}
+"#,
+    )
+    .run();
+}
+
+#[test]
+fn verify_accepts_every_column() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+  inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+
+  CommandBuilder::new(format!(
+    "export --output {} --dedupe none --columns number,id,hash,timestamp,height,fee,sat,sat_name,rarity,satpoint,owner,media,content_type,content_length,utf8,structure,text,body,path,link,duplicate_of,duplicate_count",
+    output.display()
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  CommandBuilder::new(format!("export verify {}", output.display()))
+    .rpc_server(&rpc_server)
+    .stdout_regex(r#".*"rows": 2,\s*"verified": 2,.*"manifest": \[\]\s*\}\s*"#)
+    .run();
+}
+
+#[test]
+fn verify_detects_edited_text() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+
+  CommandBuilder::new(format!("export --output {}", output.display()))
+    .rpc_server(&rpc_server)
+    .run();
+
+  fs::write(
+    &output,
+    fs::read_to_string(&output)
+      .unwrap()
+      .replace(",FOO,", ",BAR,"),
+  )
+  .unwrap();
+
+  CommandBuilder::new(format!("export verify {}", output.display()))
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      r#".*"altered": \[
+    \{{
+      "row": 1,
+      "inscription": "{inscription}",
+      "columns": \[
+        "text"
+      \]
+    \}}
+  \],
+  "reorged": \[\],
+  "manifest": \[
+    "export.csv",
+    "merkle_root"
+  \]
+\}}
+"#
+    ))
+    .expected_stderr("error: 1 of 1 rows failed verification: 0 missing, 1 altered, 0 reorged\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn verify_detects_edited_manifest() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+  let manifest = tempdir.path().join("export.csv.manifest.json");
+
+  CommandBuilder::new(format!("export --output {}", output.display()))
+    .rpc_server(&rpc_server)
+    .run();
+
+  let mut json =
+    serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&manifest).unwrap()).unwrap();
+
+  json["rows"] = 2.into();
+  json["files"][0]["sha256"] =
+    "0000000000000000000000000000000000000000000000000000000000000000".into();
+  json["merkle_root"] = "0000000000000000000000000000000000000000000000000000000000000000".into();
+
+  fs::write(&manifest, json.to_string()).unwrap();
+
+  CommandBuilder::new(format!("export verify {}", output.display()))
+    .rpc_server(&rpc_server)
+    .stdout_regex(
+      r#".*"verified": 1,.*"manifest": \[
+    "export.csv",
+    "rows",
+    "merkle_root"
+  \]
+\}
+"#,
+    )
+    .expected_stderr("error: export does not match manifest: export.csv, rows, merkle_root\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn verify_reports_missing_and_altered_rows() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let missing = "1111111111111111111111111111111111111111111111111111111111111111i0";
+
+  CommandBuilder::new("export verify export.csv")
+    .write(
+      "export.csv",
+      format!(
+        "id,hash\n{inscription},0000\n{missing},0000\n{inscription},{}\n",
+        "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+      ),
+    )
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      r#".*"rows": 3,
+  "verified": 0,
+  "missing": \[
+    \{{
+      "row": 2,
+      "inscription": "{missing}",
+      "columns": \[\]
+    \}}
+  \],
+  "altered": \[
+    \{{
+      "row": 1,
+      "inscription": "{inscription}",
+      "columns": \[
+        "hash"
+      \]
+    \}},
+    \{{
+      "row": 3,.*"#
+    ))
+    .expected_stderr("error: 3 of 3 rows failed verification: 1 missing, 2 altered, 0 reorged\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn verify_reports_reorged_rows() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  CommandBuilder::new("export verify export.csv")
+    .write("export.csv", format!("id,height\n{inscription},7\n"))
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      r#".*"reorged": \[
+    \{{
+      "row": 1,
+      "inscription": "{inscription}",
+      "columns": \[
+        "height"
+      \]
+    \}}
+  \],
+  "manifest": \[\]
+\}}
+"#
+    ))
+    .expected_stderr("error: 1 of 1 rows failed verification: 0 missing, 0 altered, 1 reorged\n")
+    .expected_exit_code(1)
+    .run();
+}
+
+#[test]
+fn index_content_exports_without_bitcoin_core() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let index = tempdir.path().join("index.redb");
+
+  CommandBuilder::new(format!(
+    "--index-content --index {} export --output - --columns id,text",
+    index.display()
+  ))
+  .rpc_server(&rpc_server)
+  .stdout_regex(format!("id,text\n{inscription},FOO\n"))
+  .run();
+
+  CommandBuilder::new(format!(
+    "--rpc-url 127.0.0.1:1 --cookie-file {} --index {} export --output - --columns id,text",
+    tempdir.path().join("missing").display(),
+    index.display()
+  ))
+  .stdout_regex(format!("id,text\n{inscription},FOO\n"))
+  .stderr_regex("warning: failed to update index, exporting indexed inscriptions: .*\n")
+  .run();
+}
+
+#[test]
+fn jobs_preserve_inscription_number_order() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let inscriptions = (0..4)
+    .map(|_| inscribe(&rpc_server).inscription)
+    .collect::<Vec<String>>();
+
+  for jobs in [1, 3] {
+    CommandBuilder::new(format!(
+      "export --output - --order ascending --dedupe none --columns id --jobs {jobs}"
+    ))
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!("id\n{}\n", inscriptions.join("\n")))
+    .run();
+  }
+}
+
+#[test]
+fn jobs_must_be_positive() {
+  CommandBuilder::new("export --output - --jobs 0")
+    .stderr_regex(".*Invalid value \"0\" for '--jobs <JOBS>'.*")
+    .expected_exit_code(2)
+    .run();
+}
+
+#[test]
+fn invalid_utf8_bodies_can_be_skipped_or_quarantined() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  rpc_server.mine_blocks(1);
+
+  let Inscribe {
+    inscription: invalid,
+    ..
+  } = CommandBuilder::new("wallet inscribe foo.txt")
+    .write("foo.txt", b"\xffFOO")
+    .rpc_server(&rpc_server)
+    .output();
+
+  rpc_server.mine_blocks(1);
+
+  let command = "export --output - --order ascending --columns id,content_type,utf8,structure,text,body --invalid-utf8";
+
+  CommandBuilder::new(format!("{command} replace"))
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      "id,content_type,utf8,structure,text,body
+{inscription},text/plain;charset=utf-8,true,single_line,FOO,
+{invalid},text/plain;charset=utf-8,false,single_line,\u{fffd}FOO,
+"
+    ))
+    .run();
+
+  CommandBuilder::new(format!("{command} skip"))
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      "id,content_type,utf8,structure,text,body
+{inscription},text/plain;charset=utf-8,true,single_line,FOO,
+"
+    ))
+    .run();
+
+  CommandBuilder::new(format!("{command} quarantine"))
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      "id,content_type,utf8,structure,text,body
+{inscription},text/plain;charset=utf-8,true,single_line,FOO,
+{invalid},text/plain;charset=utf-8,false,single_line,,/0ZPTw==
+"
+    ))
+    .run();
+}
+
+#[test]
+fn text_filters_select_matching_inscriptions() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new("wallet inscribe foo.txt")
+    .write("foo.txt", "see ordinals.com")
+    .rpc_server(&rpc_server)
+    .output::<Inscribe>();
+
+  rpc_server.mine_blocks(1);
+
+  let command = "export --output - --order ascending --columns text";
+
+  CommandBuilder::new(format!("{command} --match foo"))
+    .rpc_server(&rpc_server)
+    .stdout_regex("text\n")
+    .run();
+
+  CommandBuilder::new(format!("{command} --match foo --ignore-case"))
+    .rpc_server(&rpc_server)
+    .stdout_regex("text\nFOO\n")
+    .run();
+
+  CommandBuilder::new(format!("{command} --match [[:alpha:]]+\\.com$"))
+    .rpc_server(&rpc_server)
+    .stdout_regex("text\nsee ordinals.com\n")
+    .run();
+
+  CommandBuilder::new(format!("{command} --min-length 4"))
+    .rpc_server(&rpc_server)
+    .stdout_regex("text\nsee ordinals.com\n")
+    .run();
+
+  CommandBuilder::new(format!("{command} --max-length 3"))
+    .rpc_server(&rpc_server)
+    .stdout_regex("text\nFOO\n")
+    .run();
+
+  let tempdir = TempDir::new().unwrap();
+  let output = tempdir.path().join("export.csv");
+
+  CommandBuilder::new(format!(
+    "export --output {} --match ordinals --ignore-case --min-length 1",
+    output.display()
+  ))
+  .rpc_server(&rpc_server)
+  .run();
+
+  let manifest = serde_json::from_slice::<serde_json::Value>(
+    &fs::read(tempdir.path().join("export.csv.manifest.json")).unwrap(),
+  )
+  .unwrap();
+
+  assert_eq!(manifest["rows"], 1);
+  assert_eq!(manifest["filters"]["match"], "ordinals");
+  assert_eq!(manifest["filters"]["ignore_case"], true);
+  assert_eq!(manifest["filters"]["min_length"], 1);
+  assert_eq!(manifest["filters"]["max_length"], serde_json::Value::Null);
+}
+
+#[test]
+fn transfers_export_ledger_of_inscription_moves() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let Inscribe {
+    inscription,
+    reveal,
+    ..
+  } = inscribe(&rpc_server);
+
+  rpc_server.mine_blocks(1);
+
+  let send = CommandBuilder::new(format!(
+    "wallet send --fee-rate 1 bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4 {inscription}",
+  ))
+  .rpc_server(&rpc_server)
+  .stdout_regex(r".*")
+  .run();
+
+  rpc_server.mine_blocks(1);
+
+  let send = send.trim();
+
+  CommandBuilder::new("export --transfers --format jsonl --output -")
+    .rpc_server(&rpc_server)
+    .stdout_regex(format!(
+      r#"\{{"number":0,"id":"{inscription}","height":\d+,"timestamp":"[^"]+","txid":"{send}","old_satpoint":"{reveal}:0:0","new_satpoint":"{send}:0:0","lost":false\}}
+"#
+    ))
+    .run();
+}
+
+#[test]
+fn transfers_conflict_with_inscription_options() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("export --transfers --media all")
+    .rpc_server(&rpc_server)
+    .stderr_regex("error: The argument '--transfers' cannot be used with .*")
+    .expected_exit_code(2)
+    .run();
+}
+
+#[test]
+fn owner_column_is_address_of_inscription_output() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+  rpc_server.mine_blocks(1);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  rpc_server.mine_blocks(1);
+
+  CommandBuilder::new(format!(
+    "wallet send --fee-rate 1 bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4 {inscription}",
+  ))
+  .rpc_server(&rpc_server)
+  .stdout_regex(r".*")
+  .run();
+
+  rpc_server.mine_blocks(1);
+
+  assert_eq!(
+    CommandBuilder::new("--index-addresses export --columns id,owner --format jsonl --output -")
+      .rpc_server(&rpc_server)
+      .stdout_regex(".*")
+      .run(),
+    format!(
+      "{{\"id\":\"{inscription}\",\"owner\":\"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4\"}}\n"
+    ),
+  );
+
+  assert_eq!(
+    CommandBuilder::new("export --columns id,owner --format jsonl --output -")
+      .rpc_server(&rpc_server)
+      .stdout_regex(".*")
+      .run(),
+    format!("{{\"id\":\"{inscription}\",\"owner\":null}}\n"),
+  );
+}
@@ -0,0 +1,48 @@
+use {super::*, ord::subcommand::find_content::Output};
+
+#[test]
+fn find_content_returns_inscriptions_with_same_content() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let Inscribe { inscription, .. } = inscribe(&rpc_server);
+
+  let output = CommandBuilder::new("find-content foo.txt")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .output::<Output>();
+
+  assert_eq!(
+    output.content_hash.to_string(),
+    "9520437ce8902eb379a7d8aaa98fc4c94eeb07b6684854868fa6f72bf34b0fd3"
+  );
+  assert_eq!(output.inscriptions.len(), 1);
+  assert_eq!(output.inscriptions[0].inscription.to_string(), inscription);
+  assert_eq!(output.inscriptions[0].number, 0);
+}
+
+#[test]
+fn find_content_returns_nothing_for_uninscribed_content() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  inscribe(&rpc_server);
+
+  let output = CommandBuilder::new("find-content bar.txt")
+    .write("bar.txt", "BAR")
+    .rpc_server(&rpc_server)
+    .output::<Output>();
+
+  assert!(output.inscriptions.is_empty());
+}
+
+#[test]
+fn find_content_requires_file() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("find-content missing.txt")
+    .rpc_server(&rpc_server)
+    .stderr_regex("error: I/O error reading `missing.txt`\nbecause: .*\n")
+    .expected_exit_code(1)
+    .run();
+}
@@ -35,3 +35,108 @@ fn re_opening_database_does_not_trigger_schema_check() {
     .rpc_server(&rpc_server)
     .run();
 }
+
+#[test]
+fn block_files_are_indexed_without_bitcoin_core() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let mut blocks = vec![bitcoin::blockdata::constants::genesis_block(
+    Network::Bitcoin,
+  )];
+
+  blocks.extend(rpc_server.mine_blocks(1));
+
+  let Inscribe { inscription, .. } = CommandBuilder::new("wallet inscribe foo.txt")
+    .write("foo.txt", "FOO")
+    .rpc_server(&rpc_server)
+    .output();
+
+  blocks.extend(rpc_server.mine_blocks(1));
+
+  drop(rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let blocks_dir = tempdir.path().join("blocks");
+  let index_dir = blocks_dir.join("index");
+  fs::create_dir_all(&index_dir).unwrap();
+
+  // Bitcoin Core's base-128 varint
+  let varint = |mut n: u64| {
+    let mut bytes = vec![u8::try_from(n & 0x7f).unwrap()];
+    while n > 0x7f {
+      n = (n >> 7) - 1;
+      bytes.push(u8::try_from(n & 0x7f).unwrap() | 0x80);
+    }
+    bytes.reverse();
+    bytes
+  };
+
+  let mut blk = Vec::new();
+  let mut batch = 1u64.to_le_bytes().to_vec();
+  batch.extend(u32::try_from(blocks.len()).unwrap().to_le_bytes());
+
+  // The mock's blocks have no work, so they are relinked with the genesis
+  // block's difficulty to be followed as the chain with the most work.
+  for i in 0..blocks.len() {
+    if i > 0 {
+      blocks[i].header.bits = blocks[0].header.bits;
+      blocks[i].header.prev_blockhash = blocks[i - 1].block_hash();
+    }
+
+    let block = bitcoin::consensus::serialize(&blocks[i]);
+    blk.extend(Network::Bitcoin.magic().to_le_bytes());
+    blk.extend(u32::try_from(block.len()).unwrap().to_le_bytes());
+
+    // a block index entry for a fully validated block stored in blk00000.dat
+    let mut value = [
+      varint(250000),
+      varint(i as u64),
+      varint(8 | 5),
+      varint(blocks[i].txdata.len() as u64),
+      varint(0),
+      varint(blk.len() as u64),
+    ]
+    .concat();
+    value.extend(bitcoin::consensus::serialize(&blocks[i].header));
+
+    batch.push(1);
+    batch.push(33);
+    batch.push(b'b');
+    batch.extend(&blocks[i].block_hash()[..]);
+    batch.push(value.len().try_into().unwrap());
+    batch.extend(value);
+
+    blk.extend(block);
+  }
+
+  fs::write(blocks_dir.join("blk00000.dat"), blk).unwrap();
+
+  let mut log = vec![0, 0, 0, 0];
+  log.extend(u16::try_from(batch.len()).unwrap().to_le_bytes());
+  log.push(1);
+  log.extend(batch);
+
+  fs::write(index_dir.join("CURRENT"), "MANIFEST-000001\n").unwrap();
+  fs::write(
+    index_dir.join("MANIFEST-000001"),
+    [0, 0, 0, 0, 2, 0, 1, 2, 2],
+  )
+  .unwrap();
+  fs::write(index_dir.join("000002.log"), log).unwrap();
+
+  let options = format!(
+    "--block-source files --bitcoin-data-dir {} --rpc-url 127.0.0.1:1 --cookie-file {} --index {} --index-content",
+    tempdir.path().display(),
+    tempdir.path().join("missing").display(),
+    tempdir.path().join("index.redb").display(),
+  );
+
+  CommandBuilder::new(format!("{options} index")).run();
+
+  CommandBuilder::new(format!(
+    "{options} export --output - --columns height,id,text"
+  ))
+  .stdout_regex(format!("height,id,text\n2,{inscription},FOO\n"))
+  .run();
+}
@@ -69,15 +69,19 @@ fn create_wallet(rpc_server: &test_bitcoincore_rpc::Handle) {
     .output::<Create>();
 }
 
+mod address;
 mod command_builder;
 mod core;
 mod epochs;
 mod expected;
+mod export;
 mod find;
+mod find_content;
 mod index;
 mod info;
 mod list;
 mod parse;
+mod search_text;
 mod server;
 mod subsidy;
 mod supply;
@@ -0,0 +1,67 @@
+use {super::*, ord::subcommand::search_text::Output};
+
+#[test]
+fn search_text_returns_ranked_inscriptions() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+  create_wallet(&rpc_server);
+
+  let tempdir = TempDir::new().unwrap();
+  let index = tempdir.path().join("index.redb");
+
+  let mut inscriptions = Vec::new();
+
+  for body in ["foo bar", "foo foo", "baz"] {
+    rpc_server.mine_blocks(1);
+
+    inscriptions.push(
+      CommandBuilder::new(format!(
+        "--index-text --index {} wallet inscribe foo.txt",
+        index.display()
+      ))
+      .write("foo.txt", body)
+      .rpc_server(&rpc_server)
+      .output::<Inscribe>()
+      .inscription,
+    );
+  }
+
+  rpc_server.mine_blocks(1);
+
+  let output = CommandBuilder::new(format!("--index {} search-text FOO bar", index.display()))
+    .rpc_server(&rpc_server)
+    .output::<Output>();
+
+  assert_eq!(output.total, 2);
+  assert!(!output.more);
+  assert_eq!(
+    output
+      .matches
+      .iter()
+      .map(|text_match| text_match.inscription.to_string())
+      .collect::<Vec<String>>(),
+    [inscriptions[0].clone(), inscriptions[1].clone()]
+  );
+  assert_eq!(output.matches[0].number, 0);
+
+  let output = CommandBuilder::new(format!(
+    "--index {} search-text foo --page 1 --limit 1",
+    index.display()
+  ))
+  .rpc_server(&rpc_server)
+  .output::<Output>();
+
+  assert_eq!(output.page, 1);
+  assert!(!output.more);
+  assert_eq!(output.matches.len(), 1);
+}
+
+#[test]
+fn search_text_requires_text_index() {
+  let rpc_server = test_bitcoincore_rpc::spawn();
+
+  CommandBuilder::new("search-text foo")
+    .rpc_server(&rpc_server)
+    .expected_stderr("error: full-text search requires index created with `--index-text` flag\n")
+    .expected_exit_code(1)
+    .run();
+}